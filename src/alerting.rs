@@ -0,0 +1,449 @@
+// lwas_economy/src/payments/alerting.rs
+// ARCHITECT: QANTUM AETERNA | STATUS: BETA
+// Ops alerting: rules over webhook outcomes, posted to a Slack/Discord-compatible chat webhook
+
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::event_archive::Provider;
+use crate::http_client::{OutboundClient, OutboundConfig};
+use crate::metrics::metrics;
+use crate::runtime::env_u64;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// CONFIGURATION
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Debug)]
+pub struct AlertConfig {
+    /// Incoming-webhook URL of the ops channel; alerts are only logged without one
+    pub webhook_url: Option<String>,
+    /// Sliding window the failure thresholds are counted over
+    pub window: Duration,
+    pub signature_failure_threshold: usize,
+    /// Distinct failing events (not attempts) within `window`
+    pub processing_error_threshold: usize,
+    /// Alert when no webhook at all arrived for this long (`ALERT_SILENCE_SECS=0` disables)
+    pub silence: Option<Duration>,
+    /// Minimum time between two alerts of the same threshold rule
+    pub cooldown: Duration,
+    /// How often the scheduler checks for silence
+    pub check_interval: Duration,
+}
+
+impl AlertConfig {
+    pub fn from_env() -> Self {
+        let silence = env_u64("ALERT_SILENCE_SECS", 6 * 3600);
+        Self {
+            webhook_url: std::env::var("ALERT_WEBHOOK_URL").ok().filter(|s| !s.is_empty()),
+            window: Duration::from_secs(env_u64("ALERT_WINDOW_SECS", 300)),
+            signature_failure_threshold: env_u64("ALERT_SIGNATURE_FAILURES", 10).max(1) as usize,
+            processing_error_threshold: env_u64("ALERT_PROCESSING_ERRORS", 5).max(1) as usize,
+            silence: (silence > 0).then(|| Duration::from_secs(silence)),
+            cooldown: Duration::from_secs(env_u64("ALERT_COOLDOWN_SECS", 1800)),
+            check_interval: Duration::from_secs(env_u64("ALERT_CHECK_INTERVAL_SECS", 60)),
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// ALERTS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AlertRule {
+    SignatureFailures,
+    ProcessingErrors,
+    DisputeOpened,
+    DisputeDeadline,
+    WebhookSilence,
+}
+
+impl AlertRule {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertRule::SignatureFailures => "signature_failures",
+            AlertRule::ProcessingErrors => "processing_errors",
+            AlertRule::DisputeOpened => "dispute_opened",
+            AlertRule::DisputeDeadline => "dispute_deadline",
+            AlertRule::WebhookSilence => "webhook_silence",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Alert {
+    pub rule: AlertRule,
+    pub message: String,
+}
+
+/// Slack reads `text`, Discord reads `content`; each ignores the other
+#[derive(Serialize)]
+struct ChatMessage<'a> {
+    text: &'a str,
+    content: &'a str,
+}
+
+fn provider_name(provider: Provider) -> &'static str {
+    match provider {
+        Provider::Stripe => "Stripe",
+        Provider::PayPal => "PayPal",
+    }
+}
+
+fn minutes(duration: Duration) -> u64 {
+    (duration.as_secs() / 60).max(1)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// ALERTER
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Latest verified delivery on any replica (unix seconds)
+const LAST_RECEIVED_KEY: &str = "alerts:last_webhook_received";
+/// Start of the silent stretch last alerted for, so one alert goes out per stretch
+const SILENCE_ALERTED_KEY: &str = "alerts:silence_alerted_since";
+
+#[derive(Default)]
+struct AlertWindow {
+    signature_failures: VecDeque<(DateTime<Utc>, Option<String>)>,
+    /// With the failing event id: retries of one event count once
+    processing_errors: VecDeque<(DateTime<Utc>, Option<String>)>,
+    /// Last verified delivery, or when watching started
+    last_received: Option<DateTime<Utc>>,
+    /// Start of the silent stretch last alerted for
+    silence_alerted_since: Option<DateTime<Utc>>,
+    last_fired: HashMap<AlertRule, DateTime<Utc>>,
+}
+
+/// Threshold counts are per process, like the metrics registry. The silence rule is
+/// shared through Redis when configured: deliveries may land on any replica, but only
+/// the scheduler's leader checks for silence.
+#[derive(Clone)]
+pub struct Alerter {
+    pub config: AlertConfig,
+    http: OutboundClient,
+    redis_client: Option<redis::Client>,
+    window: Arc<Mutex<AlertWindow>>,
+}
+
+impl Alerter {
+    pub fn new(config: AlertConfig, redis_url: Option<String>) -> Self {
+        if config.webhook_url.is_none() {
+            println!("⚠️ ALERT_WEBHOOK_URL not set: ops alerts are only logged");
+        }
+        let redis_client = redis_url.and_then(|url| {
+            redis::Client::open(url).map_err(|e| println!("❌ Redis connect error: {}", e)).ok()
+        });
+        Self {
+            config,
+            http: OutboundClient::new("alerts", OutboundConfig::from_env()),
+            redis_client,
+            window: Arc::new(Mutex::new(AlertWindow::default())),
+        }
+    }
+
+    /// A delivery passed signature verification
+    pub fn webhook_received(&self, now: DateTime<Utc>) {
+        {
+            let mut window = self.window.lock().unwrap();
+            window.last_received = Some(now);
+            if window.silence_alerted_since.take().is_some() {
+                println!("[ALERT] ✅ Webhooks are arriving again");
+            }
+        }
+
+        if let Some(client) = self.redis_client.clone() {
+            tokio::spawn(async move {
+                let written: Result<(), String> = async {
+                    let mut con = client.get_multiplexed_async_connection().await.map_err(|e| e.to_string())?;
+                    con.set(LAST_RECEIVED_KEY, now.timestamp()).await.map_err(|e| e.to_string())
+                }
+                .await;
+                if let Err(e) = written {
+                    println!("[ALERT] ⚠️ Could not share last delivery time: {}", e);
+                }
+            });
+        }
+    }
+
+    pub fn signature_failed(&self, provider: Provider, now: DateTime<Utc>) -> Option<Alert> {
+        let threshold = self.config.signature_failure_threshold;
+        let count = self.count(AlertRule::SignatureFailures, None, now)?;
+        (count >= threshold)
+            .then(|| {
+                format!(
+                    "🚨 Webhook signature failures: {} in the last {} min (threshold {}), latest from {}. Rotated secret or spoofed traffic?",
+                    count,
+                    minutes(self.config.window),
+                    threshold,
+                    provider_name(provider)
+                )
+            })
+            .and_then(|message| self.fire_throttled(AlertRule::SignatureFailures, message, now))
+    }
+
+    pub fn processing_failed(&self, event_id: &str, event_type: &str, error: &str, now: DateTime<Utc>) -> Option<Alert> {
+        let threshold = self.config.processing_error_threshold;
+        let count = self.count(AlertRule::ProcessingErrors, Some(event_id), now)?;
+        (count >= threshold)
+            .then(|| {
+                format!(
+                    "🚨 Webhook processing errors: {} events failing in the last {} min (threshold {}). Latest: {} ({}): {}",
+                    count,
+                    minutes(self.config.window),
+                    threshold,
+                    event_id,
+                    event_type,
+                    error
+                )
+            })
+            .and_then(|message| self.fire_throttled(AlertRule::ProcessingErrors, message, now))
+    }
+
+    /// Every new dispute is worth a look: evidence deadlines are short
+    pub fn dispute_opened(
+        &self,
+        provider: Provider,
+        dispute_id: &str,
+        amount: &str,
+        reason: &str,
+        customer: Option<&str>,
+    ) -> Alert {
+        self.fire(Alert {
+            rule: AlertRule::DisputeOpened,
+            message: format!(
+                "⚖️ New {} dispute {}: {} ({}), customer {}",
+                provider_name(provider),
+                dispute_id,
+                amount,
+                reason,
+                customer.unwrap_or("unknown")
+            ),
+        })
+    }
+
+    /// Evidence (Stripe) or a seller response (PayPal) is due within `hours`; the dispute
+    /// stores make sure each threshold fires once per dispute
+    pub fn dispute_deadline(
+        &self,
+        provider: Provider,
+        dispute_id: &str,
+        amount: &str,
+        customer: Option<&str>,
+        due_by: DateTime<Utc>,
+        hours: u64,
+    ) -> Alert {
+        self.fire(Alert {
+            rule: AlertRule::DisputeDeadline,
+            message: format!(
+                "⏰ {} dispute {} ({}, customer {}): response due {}, less than {}h left",
+                provider_name(provider),
+                dispute_id,
+                amount,
+                customer.unwrap_or("unknown"),
+                due_by.to_rfc3339(),
+                hours
+            ),
+        })
+    }
+
+    /// Silence rule, run by the scheduler: fires once per silent stretch
+    pub async fn check_silence(&self, now: DateTime<Utc>) -> Option<Alert> {
+        let silence = self.config.silence?;
+        let threshold = chrono::Duration::from_std(silence).ok()?;
+        let since = match self.last_received(now).await {
+            Ok(since) => since,
+            Err(e) => {
+                // Better no alert than one raised on this replica's partial view
+                println!("[ALERT] ⚠️ Silence check skipped: {}", e);
+                return None;
+            }
+        };
+        if now - since < threshold || !self.claim_silence_alert(since).await {
+            return None;
+        }
+        self.window.lock().unwrap().silence_alerted_since = Some(since);
+
+        Some(self.fire(Alert {
+            rule: AlertRule::WebhookSilence,
+            message: format!(
+                "🔇 No webhooks received since {} ({} min). Check the provider dashboards and endpoint health.",
+                since.to_rfc3339(),
+                minutes(silence)
+            ),
+        }))
+    }
+
+    /// Latest delivery on any replica; the clock starts at `now` if none was seen yet
+    async fn last_received(&self, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+        let Some(client) = &self.redis_client else {
+            return Ok(*self.window.lock().unwrap().last_received.get_or_insert(now));
+        };
+
+        let redis_err = |e: redis::RedisError| format!("Redis error: {}", e);
+        let mut con = client.get_multiplexed_async_connection().await.map_err(redis_err)?;
+        let _: Option<String> = redis::cmd("SET")
+            .arg(LAST_RECEIVED_KEY)
+            .arg(now.timestamp())
+            .arg("NX")
+            .query_async(&mut con)
+            .await
+            .map_err(redis_err)?;
+        let ts: i64 = con.get(LAST_RECEIVED_KEY).await.map_err(redis_err)?;
+        DateTime::from_timestamp(ts, 0).ok_or_else(|| format!("Bad timestamp {}", ts))
+    }
+
+    /// Whether no alert went out yet for the silent stretch that began at `since`
+    async fn claim_silence_alert(&self, since: DateTime<Utc>) -> bool {
+        let Some(client) = &self.redis_client else {
+            return self.window.lock().unwrap().silence_alerted_since != Some(since);
+        };
+
+        let previous: Result<Option<i64>, _> = match client.get_multiplexed_async_connection().await {
+            Ok(mut con) => con.getset(SILENCE_ALERTED_KEY, since.timestamp()).await,
+            Err(e) => Err(e),
+        };
+        match previous {
+            Ok(previous) => previous != Some(since.timestamp()),
+            Err(e) => {
+                println!("[ALERT] ⚠️ Silence check skipped: Redis error: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Record one occurrence for a threshold rule; returns how many fall within the window.
+    /// Occurrences sharing a `subject` (e.g. retries of one event) count once.
+    fn count(&self, rule: AlertRule, subject: Option<&str>, now: DateTime<Utc>) -> Option<usize> {
+        let cutoff = now - chrono::Duration::from_std(self.config.window).ok()?;
+        let mut window = self.window.lock().unwrap();
+        let occurrences = match rule {
+            AlertRule::SignatureFailures => &mut window.signature_failures,
+            AlertRule::ProcessingErrors => &mut window.processing_errors,
+            AlertRule::DisputeOpened | AlertRule::DisputeDeadline | AlertRule::WebhookSilence => return None,
+        };
+        occurrences.push_back((now, subject.map(str::to_string)));
+        while occurrences.front().is_some_and(|(at, _)| *at < cutoff) {
+            occurrences.pop_front();
+        }
+        let subjects: HashSet<&str> = occurrences.iter().filter_map(|(_, s)| s.as_deref()).collect();
+        Some(subjects.len() + occurrences.iter().filter(|(_, s)| s.is_none()).count())
+    }
+
+    fn fire_throttled(&self, rule: AlertRule, message: String, now: DateTime<Utc>) -> Option<Alert> {
+        {
+            let mut window = self.window.lock().unwrap();
+            let cooldown = chrono::Duration::from_std(self.config.cooldown).unwrap_or(chrono::Duration::zero());
+            if window.last_fired.get(&rule).is_some_and(|last| now - *last < cooldown) {
+                return None;
+            }
+            window.last_fired.insert(rule, now);
+        }
+        Some(self.fire(Alert { rule, message }))
+    }
+
+    /// Log, count and post in the background: alerting never slows down webhook handling
+    fn fire(&self, alert: Alert) -> Alert {
+        println!("[ALERT] {} {}", alert.rule.as_str(), alert.message);
+        metrics().inc_counter("alerts_fired_total", &[("rule", alert.rule.as_str())]);
+
+        if let Some(url) = self.config.webhook_url.clone() {
+            let http = self.http.clone();
+            let message = alert.message.clone();
+            tokio::spawn(async move {
+                let body = ChatMessage {
+                    text: &message,
+                    content: &message,
+                };
+                match http.send("POST chat webhook", |c| c.post(&url).json(&body)).await {
+                    Ok(resp) if resp.status().is_success() => {}
+                    Ok(resp) => println!("[ALERT] ❌ Chat webhook answered {}", resp.status()),
+                    Err(e) => println!("[ALERT] ❌ Chat webhook failed: {}", e),
+                }
+            });
+        }
+
+        alert
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn config() -> AlertConfig {
+        AlertConfig {
+            webhook_url: None,
+            window: Duration::from_secs(300),
+            signature_failure_threshold: 3,
+            processing_error_threshold: 2,
+            silence: Some(Duration::from_secs(3600)),
+            cooldown: Duration::from_secs(600),
+            check_interval: Duration::from_secs(60),
+        }
+    }
+
+    #[tokio::test]
+    async fn threshold_rules_fire_within_window_then_cool_down() {
+        let alerter = Alerter::new(config(), None);
+
+        // Two failures, then two more after the first pair left the window
+        assert!(alerter.signature_failed(Provider::Stripe, at(0)).is_none());
+        assert!(alerter.signature_failed(Provider::Stripe, at(10)).is_none());
+        assert!(alerter.signature_failed(Provider::Stripe, at(400)).is_none());
+        assert!(alerter.signature_failed(Provider::Stripe, at(405)).is_none());
+
+        assert!(alerter.signature_failed(Provider::PayPal, at(410)).is_some());
+        // Still spiking, but within the cooldown
+        assert!(alerter.signature_failed(Provider::PayPal, at(420)).is_none());
+        assert!(alerter.signature_failed(Provider::PayPal, at(1_090)).is_none());
+        assert!(alerter.signature_failed(Provider::PayPal, at(1_095)).is_none());
+        assert!(alerter.signature_failed(Provider::PayPal, at(1_100)).is_some());
+
+        // One poison event retrying within the window is a single failing event
+        for retry_at in [0, 5, 15, 35, 75] {
+            assert!(alerter.processing_failed("evt_1", "invoice.paid", "boom", at(retry_at)).is_none());
+        }
+        let alert = alerter.processing_failed("evt_2", "invoice.paid", "boom", at(80)).unwrap();
+        assert_eq!(alert.rule, AlertRule::ProcessingErrors);
+        assert!(alert.message.contains("evt_2"));
+    }
+
+    #[tokio::test]
+    async fn silence_fires_once_until_webhooks_resume() {
+        let alerter = Alerter::new(config(), None);
+
+        assert!(alerter.check_silence(at(0)).await.is_none());
+        assert!(alerter.check_silence(at(3_599)).await.is_none());
+        assert!(alerter.check_silence(at(3_600)).await.is_some());
+        assert!(alerter.check_silence(at(7_200)).await.is_none());
+
+        alerter.webhook_received(at(7_300));
+        assert!(alerter.check_silence(at(8_000)).await.is_none());
+        assert!(alerter.check_silence(at(10_900)).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn dispute_alert_is_posted_as_chat_message() {
+        let (url, mut rx) = crate::test_support::spawn_chat_hook().await;
+        let alerter = Alerter::new(AlertConfig {
+            webhook_url: Some(url),
+            ..config()
+        }, None);
+
+        alerter.dispute_opened(Provider::Stripe, "dp_1", "49.00 EUR", "fraudulent", Some("ada@example.com"));
+
+        let body = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        assert_eq!(body["text"], body["content"]);
+        assert!(body["text"].as_str().unwrap().contains("dp_1"));
+    }
+}
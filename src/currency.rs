@@ -0,0 +1,80 @@
+// lwas_economy/src/payments/currency.rs
+// ARCHITECT: QANTUM AETERNA | STATUS: BETA
+// Currency precision: amounts are kept in the smallest currency unit, as Stripe does
+
+/// Stripe's zero-decimal currencies: the amount is already in whole units (¥1200 is 1200)
+const ZERO_DECIMAL: &[&str] = &[
+    "bif", "clp", "djf", "gnf", "jpy", "kmf", "krw", "mga", "pyg", "rwf", "ugx", "vnd", "vuv", "xaf", "xof", "xpf",
+];
+
+/// Three-decimal currencies (1.000 BHD is 1000)
+const THREE_DECIMAL: &[&str] = &["bhd", "jod", "kwd", "omr", "tnd"];
+
+/// Decimal places between the major and the smallest unit of `currency` (ISO code, any case)
+pub fn exponent(currency: &str) -> u32 {
+    let currency = currency.to_lowercase();
+    if ZERO_DECIMAL.contains(&currency.as_str()) {
+        0
+    } else if THREE_DECIMAL.contains(&currency.as_str()) {
+        3
+    } else {
+        2
+    }
+}
+
+/// A decimal string ("9.5", "10", "1200") in smallest units of `currency`. `None` when it is
+/// not a plain decimal or is more precise than the currency allows.
+pub fn parse_minor_units(value: &str, currency: &str) -> Option<i64> {
+    let exponent = exponent(currency);
+    let (negative, digits) = match value.trim().strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.trim()),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let plain = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if whole.is_empty() || !plain(whole) || !plain(fraction) || fraction.len() > exponent as usize {
+        return None;
+    }
+
+    let whole: i64 = whole.parse().ok()?;
+    let fraction: i64 = format!("{:0<width$}", fraction, width = exponent as usize).parse().unwrap_or(0);
+    let units = whole.checked_mul(10i64.pow(exponent))?.checked_add(fraction)?;
+    Some(if negative { -units } else { units })
+}
+
+/// `1250 eur` → "12.50 EUR", `1200 jpy` → "1200 JPY"
+pub fn format_amount(amount: i64, currency: &str) -> String {
+    let exponent = exponent(currency);
+    let major = amount as f64 / 10f64.powi(exponent as i32);
+    format!("{:.*} {}", exponent as usize, major, currency.to_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decimal_strings_scale_by_the_currency_exponent() {
+        assert_eq!(parse_minor_units("9.00", "USD"), Some(900));
+        assert_eq!(parse_minor_units("9.5", "USD"), Some(950));
+        assert_eq!(parse_minor_units("10", "usd"), Some(1000));
+        assert_eq!(parse_minor_units("1200", "JPY"), Some(1200));
+        assert_eq!(parse_minor_units("1.5", "KWD"), Some(1500));
+        assert_eq!(parse_minor_units("-4.20", "EUR"), Some(-420));
+
+        // More precise than the currency, or not a number at all
+        assert_eq!(parse_minor_units("9.005", "USD"), None);
+        assert_eq!(parse_minor_units("1200.5", "JPY"), None);
+        assert_eq!(parse_minor_units(".5", "USD"), None);
+        assert_eq!(parse_minor_units("1e3", "USD"), None);
+        assert_eq!(parse_minor_units("9.+5", "USD"), None);
+    }
+
+    #[test]
+    fn amounts_format_with_the_currency_precision() {
+        assert_eq!(format_amount(1250, "eur"), "12.50 EUR");
+        assert_eq!(format_amount(1200, "jpy"), "1200 JPY");
+        assert_eq!(format_amount(1500, "kwd"), "1.500 KWD");
+        assert_eq!(format_amount(-420, "usd"), "-4.20 USD");
+    }
+}
@@ -0,0 +1,505 @@
+// lwas_economy/src/payments/dunning.rs
+// ARCHITECT: QANTUM AETERNA | STATUS: BETA
+// Dunning: failed invoice payments → PastDue, reminder cadence, Unpaid after the grace period
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+use crate::currency::format_amount;
+use crate::events::{self, DomainEventType};
+use crate::metrics::metrics;
+use crate::notifications::Notification;
+use crate::stripe_handler::{log_payment_event, StripeWebhookState};
+use crate::stripe_models::Invoice;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DUNNING CONFIGURATION
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Debug)]
+pub struct DunningConfig {
+    /// Days after the first failure at which a reminder goes out
+    pub reminder_days: Vec<i64>,
+    /// Days after the first failure until the subscription is downgraded to Unpaid
+    pub grace_days: i64,
+    pub check_interval: Duration,
+}
+
+impl DunningConfig {
+    pub fn from_env() -> Self {
+        let mut reminder_days: Vec<i64> = std::env::var("DUNNING_REMINDER_DAYS")
+            .unwrap_or_else(|_| "1,3,7".to_string())
+            .split(',')
+            .filter_map(|d| d.trim().parse().ok())
+            .collect();
+        reminder_days.sort_unstable();
+        reminder_days.dedup();
+
+        Self {
+            reminder_days,
+            grace_days: std::env::var("DUNNING_GRACE_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(14),
+            check_interval: Duration::from_secs(
+                std::env::var("DUNNING_CHECK_INTERVAL_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(3600),
+            ),
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DUNNING STORE
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum DunningOutcome {
+    Recovered,
+    Downgraded,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DunningCase {
+    pub email: String,
+    pub customer_id: String,
+    pub subscription_id: Option<String>,
+    /// Latest failed invoice; reminders quote its amount and payment link
+    pub invoice_id: String,
+    /// Every invoice that failed during this case and is still unpaid
+    pub open_invoices: BTreeSet<String>,
+    /// Invoices paid since, so their late-arriving failures are ignored
+    pub paid_invoices: BTreeSet<String>,
+    pub amount_due: i64,
+    pub currency: String,
+    /// Stripe's `attempt_count` from the latest `invoice.payment_failed`
+    pub attempt_count: u32,
+    pub next_payment_attempt: Option<DateTime<Utc>>,
+    pub hosted_invoice_url: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub last_failure_at: DateTime<Utc>,
+    pub reminders_sent: usize,
+    /// Closed cases are kept so a late `payment_failed` for a settled invoice is ignored
+    pub outcome: Option<DunningOutcome>,
+}
+
+impl DunningCase {
+    fn new(email: &str, invoice: &Invoice, now: DateTime<Utc>, outcome: Option<DunningOutcome>) -> Self {
+        let (open_invoices, paid_invoices) = match outcome {
+            None => (BTreeSet::from([invoice.id.clone()]), BTreeSet::new()),
+            Some(_) => (BTreeSet::new(), BTreeSet::from([invoice.id.clone()])),
+        };
+        Self {
+            email: email.to_string(),
+            customer_id: invoice.customer.clone(),
+            subscription_id: invoice.subscription.clone(),
+            invoice_id: invoice.id.clone(),
+            open_invoices,
+            paid_invoices,
+            amount_due: invoice.amount_due,
+            currency: invoice.currency.clone(),
+            attempt_count: invoice.attempt_count,
+            next_payment_attempt: None,
+            hosted_invoice_url: None,
+            started_at: now,
+            last_failure_at: now,
+            reminders_sent: 0,
+            outcome,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.outcome.is_none()
+    }
+
+    /// Whether `invoice_id` failed during this case and is still unpaid
+    pub fn owes(&self, invoice_id: &str) -> bool {
+        self.open_invoices.contains(invoice_id)
+    }
+}
+
+/// What a dunning pass decided for one case
+#[derive(Debug, PartialEq)]
+pub enum DunningAction {
+    Remind { email: String, reminder: usize },
+    Downgrade { email: String },
+}
+
+#[derive(Clone)]
+pub struct DunningStore {
+    // One case per customer email, matching SubscriptionManager's keying
+    cases: Arc<RwLock<HashMap<String, DunningCase>>>,
+}
+
+impl DunningStore {
+    pub fn new() -> Self {
+        Self {
+            cases: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub async fn get(&self, email: &str) -> Option<DunningCase> {
+        let store = self.cases.read().await;
+        store.get(email).cloned()
+    }
+
+    /// Open or update the case for a failed invoice. Returns `None` when the invoice
+    /// was already settled (the failure arrived after the payment).
+    pub async fn record_failure(&self, email: &str, invoice: &Invoice, now: DateTime<Utc>) -> Option<DunningCase> {
+        let mut store = self.cases.write().await;
+
+        let case = match store.get_mut(email) {
+            Some(case) if case.paid_invoices.contains(&invoice.id) => return None,
+            Some(case) if case.owes(&invoice.id) => {
+                if case.invoice_id == invoice.id {
+                    case.attempt_count = case.attempt_count.max(invoice.attempt_count);
+                }
+                case
+            }
+            // Another invoice failing mid-dunning joins the case but does not reset its clock:
+            // the grace period and reminder cadence run from the first failure
+            Some(case) if !case.open_invoices.is_empty() => {
+                let mut next = DunningCase::new(email, invoice, case.started_at, case.outcome.clone());
+                next.open_invoices = std::mem::take(&mut case.open_invoices);
+                next.open_invoices.insert(invoice.id.clone());
+                next.paid_invoices = std::mem::take(&mut case.paid_invoices);
+                next.reminders_sent = case.reminders_sent;
+                *case = next;
+                case
+            }
+            // A new failure after recovery starts a new case, still remembering what was paid
+            existing => {
+                let paid = existing.map(|c| std::mem::take(&mut c.paid_invoices)).unwrap_or_default();
+                let mut case = DunningCase::new(email, invoice, now, None);
+                case.paid_invoices = paid;
+                store.insert(email.to_string(), case);
+                store.get_mut(email).expect("just inserted")
+            }
+        };
+
+        case.last_failure_at = now;
+        case.next_payment_attempt = invoice
+            .next_payment_attempt
+            .and_then(|ts| DateTime::from_timestamp(ts, 0));
+        if invoice.hosted_invoice_url.is_some() {
+            case.hosted_invoice_url = invoice.hosted_invoice_url.clone();
+        }
+        Some(case.clone())
+    }
+
+    /// Settle `invoice` in the case for `email`, closing an open (or downgraded) case as
+    /// recovered once every invoice it tracks is paid. A paid invoice is remembered either
+    /// way so its late-arriving failure is ignored.
+    pub async fn record_payment(&self, email: &str, invoice: &Invoice, now: DateTime<Utc>) -> Option<DunningCase> {
+        let mut store = self.cases.write().await;
+
+        let Some(case) = store.get_mut(email) else {
+            let settled = DunningCase::new(email, invoice, now, Some(DunningOutcome::Recovered));
+            store.insert(email.to_string(), settled);
+            return None;
+        };
+
+        case.paid_invoices.insert(invoice.id.clone());
+        // Paying something else (e.g. a one-off purchase) leaves the unpaid invoices' case alone
+        if !case.open_invoices.remove(&invoice.id) || !case.open_invoices.is_empty() {
+            return None;
+        }
+        case.outcome = Some(DunningOutcome::Recovered);
+        Some(case.clone())
+    }
+
+    /// Forget closed cases whose last failure is older than `cutoff`
+    pub async fn prune_closed(&self, cutoff: DateTime<Utc>) -> usize {
+        let mut store = self.cases.write().await;
+        let before = store.len();
+        store.retain(|_, c| c.is_open() || c.last_failure_at >= cutoff);
+        before - store.len()
+    }
+
+    /// Reminders and downgrades due at `now` per `config`; marks them done
+    pub async fn take_due_actions(&self, config: &DunningConfig, now: DateTime<Utc>) -> Vec<DunningAction> {
+        let mut store = self.cases.write().await;
+        let mut actions = Vec::new();
+
+        for case in store.values_mut().filter(|c| c.is_open()) {
+            let elapsed = now - case.started_at;
+
+            if elapsed >= chrono::Duration::days(config.grace_days) {
+                case.outcome = Some(DunningOutcome::Downgraded);
+                actions.push(DunningAction::Downgrade {
+                    email: case.email.clone(),
+                });
+                continue;
+            }
+
+            // Only the latest due reminder goes out if several fell due since the last pass
+            let due = config
+                .reminder_days
+                .iter()
+                .filter(|d| elapsed >= chrono::Duration::days(**d))
+                .count();
+            if due > case.reminders_sent {
+                case.reminders_sent = due;
+                actions.push(DunningAction::Remind {
+                    email: case.email.clone(),
+                    reminder: due,
+                });
+            }
+        }
+
+        actions
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DUNNING WORKFLOW
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// `invoice.payment_failed`: open/update the case and move the subscription to PastDue
+pub async fn handle_payment_failed(state: &StripeWebhookState, email: &str, invoice: &Invoice, source: &str) {
+    // Stripe's retries of the same invoice are covered by the reminder cadence
+    let opened = state.dunning.get(email).await.is_none_or(|c| !c.owes(&invoice.id));
+    let Some(case) = state.dunning.record_failure(email, invoice, state.clock.now()).await else {
+        println!("[DUNNING] ⚡ Invoice {} already settled, ignoring late failure", invoice.id);
+        return;
+    };
+
+    if state.subscriptions.mark_past_due(email).await {
+        events::publish_subscription(state, DomainEventType::SubscriptionUpdated, email, Some(source)).await;
+    }
+    metrics().inc_counter("dunning_payment_failures_total", &[]);
+    if opened {
+        state
+            .notifier
+            .send(Notification::payment_failed(
+                email,
+                case.amount_due,
+                &case.currency,
+                case.hosted_invoice_url.as_deref(),
+            ))
+            .await;
+    }
+    println!(
+        "[DUNNING] ⚠️ {} attempt {} failed for {} ({}), next retry: {:?}",
+        case.invoice_id,
+        case.attempt_count,
+        email,
+        format_amount(case.amount_due, &case.currency),
+        case.next_payment_attempt
+    );
+}
+
+/// `invoice.paid`: close the case and restore access once every failed invoice is paid
+pub async fn handle_invoice_paid(state: &StripeWebhookState, email: &str, invoice: &Invoice, source: &str) {
+    if let Some(case) = state.dunning.record_payment(email, invoice, state.clock.now()).await {
+        if state.subscriptions.restore_active(email).await {
+            events::publish_subscription(state, DomainEventType::SubscriptionUpdated, email, Some(source)).await;
+        }
+        metrics().inc_counter("dunning_cases_closed_total", &[("outcome", "recovered")]);
+        log_payment_event(email, "dunning.recovered", Some(invoice.amount_paid), Some(&invoice.currency));
+        println!(
+            "[DUNNING] ✅ Recovered {} after {} attempts",
+            email, case.attempt_count
+        );
+    }
+}
+
+/// Send due reminders and downgrade cases past the grace period; returns the actions taken
+pub async fn process_due(state: &StripeWebhookState) -> Vec<DunningAction> {
+    let config = &state.config.dunning;
+    let actions = state.dunning.take_due_actions(config, state.clock.now()).await;
+
+    for action in &actions {
+        match action {
+            DunningAction::Remind { email, reminder } => {
+                metrics().inc_counter("dunning_reminders_total", &[]);
+                println!(
+                    "[DUNNING] 📧 Reminder {}/{} to {}: update your payment method",
+                    reminder,
+                    config.reminder_days.len(),
+                    email
+                );
+                if let Some(case) = state.dunning.get(email).await {
+                    state
+                        .notifier
+                        .send(Notification::payment_failed(
+                            email,
+                            case.amount_due,
+                            &case.currency,
+                            case.hosted_invoice_url.as_deref(),
+                        ))
+                        .await;
+                }
+            }
+            DunningAction::Downgrade { email } => {
+                if state.subscriptions.mark_unpaid(email).await {
+                    events::publish_subscription(state, DomainEventType::SubscriptionUpdated, email, None).await;
+                }
+                metrics().inc_counter("dunning_cases_closed_total", &[("outcome", "downgraded")]);
+                log_payment_event(email, "dunning.downgraded", None, None);
+                println!(
+                    "[DUNNING] ⛔ Grace period of {} days over for {}, downgraded to Unpaid",
+                    config.grace_days, email
+                );
+            }
+        }
+    }
+
+    actions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use crate::stripe_handler::{EventStamp, SubscriptionStatus};
+
+    fn invoice(id: &str, attempt_count: u32) -> Invoice {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "customer": "cus_1", "subscription": "sub_1",
+            "amount_due": 900, "amount_paid": 0, "currency": "eur",
+            "attempt_count": attempt_count, "next_payment_attempt": 1_700_100_000,
+            "hosted_invoice_url": "https://invoice.stripe.com/i/1"
+        }))
+        .unwrap()
+    }
+
+    fn day(n: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + n * 86_400, 0).unwrap()
+    }
+
+    async fn subscribed_state() -> (StripeWebhookState, Arc<FixedClock>) {
+        let clock = FixedClock::at(day(0));
+        let mut state = StripeWebhookState::new(crate::test_support::authenticator());
+        state.clock = clock.clone();
+        state.config.dunning = DunningConfig {
+            reminder_days: vec![1, 3, 7],
+            grace_days: 14,
+            check_interval: Duration::from_secs(3600),
+        };
+        state
+            .subscriptions
+            .activate_subscription(
+                None,
+                "ada@example.com",
+                Some("cus_1".into()),
+                Some("sub_1".into()),
+                "pro_monthly",
+                EventStamp {
+                    id: "evt_checkout".into(),
+                    created: 1,
+                },
+            )
+            .await;
+        (state, clock)
+    }
+
+    async fn status(state: &StripeWebhookState) -> SubscriptionStatus {
+        state.subscriptions.get_by_email("ada@example.com").await.unwrap().status
+    }
+
+    #[tokio::test]
+    async fn failure_reminds_on_cadence_then_recovers() {
+        let (state, clock) = subscribed_state().await;
+
+        handle_payment_failed(&state, "ada@example.com", &invoice("in_1", 1), "evt_1").await;
+        assert_eq!(status(&state).await, SubscriptionStatus::PastDue);
+        assert!(process_due(&state).await.is_empty());
+
+        clock.set(day(1));
+        handle_payment_failed(&state, "ada@example.com", &invoice("in_1", 2), "evt_2").await;
+        assert_eq!(
+            process_due(&state).await,
+            vec![DunningAction::Remind { email: "ada@example.com".into(), reminder: 1 }]
+        );
+        assert!(process_due(&state).await.is_empty());
+
+        // Reminders missed while down collapse into the latest one
+        clock.set(day(8));
+        assert_eq!(
+            process_due(&state).await,
+            vec![DunningAction::Remind { email: "ada@example.com".into(), reminder: 3 }]
+        );
+
+        let case = state.dunning.get("ada@example.com").await.unwrap();
+        assert_eq!(case.attempt_count, 2);
+        assert_eq!(case.started_at, day(0));
+
+        handle_invoice_paid(&state, "ada@example.com", &invoice("in_1", 3), "evt_3").await;
+        assert_eq!(status(&state).await, SubscriptionStatus::Active);
+        clock.set(day(30));
+        assert!(process_due(&state).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn grace_period_end_downgrades_to_unpaid() {
+        let (state, clock) = subscribed_state().await;
+
+        handle_payment_failed(&state, "ada@example.com", &invoice("in_1", 1), "evt_1").await;
+        clock.set(day(14));
+        assert_eq!(
+            process_due(&state).await,
+            vec![DunningAction::Downgrade { email: "ada@example.com".into() }]
+        );
+        assert_eq!(status(&state).await, SubscriptionStatus::Unpaid);
+
+        // Paying the invoice later still restores access
+        handle_invoice_paid(&state, "ada@example.com", &invoice("in_1", 4), "evt_4").await;
+        assert_eq!(status(&state).await, SubscriptionStatus::Active);
+    }
+
+    #[tokio::test]
+    async fn case_closes_only_once_every_failed_invoice_is_paid() {
+        let (state, clock) = subscribed_state().await;
+
+        handle_payment_failed(&state, "ada@example.com", &invoice("in_1", 1), "evt_1").await;
+        handle_invoice_paid(&state, "ada@example.com", &invoice("in_oneoff", 1), "evt_2").await;
+        assert_eq!(status(&state).await, SubscriptionStatus::PastDue);
+        assert!(state.dunning.get("ada@example.com").await.unwrap().is_open());
+
+        // A second invoice failing mid-dunning does not restart the grace period
+        clock.set(day(5));
+        handle_payment_failed(&state, "ada@example.com", &invoice("in_2", 1), "evt_3").await;
+        let case = state.dunning.get("ada@example.com").await.unwrap();
+        assert_eq!((case.invoice_id.as_str(), case.started_at), ("in_2", day(0)));
+        assert_eq!(case.open_invoices, BTreeSet::from(["in_1".to_string(), "in_2".to_string()]));
+
+        clock.set(day(14));
+        assert_eq!(
+            process_due(&state).await,
+            vec![DunningAction::Downgrade { email: "ada@example.com".into() }]
+        );
+
+        // in_1 is still unpaid: paying in_2 alone restores nothing
+        handle_invoice_paid(&state, "ada@example.com", &invoice("in_2", 2), "evt_4").await;
+        assert_eq!(status(&state).await, SubscriptionStatus::Unpaid);
+        let case = state.dunning.get("ada@example.com").await.unwrap();
+        assert_eq!(case.open_invoices, BTreeSet::from(["in_1".to_string()]));
+        assert_eq!(case.outcome, Some(DunningOutcome::Downgraded));
+
+        handle_invoice_paid(&state, "ada@example.com", &invoice("in_1", 2), "evt_5").await;
+        assert_eq!(status(&state).await, SubscriptionStatus::Active);
+        assert_eq!(state.dunning.get("ada@example.com").await.unwrap().outcome, Some(DunningOutcome::Recovered));
+
+        // Both are settled now: late failures for either are ignored
+        handle_payment_failed(&state, "ada@example.com", &invoice("in_1", 3), "evt_6").await;
+        assert_eq!(status(&state).await, SubscriptionStatus::Active);
+    }
+
+    #[tokio::test]
+    async fn late_failure_for_paid_invoice_is_ignored() {
+        let (state, _clock) = subscribed_state().await;
+
+        handle_invoice_paid(&state, "ada@example.com", &invoice("in_1", 2), "evt_2").await;
+        handle_payment_failed(&state, "ada@example.com", &invoice("in_1", 1), "evt_1").await;
+
+        assert_eq!(status(&state).await, SubscriptionStatus::Active);
+        assert!(!state.dunning.get("ada@example.com").await.unwrap().is_open());
+    }
+}
@@ -0,0 +1,332 @@
+// lwas_economy/src/payments/event_archive.rs
+// ARCHITECT: QANTUM AETERNA | STATUS: BETA
+// Raw webhook archive: what Stripe and PayPal actually sent, and what we did with it
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+use crate::auth::Authenticator;
+use crate::metrics::redis_fallback;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// ARCHIVED EVENTS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Provider {
+    Stripe,
+    PayPal,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveOutcome {
+    /// Verified, processing not finished yet
+    Received,
+    /// Persisted to the webhook inbox
+    Queued,
+    Processed,
+    /// Failed, another attempt is scheduled
+    Retrying,
+    Failed,
+    DeadLettered,
+    /// Dead letter dropped by an admin
+    Discarded,
+    /// Verified but refused (e.g. livemode mismatch)
+    Rejected,
+}
+
+impl ArchiveOutcome {
+    /// Nothing happens to the event after these; a dead letter can still be replayed
+    pub fn is_final(&self) -> bool {
+        matches!(self, ArchiveOutcome::Processed | ArchiveOutcome::Discarded | ArchiveOutcome::Rejected)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ArchivedEvent {
+    pub provider: Provider,
+    pub event_id: String,
+    pub event_type: String,
+    pub received_at: DateTime<Utc>,
+    /// Delivery headers worth keeping (signatures, transmission ids, user agent)
+    pub headers: BTreeMap<String, String>,
+    /// The request body exactly as delivered; providers send JSON, and bodies that are not
+    /// UTF-8 are rejected before archiving, so no bytes are replaced
+    pub body: String,
+    pub outcome: ArchiveOutcome,
+    pub outcome_detail: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ArchivedEvent {
+    pub fn new(
+        provider: Provider,
+        event_id: &str,
+        event_type: &str,
+        headers: &HeaderMap,
+        body: String,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            provider,
+            event_id: event_id.to_string(),
+            event_type: event_type.to_string(),
+            received_at: now,
+            headers: relevant_headers(headers),
+            body,
+            outcome: ArchiveOutcome::Received,
+            outcome_detail: None,
+            updated_at: now,
+        }
+    }
+
+    pub fn with_outcome(mut self, outcome: ArchiveOutcome, detail: Option<String>) -> Self {
+        self.outcome = outcome;
+        self.outcome_detail = detail;
+        self
+    }
+}
+
+/// Provider headers plus the generic ones useful when debugging a delivery. Nothing
+/// here is a credential: signatures are only verifiable with our own secrets.
+fn relevant_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .filter(|(name, _)| {
+            let name = name.as_str();
+            name.starts_with("stripe-")
+                || name.starts_with("paypal-")
+                || name == "content-type"
+                || name == "user-agent"
+        })
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// ARCHIVE STORE
+// ═══════════════════════════════════════════════════════════════════════════════
+// Best effort, like the idempotency store: the inbox is what makes delivery durable,
+// so an archive outage is logged and never fails a webhook.
+
+#[derive(Clone)]
+pub struct EventArchive {
+    redis_client: Option<redis::Client>,
+    fallback: Arc<RwLock<HashMap<String, ArchivedEvent>>>,
+    retention: Duration,
+}
+
+/// Replace the archived event ARGV[1] with ARGV[2], keeping its TTL, unless it changed since
+const COMPARE_AND_SET_SCRIPT: &str = r#"
+if redis.call('get', KEYS[1]) == ARGV[1] then
+    redis.call('set', KEYS[1], ARGV[2], 'KEEPTTL')
+    return 1
+end
+return 0
+"#;
+
+const MAX_UPDATE_ATTEMPTS: usize = 5;
+
+impl EventArchive {
+    pub fn new(redis_url: Option<String>, retention: Duration) -> Self {
+        let redis_client = redis_url.and_then(|url| {
+            redis::Client::open(url).map_err(|e| println!("❌ Redis connect error: {}", e)).ok()
+        });
+
+        Self {
+            redis_client,
+            fallback: Arc::new(RwLock::new(HashMap::new())),
+            retention,
+        }
+    }
+
+    fn key(event_id: &str) -> String {
+        format!("archive:event:{}", event_id)
+    }
+
+    /// Archive the first delivery of an event; redeliveries leave it untouched.
+    /// Returns whether this call stored it.
+    pub async fn record(&self, event: ArchivedEvent) -> bool {
+        if let Some(client) = &self.redis_client {
+            if let Ok(mut con) = client.get_multiplexed_async_connection().await {
+                let json = serde_json::to_string(&event).unwrap_or_default();
+                let stored: Result<Option<String>, _> = redis::cmd("SET")
+                    .arg(Self::key(&event.event_id))
+                    .arg(json)
+                    .arg("NX")
+                    .arg("EX")
+                    .arg(self.retention.as_secs().max(1))
+                    .query_async(&mut con)
+                    .await;
+                return match stored {
+                    Ok(reply) => reply.is_some(),
+                    Err(e) => {
+                        println!("[ARCHIVE] ❌ Failed to archive {}: {}", event.event_id, e);
+                        false
+                    }
+                };
+            }
+        }
+
+        redis_fallback("archive", self.redis_client.is_some());
+        let mut store = self.fallback.write().await;
+        if store.contains_key(&event.event_id) {
+            return false;
+        }
+        store.insert(event.event_id.clone(), event);
+        true
+    }
+
+    /// Update what became of an archived event; unknown (or expired) events are ignored, and
+    /// so is any update to an event whose outcome is already final
+    pub async fn set_outcome(&self, event_id: &str, outcome: ArchiveOutcome, detail: Option<String>, now: DateTime<Utc>) {
+        let update = |event: &mut ArchivedEvent| {
+            if event.outcome.is_final() {
+                return false;
+            }
+            event.outcome = outcome;
+            event.outcome_detail = detail.clone();
+            event.updated_at = now;
+            true
+        };
+
+        if let Some(client) = &self.redis_client {
+            if let Ok(mut con) = client.get_multiplexed_async_connection().await {
+                // The webhook handler and a worker may update the same event at once
+                for _ in 0..MAX_UPDATE_ATTEMPTS {
+                    let raw: Option<String> = con.get(Self::key(event_id)).await.unwrap_or(None);
+                    let Some(raw) = raw else {
+                        return;
+                    };
+                    let Some(mut event) = serde_json::from_str::<ArchivedEvent>(&raw).ok() else {
+                        return;
+                    };
+                    if !update(&mut event) {
+                        return;
+                    }
+                    let json = serde_json::to_string(&event).unwrap_or_default();
+                    let written: Result<i64, _> = redis::Script::new(COMPARE_AND_SET_SCRIPT)
+                        .key(Self::key(event_id))
+                        .arg(raw)
+                        .arg(json)
+                        .invoke_async(&mut con)
+                        .await;
+                    match written {
+                        Ok(0) => continue,
+                        Ok(_) => return,
+                        Err(e) => {
+                            println!("[ARCHIVE] ❌ Failed to update {}: {}", event_id, e);
+                            return;
+                        }
+                    }
+                }
+                println!("[ARCHIVE] ❌ Gave up updating {}: kept changing underneath", event_id);
+                return;
+            }
+        }
+
+        redis_fallback("archive", self.redis_client.is_some());
+        if let Some(event) = self.fallback.write().await.get_mut(event_id) {
+            update(event);
+        }
+    }
+
+    pub async fn get(&self, event_id: &str) -> Option<ArchivedEvent> {
+        if let Some(client) = &self.redis_client {
+            if let Ok(mut con) = client.get_multiplexed_async_connection().await {
+                let raw: Option<String> = con.get(Self::key(event_id)).await.unwrap_or(None);
+                return raw.and_then(|r| serde_json::from_str(&r).ok());
+            }
+        }
+
+        redis_fallback("archive", self.redis_client.is_some());
+        self.fallback.read().await.get(event_id).cloned()
+    }
+
+    /// Drop fallback entries received before `cutoff` (Redis entries expire on their own)
+    pub async fn prune_fallback(&self, cutoff: DateTime<Utc>) -> usize {
+        let mut store = self.fallback.write().await;
+        let before = store.len();
+        store.retain(|_, e| e.received_at >= cutoff);
+        before - store.len()
+    }
+
+    pub fn retention(&self) -> Duration {
+        self.retention
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// LOOKUP (ADMIN)
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Archived delivery by provider event id (`evt_...` / `WH-...`)
+pub async fn get_archived_event(
+    State(archive): State<EventArchive>,
+    State(auth): State<Authenticator>,
+    headers: HeaderMap,
+    Path(event_id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(e) = auth.require_admin(&headers) {
+        return e.into_response();
+    }
+
+    match archive.get(&event_id).await {
+        Some(event) => Json(event).into_response(),
+        None => (StatusCode::NOT_FOUND, "Event not archived (unknown or past retention)").into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn keeps_first_delivery_and_tracks_outcome() {
+        let archive = EventArchive::new(None, Duration::from_secs(3600));
+        let received = DateTime::from_timestamp(1_000, 0).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("paypal-transmission-id", "tx-1".parse().unwrap());
+        headers.insert("authorization", "Bearer secret".parse().unwrap());
+        headers.insert("user-agent", "PayPal/AUHD-214.0".parse().unwrap());
+
+        let first = ArchivedEvent::new(Provider::PayPal, "WH-1", "CUSTOMER.DISPUTE.CREATED", &headers, "{}".into(), received);
+        assert!(archive.record(first.clone()).await);
+        let redelivery = ArchivedEvent {
+            body: "{\"changed\":true}".into(),
+            ..first
+        };
+        assert!(!archive.record(redelivery).await);
+
+        archive
+            .set_outcome("WH-1", ArchiveOutcome::Processed, None, received + chrono::Duration::seconds(1))
+            .await;
+        let stored = archive.get("WH-1").await.unwrap();
+        assert_eq!(stored.body, "{}");
+        assert_eq!(stored.outcome, ArchiveOutcome::Processed);
+        assert_eq!(stored.headers.keys().collect::<Vec<_>>(), ["paypal-transmission-id", "user-agent"]);
+
+        // A late "queued" from the webhook handler does not undo the worker's outcome
+        archive
+            .set_outcome("WH-1", ArchiveOutcome::Queued, None, received + chrono::Duration::seconds(2))
+            .await;
+        let stored = archive.get("WH-1").await.unwrap();
+        assert_eq!((stored.outcome, stored.updated_at), (ArchiveOutcome::Processed, received + chrono::Duration::seconds(1)));
+
+        assert_eq!(archive.prune_fallback(received + chrono::Duration::seconds(1)).await, 1);
+        assert!(archive.get("WH-1").await.is_none());
+    }
+}
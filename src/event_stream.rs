@@ -0,0 +1,217 @@
+// lwas_economy/src/payments/event_stream.rs
+// ARCHITECT: QANTUM AETERNA | STATUS: BETA
+// Server-Sent Events stream of domain events for dashboards and the post-checkout page
+
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
+};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
+
+use crate::events::DomainEvent;
+use crate::metrics::metrics;
+use crate::stripe_handler::StripeWebhookState;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// USER FILTER
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Which events belong to a user. Subscription events carry the user id; payment,
+/// refund and dispute events only the email, learned from the former as they pass.
+struct UserFilter {
+    user_id: String,
+    email: Option<String>,
+}
+
+impl UserFilter {
+    async fn new(state: &StripeWebhookState, user_id: Uuid) -> Self {
+        let email = state.subscriptions.get_by_user_id(user_id).await.map(|s| s.email);
+        Self {
+            user_id: user_id.to_string(),
+            email,
+        }
+    }
+
+    fn matches(&mut self, event: &DomainEvent) -> bool {
+        if event.data["user_id"].as_str() == Some(self.user_id.as_str()) {
+            if let Some(email) = event.data["email"].as_str() {
+                self.email = Some(email.to_string());
+            }
+            return true;
+        }
+        self.email.is_some() && event.data["email"].as_str() == self.email.as_deref()
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// STREAM
+// ═══════════════════════════════════════════════════════════════════════════════
+
+struct StreamState {
+    missed: VecDeque<DomainEvent>,
+    live: broadcast::Receiver<DomainEvent>,
+    filter: Option<UserFilter>,
+}
+
+impl StreamState {
+    /// Next event for this client: missed ones first, then live. `None` ends the stream.
+    async fn next(&mut self) -> Option<DomainEvent> {
+        loop {
+            let event = match self.missed.pop_front() {
+                Some(event) => event,
+                None => match self.live.recv().await {
+                    Ok(event) => event,
+                    // Too slow to keep up: hang up, the client resumes from its Last-Event-ID
+                    Err(RecvError::Lagged(skipped)) => {
+                        println!("[STREAM] ⚠️ Client lagged by {} events, closing", skipped);
+                        metrics().inc_counter("event_stream_lagged_total", &[]);
+                        return None;
+                    }
+                    Err(RecvError::Closed) => return None,
+                },
+            };
+
+            if self.filter.as_mut().is_none_or(|f| f.matches(&event)) {
+                return Some(event);
+            }
+        }
+    }
+}
+
+fn sse_event(event: &DomainEvent) -> Event {
+    Event::default()
+        .id(event.id.clone())
+        .event(event.event_type.clone())
+        .data(serde_json::to_string(event).unwrap_or_default())
+}
+
+#[derive(Deserialize)]
+pub struct StreamQuery {
+    /// Operators only: restrict the stream to one user
+    pub user_id: Option<Uuid>,
+}
+
+/// `GET /events/stream`: the admin token sees every event (or `?user_id=`'s), a customer
+/// bearer token only the caller's own. Reconnects resume after `Last-Event-ID`.
+pub async fn stream_events(
+    State(state): State<Arc<StripeWebhookState>>,
+    headers: HeaderMap,
+    Query(query): Query<StreamQuery>,
+) -> impl IntoResponse {
+    let (scope, filter) = if state.auth.is_admin(&headers) {
+        match query.user_id {
+            Some(user_id) => ("admin", Some(UserFilter::new(&state, user_id).await)),
+            None => ("admin", None),
+        }
+    } else {
+        match state.auth.authenticate_request(&headers).await {
+            Ok(user) => ("user", Some(UserFilter::new(&state, user.user_id).await)),
+            Err(e) => return e.into_response(),
+        }
+    };
+
+    let last_event_id = headers.get("last-event-id").and_then(|v| v.to_str().ok());
+    let (missed, live) = state.events.resume(last_event_id);
+    println!("[STREAM] 📡 {} client connected ({} missed events)", scope, missed.len());
+    metrics().inc_counter("event_stream_connections_total", &[("scope", scope)]);
+
+    let stream = StreamState {
+        missed: missed.into(),
+        live,
+        filter,
+    };
+    let events = futures::stream::unfold(stream, |mut stream| async move {
+        let event = stream.next().await?;
+        Some((Ok::<_, Infallible>(sse_event(&event)), stream))
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default()).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{AuthConfig, Authenticator};
+    use crate::events::{DomainEventType, EventBus};
+    use axum::{routing::get, Router};
+    use chrono::Utc;
+
+    async fn payment(bus: &EventBus, user_id: Option<Uuid>, email: &str) -> DomainEvent {
+        let event = DomainEvent::new(
+            DomainEventType::PaymentSucceeded,
+            None,
+            serde_json::json!({ "email": email, "user_id": user_id, "amount": 900 }),
+            Utc::now(),
+        );
+        bus.publish(event.clone()).await;
+        event
+    }
+
+    /// Ids of the `count` next SSE events on the response
+    async fn read_ids(resp: &mut reqwest::Response, count: usize) -> Vec<String> {
+        let mut buffer = String::new();
+        loop {
+            let ids: Vec<String> = buffer
+                .lines()
+                .filter_map(|l| l.strip_prefix("id: ").map(|id| id.to_string()))
+                .collect();
+            if ids.len() >= count {
+                return ids;
+            }
+            let chunk = resp.chunk().await.unwrap().expect("stream ended");
+            buffer.push_str(&String::from_utf8_lossy(&chunk));
+        }
+    }
+
+    #[tokio::test]
+    async fn customer_resumes_own_events_after_last_event_id() {
+        let state = Arc::new(StripeWebhookState::new(Authenticator::new(AuthConfig {
+            jwt_secret: Some("stream_test_secret".into()),
+            ..Default::default()
+        })));
+        let user = Uuid::new_v4();
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &serde_json::json!({ "sub": user.to_string(), "exp": Utc::now().timestamp() + 600 }),
+            &jsonwebtoken::EncodingKey::from_secret(b"stream_test_secret"),
+        )
+        .unwrap();
+
+        let seen = payment(&state.events, Some(user), "me@example.com").await;
+        payment(&state.events, Some(Uuid::new_v4()), "someone@example.com").await;
+        let missed = payment(&state.events, Some(user), "me@example.com").await;
+
+        let url = crate::test_support::spawn_mock(
+            Router::new().route("/stream", get(stream_events)).with_state(state.clone()),
+        )
+        .await;
+        let client = reqwest::Client::new();
+
+        let unauthenticated = client.get(format!("{}/stream", url)).send().await.unwrap();
+        assert_eq!(unauthenticated.status(), 401);
+
+        let mut resp = client
+            .get(format!("{}/stream", url))
+            .bearer_auth(&token)
+            .header("Last-Event-ID", &seen.id)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+
+        // Live: someone else's payment is filtered out; a payment known only by email gets through
+        payment(&state.events, None, "someone@example.com").await;
+        let by_email = payment(&state.events, None, "me@example.com").await;
+
+        assert_eq!(read_ids(&mut resp, 2).await, [missed.id, by_email.id]);
+    }
+}
@@ -0,0 +1,193 @@
+// lwas_economy/src/payments/events.rs
+// ARCHITECT: QANTUM AETERNA | STATUS: BETA
+// Normalized billing events for our own services, independent of the provider that caused them
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+use crate::metrics::metrics;
+use crate::outbound_webhooks::OutboundDispatcher;
+use crate::stripe_handler::{StripeWebhookState, UserSubscription};
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DOMAIN EVENTS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DomainEventType {
+    SubscriptionActivated,
+    /// Status or billing dates changed (past due, suspended, reinstated, renewed...)
+    SubscriptionUpdated,
+    SubscriptionCanceled,
+    SubscriptionTrialWillEnd,
+    PaymentSucceeded,
+    PaymentFailed,
+    PaymentRefunded,
+    DisputeUpdated,
+}
+
+impl DomainEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DomainEventType::SubscriptionActivated => "subscription.activated",
+            DomainEventType::SubscriptionUpdated => "subscription.updated",
+            DomainEventType::SubscriptionCanceled => "subscription.canceled",
+            DomainEventType::SubscriptionTrialWillEnd => "subscription.trial_will_end",
+            DomainEventType::PaymentSucceeded => "payment.succeeded",
+            DomainEventType::PaymentFailed => "payment.failed",
+            DomainEventType::PaymentRefunded => "payment.refunded",
+            DomainEventType::DisputeUpdated => "dispute.updated",
+        }
+    }
+}
+
+/// `<prefix>_<first 128 bits of sha256(parts joined by ':')>`, stable across reprocessing
+pub fn stable_id(prefix: &str, parts: &[&str]) -> String {
+    let digest = Sha256::digest(parts.join(":").as_bytes());
+    format!("{}_{}", prefix, hex::encode(&digest[..16]))
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DomainEvent {
+    /// Derived from `(source_event, type)` so a reprocessed or redelivered provider event
+    /// republishes the same id and consumers can deduplicate on it
+    pub id: String,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub created: DateTime<Utc>,
+    /// Provider event that caused it, if any (e.g. `evt_...`)
+    pub source_event: Option<String>,
+    pub data: serde_json::Value,
+}
+
+impl DomainEvent {
+    pub fn new(
+        event_type: DomainEventType,
+        source_event: Option<&str>,
+        data: serde_json::Value,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let id = match source_event {
+            Some(source) => stable_id("ev", &[source, event_type.as_str()]),
+            // Scheduler-driven (e.g. dunning downgrades): happens once, nothing to replay
+            None => format!("ev_{}", uuid::Uuid::new_v4().simple()),
+        };
+        Self {
+            id,
+            event_type: event_type.as_str().to_string(),
+            created: now,
+            source_event: source_event.map(|s| s.to_string()),
+            data,
+        }
+    }
+}
+
+/// The subscription as our services see it
+pub fn subscription_data(sub: &UserSubscription) -> serde_json::Value {
+    serde_json::json!({
+        "email": sub.email,
+        "user_id": sub.user_id,
+        "plan": sub.plan,
+        "status": sub.status,
+        "subscription_id": sub.stripe_subscription_id,
+        "customer_id": sub.stripe_customer_id,
+        "current_period_end": sub.current_period_end,
+        "trial_end": sub.trial_end,
+    })
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// EVENT BUS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Fan-out of domain events to their consumers. Outbound webhook deliveries are persisted
+/// before `publish` returns; live consumers (event streams) get an in-process broadcast, and
+/// the last `capacity` events are kept so stream clients can resume after a disconnect.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<DomainEvent>,
+    recent: Arc<Mutex<VecDeque<DomainEvent>>>,
+    capacity: usize,
+    outbound: OutboundDispatcher,
+}
+
+impl EventBus {
+    pub fn new(capacity: usize, outbound: OutboundDispatcher) -> Self {
+        let capacity = capacity.max(1);
+        let (sender, _) = broadcast::channel(capacity);
+        Self {
+            sender,
+            recent: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+            outbound,
+        }
+    }
+
+    pub async fn publish(&self, event: DomainEvent) {
+        println!("[EVENTS] 📣 {} {}", event.event_type, event.id);
+        if let Err(e) = self.outbound.enqueue(&event, event.created).await {
+            metrics().inc_counter("outbound_webhook_enqueue_errors_total", &[]);
+            println!("[EVENTS] ❌ Outbound deliveries for {} not queued: {}", event.id, e);
+        }
+        // Buffer and send under one lock so `resume` never sees an event twice or misses one
+        let mut recent = self.recent.lock().unwrap();
+        recent.push_back(event.clone());
+        while recent.len() > self.capacity {
+            recent.pop_front();
+        }
+        // No subscribers is fine: nobody is interested yet
+        let _ = self.sender.send(event);
+    }
+
+    /// Buffered events published after `last_event_id`, and a receiver for everything after them.
+    /// An id no longer buffered (too old, or from before a restart) replays the whole buffer.
+    pub fn resume(&self, last_event_id: Option<&str>) -> (Vec<DomainEvent>, broadcast::Receiver<DomainEvent>) {
+        let recent = self.recent.lock().unwrap();
+        let missed = match last_event_id {
+            None => Vec::new(),
+            Some(id) => {
+                let start = recent.iter().position(|e| e.id == id).map_or(0, |i| i + 1);
+                recent.iter().skip(start).cloned().collect()
+            }
+        };
+        (missed, self.sender.subscribe())
+    }
+}
+
+/// Publish a subscription event carrying the current local state of `email`'s subscription
+pub async fn publish_subscription(
+    state: &StripeWebhookState,
+    event_type: DomainEventType,
+    email: &str,
+    source_event: Option<&str>,
+) {
+    if let Some(sub) = state.subscriptions.get_by_email(email).await {
+        state
+            .events
+            .publish(DomainEvent::new(event_type, source_event, subscription_data(&sub), state.clock.now()))
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reprocessing_a_provider_event_republishes_the_same_id() {
+        let now = Utc::now();
+        let event = |source, event_type| DomainEvent::new(event_type, source, serde_json::json!({}), now);
+
+        let first = event(Some("evt_1"), DomainEventType::PaymentRefunded);
+        assert_eq!(first.id, event(Some("evt_1"), DomainEventType::PaymentRefunded).id);
+        assert!(first.id.starts_with("ev_"));
+        // One provider event can cause several domain events
+        assert_ne!(first.id, event(Some("evt_1"), DomainEventType::SubscriptionCanceled).id);
+        assert_ne!(first.id, event(Some("evt_2"), DomainEventType::PaymentRefunded).id);
+        assert_ne!(event(None, DomainEventType::SubscriptionUpdated).id, event(None, DomainEventType::SubscriptionUpdated).id);
+    }
+}
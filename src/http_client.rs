@@ -0,0 +1,389 @@
+// lwas_economy/src/payments/http_client.rs
+// ARCHITECT: QANTUM AETERNA | STATUS: BETA
+// Resilient outbound HTTP layer for provider APIs (timeouts, retries, circuit breaker)
+
+use chrono::{DateTime, Utc};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::metrics::metrics;
+use crate::runtime::{env_u64, jittered_backoff};
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// OUTBOUND CONFIGURATION
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Debug)]
+pub struct OutboundConfig {
+    pub connect_timeout: Duration,
+    pub request_timeout: Duration,
+    pub max_retries: u32,
+    pub backoff_base: Duration,
+    pub backoff_max: Duration,
+    pub breaker_threshold: u32,
+    pub breaker_cooldown: Duration,
+}
+
+impl OutboundConfig {
+    pub fn from_env() -> Self {
+        Self {
+            connect_timeout: Duration::from_millis(env_u64("OUTBOUND_CONNECT_TIMEOUT_MS", 3_000)),
+            request_timeout: Duration::from_millis(env_u64("OUTBOUND_REQUEST_TIMEOUT_MS", 10_000)),
+            max_retries: env_u64("OUTBOUND_MAX_RETRIES", 3) as u32,
+            backoff_base: Duration::from_millis(env_u64("OUTBOUND_BACKOFF_BASE_MS", 200)),
+            backoff_max: Duration::from_millis(env_u64("OUTBOUND_BACKOFF_MAX_MS", 5_000)),
+            breaker_threshold: env_u64("OUTBOUND_BREAKER_THRESHOLD", 5) as u32,
+            breaker_cooldown: Duration::from_secs(env_u64("OUTBOUND_BREAKER_COOLDOWN_SECS", 30)),
+        }
+    }
+
+    /// Full-jitter exponential backoff: uniform in [0, min(max, base * 2^attempt)]
+    fn backoff(&self, attempt: u32) -> Duration {
+        jittered_backoff(self.backoff_base, self.backoff_max, attempt)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// CIRCUIT BREAKER
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug)]
+enum BreakerState {
+    Closed { consecutive_failures: u32 },
+    Open { until: Instant },
+    HalfOpen,
+}
+
+pub struct CircuitBreaker {
+    state: Mutex<BreakerState>,
+    threshold: u32,
+    cooldown: Duration,
+}
+
+impl CircuitBreaker {
+    fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            state: Mutex::new(BreakerState::Closed { consecutive_failures: 0 }),
+            threshold: threshold.max(1),
+            cooldown,
+        }
+    }
+
+    /// Whether a request may go out now. After the cooldown a single probe is let through.
+    fn allow(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            BreakerState::Closed { .. } => true,
+            BreakerState::Open { until } if Instant::now() >= until => {
+                *state = BreakerState::HalfOpen;
+                true
+            }
+            BreakerState::Open { .. } | BreakerState::HalfOpen => false,
+        }
+    }
+
+    fn record_success(&self) {
+        *self.state.lock().unwrap() = BreakerState::Closed { consecutive_failures: 0 };
+    }
+
+    /// Returns true when this failure opened the circuit
+    fn record_failure(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let failures = match *state {
+            BreakerState::Closed { consecutive_failures } => consecutive_failures + 1,
+            BreakerState::HalfOpen => self.threshold,
+            BreakerState::Open { .. } => return false,
+        };
+
+        if failures >= self.threshold {
+            *state = BreakerState::Open { until: Instant::now() + self.cooldown };
+            true
+        } else {
+            *state = BreakerState::Closed { consecutive_failures: failures };
+            false
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// OUTBOUND CLIENT
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// One instance per provider: the circuit breaker is shared by all its endpoints.
+#[derive(Clone)]
+pub struct OutboundClient {
+    provider: &'static str,
+    client: Client,
+    config: OutboundConfig,
+    breaker: Arc<CircuitBreaker>,
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// `Retry-After` as delta-seconds or HTTP-date
+fn retry_after(resp: &Response) -> Option<Duration> {
+    let value = resp.headers().get("retry-after")?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc);
+    (at - Utc::now()).to_std().ok()
+}
+
+impl OutboundClient {
+    pub fn new(provider: &'static str, config: OutboundConfig) -> Self {
+        let client = Client::builder()
+            .connect_timeout(config.connect_timeout)
+            .timeout(config.request_timeout)
+            .build()
+            .expect("Failed to build HTTP client");
+
+        Self {
+            breaker: Arc::new(CircuitBreaker::new(config.breaker_threshold, config.breaker_cooldown)),
+            provider,
+            client,
+            config,
+        }
+    }
+
+    /// Send a request built by `build`, retrying 429/5xx and transport errors.
+    /// `endpoint` is a low-cardinality label such as `"POST /v1/refunds"`.
+    /// The final response is returned as-is (including non-retryable 4xx).
+    pub async fn send<F>(&self, endpoint: &str, build: F) -> Result<Response, String>
+    where
+        F: Fn(&Client) -> RequestBuilder,
+    {
+        let labels = [("provider", self.provider), ("endpoint", endpoint)];
+        let mut attempt = 0;
+
+        loop {
+            if !self.breaker.allow() {
+                metrics().inc_counter("outbound_circuit_rejections_total", &[("provider", self.provider)]);
+                return Err(format!("{} circuit open, request not sent", self.provider));
+            }
+
+            let started = Instant::now();
+            let result = build(&self.client).send().await;
+            metrics().observe(
+                "outbound_request_duration_seconds",
+                &labels,
+                started.elapsed().as_secs_f64(),
+            );
+
+            let delay = match result {
+                Ok(resp) => {
+                    let status = resp.status();
+                    metrics().inc_counter(
+                        "outbound_requests_total",
+                        &[
+                            ("provider", self.provider),
+                            ("endpoint", endpoint),
+                            ("status", status.as_str()),
+                        ],
+                    );
+
+                    // 429 means "slow down", not "provider is down"
+                    if status.is_server_error() {
+                        self.trip_on_failure();
+                    } else {
+                        self.breaker.record_success();
+                    }
+
+                    if !is_retryable(status) || attempt >= self.config.max_retries {
+                        return Ok(resp);
+                    }
+                    retry_after(&resp)
+                        .map(|d| d.min(self.config.backoff_max))
+                        .unwrap_or_else(|| self.config.backoff(attempt))
+                }
+                Err(e) => {
+                    metrics().inc_counter(
+                        "outbound_requests_total",
+                        &[("provider", self.provider), ("endpoint", endpoint), ("status", "error")],
+                    );
+                    self.trip_on_failure();
+
+                    if attempt >= self.config.max_retries {
+                        return Err(format!("Request failed: {}", e));
+                    }
+                    self.config.backoff(attempt)
+                }
+            };
+
+            attempt += 1;
+            metrics().inc_counter("outbound_retries_total", &labels);
+            println!(
+                "[OUTBOUND] 🔁 {} {} retry {}/{} in {:?}",
+                self.provider, endpoint, attempt, self.config.max_retries, delay
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    fn trip_on_failure(&self) {
+        if self.breaker.record_failure() {
+            println!(
+                "[OUTBOUND] ⛔ {} circuit opened for {:?}",
+                self.provider, self.config.breaker_cooldown
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::HeaderValue, response::IntoResponse, routing::get, Router};
+    use std::collections::VecDeque;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn config() -> OutboundConfig {
+        OutboundConfig {
+            connect_timeout: Duration::from_secs(1),
+            request_timeout: Duration::from_secs(2),
+            max_retries: 3,
+            backoff_base: Duration::from_millis(1),
+            backoff_max: Duration::from_millis(10),
+            breaker_threshold: 5,
+            breaker_cooldown: Duration::from_secs(30),
+        }
+    }
+
+    /// Mock answering `/` with the scripted statuses (and `Retry-After`s) in order, then 200.
+    /// Returns its URL and how many requests it received.
+    async fn scripted(responses: Vec<(u16, Option<&'static str>)>) -> (String, Arc<AtomicUsize>) {
+        let script = Arc::new(Mutex::new(VecDeque::from(responses)));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let url = crate::test_support::spawn_mock(Router::new().route(
+            "/",
+            get(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let next = script.lock().unwrap().pop_front();
+                async move {
+                    let (status, retry_after) = next.unwrap_or((200, None));
+                    let mut resp = axum::http::StatusCode::from_u16(status).unwrap().into_response();
+                    if let Some(value) = retry_after {
+                        resp.headers_mut().insert("retry-after", HeaderValue::from_static(value));
+                    }
+                    resp
+                }
+            }),
+        ))
+        .await;
+        (url, calls)
+    }
+
+    async fn get_status(client: &OutboundClient, url: &str) -> Result<u16, String> {
+        client.send("GET /", |c| c.get(url)).await.map(|r| r.status().as_u16())
+    }
+
+    #[tokio::test]
+    async fn retries_429_and_5xx_but_not_other_client_errors() {
+        let (url, calls) = scripted(vec![(503, None), (429, None), (502, None)]).await;
+        let client = OutboundClient::new("test", config());
+        assert_eq!(get_status(&client, &url).await, Ok(200));
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        let (url, calls) = scripted(vec![(400, None)]).await;
+        assert_eq!(get_status(&client, &url).await, Ok(400));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Out of retries: the last response is returned as-is
+        let (url, calls) = scripted(vec![(500, None); 5]).await;
+        let client = OutboundClient::new("test", OutboundConfig { max_retries: 2, ..config() });
+        assert_eq!(get_status(&client, &url).await, Ok(500));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn retry_after_overrides_backoff_and_is_capped() {
+        // Retry-After: 0 beats a 10s backoff
+        let (url, calls) = scripted(vec![(429, Some("0"))]).await;
+        let slow = OutboundConfig {
+            backoff_base: Duration::from_secs(10),
+            backoff_max: Duration::from_secs(10),
+            ..config()
+        };
+        let started = Instant::now();
+        assert_eq!(get_status(&OutboundClient::new("test", slow), &url).await, Ok(200));
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // An hour-long Retry-After waits no longer than backoff_max
+        let (url, _) = scripted(vec![(503, Some("3600"))]).await;
+        let capped = OutboundConfig { backoff_max: Duration::from_millis(100), ..config() };
+        let started = Instant::now();
+        assert_eq!(get_status(&OutboundClient::new("test", capped), &url).await, Ok(200));
+        let waited = started.elapsed();
+        assert!(waited >= Duration::from_millis(100), "waited {:?}", waited);
+        assert!(waited < Duration::from_secs(2), "waited {:?}", waited);
+    }
+
+    #[test]
+    fn backoff_is_full_jitter_below_the_exponential_ceiling() {
+        let config = OutboundConfig {
+            backoff_base: Duration::from_millis(100),
+            backoff_max: Duration::from_millis(1_000),
+            ..config()
+        };
+        for (attempt, ceiling_ms) in [(0, 100), (1, 200), (2, 400), (3, 800), (4, 1_000), (30, 1_000)] {
+            let ceiling = Duration::from_millis(ceiling_ms);
+            let samples: Vec<Duration> = (0..200).map(|_| config.backoff(attempt)).collect();
+            assert!(samples.iter().all(|d| *d <= ceiling), "attempt {}", attempt);
+            // Spread over the whole range rather than fixed
+            assert!(samples.iter().any(|d| *d < ceiling / 2), "attempt {}", attempt);
+            assert!(samples.iter().any(|d| *d > ceiling / 2), "attempt {}", attempt);
+        }
+    }
+
+    #[test]
+    fn breaker_opens_after_threshold_and_lets_one_probe_through() {
+        let breaker = CircuitBreaker::new(2, Duration::from_millis(50));
+
+        // Closed: a success resets the count
+        assert!(breaker.allow());
+        assert!(!breaker.record_failure());
+        breaker.record_success();
+        assert!(!breaker.record_failure());
+        assert!(breaker.record_failure());
+
+        // Open until the cooldown has passed
+        assert!(!breaker.allow());
+        std::thread::sleep(Duration::from_millis(60));
+
+        // Half-open: one probe; a failed probe reopens at once
+        assert!(breaker.allow());
+        assert!(!breaker.allow());
+        assert!(breaker.record_failure());
+        assert!(!breaker.allow());
+        std::thread::sleep(Duration::from_millis(60));
+
+        // A successful probe closes it
+        assert!(breaker.allow());
+        breaker.record_success();
+        assert!(breaker.allow());
+        assert!(breaker.allow());
+    }
+
+    #[tokio::test]
+    async fn open_circuit_rejects_without_calling_the_provider() {
+        let (url, calls) = scripted(vec![(429, None), (500, None), (500, None)]).await;
+        let client = OutboundClient::new(
+            "test",
+            OutboundConfig { max_retries: 0, breaker_threshold: 2, ..config() },
+        );
+
+        // 429 means "slow down" and does not count towards the breaker
+        assert_eq!(get_status(&client, &url).await, Ok(429));
+        assert_eq!(get_status(&client, &url).await, Ok(500));
+        assert_eq!(get_status(&client, &url).await, Ok(500));
+
+        let err = get_status(&client, &url).await.unwrap_err();
+        assert!(err.contains("circuit open"), "{}", err);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,941 @@
+// lwas_economy/src/payments/inbox.rs
+// ARCHITECT: QANTUM AETERNA | STATUS: BETA
+// Durable webhook inbox: verified events are persisted, acknowledged, then processed by workers
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Notify, RwLock};
+
+use crate::event_archive::ArchiveOutcome;
+use crate::metrics::{metrics, redis_fallback};
+use crate::runtime::{env_u64, instance_id, jittered_backoff};
+use crate::scheduler::UNLOCK_SCRIPT;
+use crate::stripe_handler::{process_event, StripeEvent, StripeWebhookState};
+use crate::stripe_models::StripeEventKind;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// INBOX CONFIGURATION
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Debug)]
+pub struct InboxConfig {
+    pub workers: usize,
+    /// Attempts before an event is parked as failed
+    pub max_attempts: u32,
+    pub backoff_base: Duration,
+    pub backoff_max: Duration,
+    /// How long a worker owns a customer's queue; must exceed the slowest event
+    pub lease: Duration,
+    /// Idle poll interval (new events wake workers immediately)
+    pub poll_interval: Duration,
+}
+
+impl InboxConfig {
+    pub fn from_env() -> Self {
+        Self {
+            workers: env_u64("INBOX_WORKERS", 4).max(1) as usize,
+            max_attempts: env_u64("INBOX_MAX_ATTEMPTS", 8).max(1) as u32,
+            backoff_base: Duration::from_secs(env_u64("INBOX_BACKOFF_BASE_SECS", 5)),
+            backoff_max: Duration::from_secs(env_u64("INBOX_BACKOFF_MAX_SECS", 3600)),
+            lease: Duration::from_secs(env_u64("INBOX_LEASE_SECS", 60)),
+            poll_interval: Duration::from_millis(env_u64("INBOX_POLL_MS", 1000)),
+        }
+    }
+
+    /// Delay before retrying after `attempts` failures
+    pub fn backoff(&self, attempts: u32) -> Duration {
+        jittered_backoff(self.backoff_base, self.backoff_max, attempts.saturating_sub(1))
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// INBOX ENTRIES
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InboxEntry {
+    pub event_id: String,
+    pub event_type: String,
+    /// Events sharing a key are processed one at a time, oldest `created` first
+    pub ordering_key: String,
+    pub created: i64,
+    /// The verified request body, byte for byte. Bodies that are not UTF-8 are not JSON and
+    /// are rejected before they get here, so the string holds exactly the delivered bytes.
+    pub payload: String,
+    pub received_at: DateTime<Utc>,
+    pub attempts: u32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    #[serde(default)]
+    pub history: Vec<AttemptRecord>,
+    #[serde(default)]
+    pub dead_lettered_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AttemptRecord {
+    pub at: DateTime<Utc>,
+    pub error: String,
+}
+
+impl InboxEntry {
+    pub fn new(event: &StripeEvent, payload: String, now: DateTime<Utc>) -> Self {
+        Self {
+            event_id: event.id.clone(),
+            event_type: event.event_type.clone(),
+            ordering_key: ordering_key(event),
+            created: event.created,
+            payload,
+            received_at: now,
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+            history: Vec::new(),
+            dead_lettered_at: None,
+        }
+    }
+}
+
+/// The customer an event concerns, else its object (refunds/disputes only name a charge)
+fn ordering_key(event: &StripeEvent) -> String {
+    let kind = StripeEventKind::from_event(event).ok();
+    kind.as_ref()
+        .and_then(|k| k.customer_id().map(|c| format!("customer:{}", c)))
+        .or_else(|| event.data.object["charge"].as_str().map(|c| format!("charge:{}", c)))
+        .or_else(|| kind.as_ref().and_then(|k| k.object_id()).map(|o| format!("object:{}", o)))
+        .unwrap_or_else(|| "global".to_string())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// INBOX STORE
+// ═══════════════════════════════════════════════════════════════════════════════
+// Redis layout:
+//   inbox:entry:<event_id>   JSON InboxEntry
+//   inbox:queue:<key>        ZSET of event ids scored by `created`
+//   inbox:keys               SET of ordering keys with queued events
+//   inbox:lock:<key>         worker owning the key's queue (SET NX PX)
+//   inbox:dead_letters       HASH event_id -> JSON InboxEntry (retries exhausted)
+
+/// Persist and enqueue atomically; a redelivered event already in the inbox is a no-op
+const ENQUEUE_SCRIPT: &str = r#"
+if redis.call('set', KEYS[1], ARGV[1], 'NX') then
+    redis.call('zadd', KEYS[2], ARGV[2], ARGV[3])
+    redis.call('sadd', KEYS[3], ARGV[4])
+    return 1
+end
+return 0
+"#;
+
+/// Remove an event from its queue, dropping the key once its queue is empty
+const DEQUEUE_SCRIPT: &str = r#"
+redis.call('zrem', KEYS[1], ARGV[1])
+redis.call('del', KEYS[2])
+if redis.call('zcard', KEYS[1]) == 0 then
+    redis.call('srem', KEYS[3], ARGV[2])
+end
+return 1
+"#;
+
+/// HGET + HDEL, so concurrent replays cannot requeue the same dead letter twice
+const TAKE_SCRIPT: &str = r#"
+local value = redis.call('hget', KEYS[1], ARGV[1])
+if value then
+    redis.call('hdel', KEYS[1], ARGV[1])
+end
+return value
+"#;
+
+#[derive(Default)]
+struct MemoryInbox {
+    entries: HashMap<String, InboxEntry>,
+    queues: HashMap<String, BTreeSet<(i64, String)>>,
+    locked: HashSet<String>,
+    dead_letters: HashMap<String, InboxEntry>,
+}
+
+#[derive(Clone)]
+pub struct WebhookInbox {
+    redis_client: Option<redis::Client>,
+    fallback: Arc<RwLock<MemoryInbox>>,
+    pub notify: Arc<Notify>,
+}
+
+fn redis_err(e: redis::RedisError) -> String {
+    format!("Redis error: {}", e)
+}
+
+impl WebhookInbox {
+    pub fn new(redis_url: Option<String>) -> Self {
+        let redis_client = redis_url.and_then(|url| {
+            redis::Client::open(url).map_err(|e| println!("❌ Redis connect error: {}", e)).ok()
+        });
+        if redis_client.is_none() {
+            println!("⚠️ No REDIS_URL: webhook inbox is in-memory and lost on restart");
+        }
+
+        Self {
+            redis_client,
+            fallback: Arc::new(RwLock::new(MemoryInbox::default())),
+            notify: Arc::new(Notify::new()),
+        }
+    }
+
+    async fn connection(&self) -> Result<Option<redis::aio::MultiplexedConnection>, String> {
+        match &self.redis_client {
+            Some(client) => client.get_multiplexed_async_connection().await.map(Some).map_err(redis_err),
+            None => Ok(None),
+        }
+    }
+
+    /// Persist a verified event. Returns `false` when it is already in the inbox.
+    pub async fn enqueue(&self, entry: InboxEntry) -> Result<bool, String> {
+        let added = if let Some(mut con) = self.connection().await? {
+            let json = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+            let added: i64 = redis::Script::new(ENQUEUE_SCRIPT)
+                .key(format!("inbox:entry:{}", entry.event_id))
+                .key(format!("inbox:queue:{}", entry.ordering_key))
+                .key("inbox:keys")
+                .arg(json)
+                .arg(entry.created)
+                .arg(&entry.event_id)
+                .arg(&entry.ordering_key)
+                .invoke_async(&mut con)
+                .await
+                .map_err(redis_err)?;
+            added == 1
+        } else {
+            // Counted per event taken in, not per worker poll
+            redis_fallback("inbox", false);
+            let mut inbox = self.fallback.write().await;
+            if inbox.entries.contains_key(&entry.event_id) {
+                false
+            } else {
+                inbox
+                    .queues
+                    .entry(entry.ordering_key.clone())
+                    .or_default()
+                    .insert((entry.created, entry.event_id.clone()));
+                inbox.entries.insert(entry.event_id.clone(), entry);
+                true
+            }
+        };
+
+        if added {
+            metrics().inc_counter("inbox_enqueued_total", &[]);
+            self.notify.notify_one();
+        }
+        Ok(added)
+    }
+
+    /// Claim the head event of some ordering key whose retry time has come. The key stays
+    /// locked (no other worker touches its queue) until `complete`, `retry_later` or `park`.
+    pub async fn claim_next(
+        &self,
+        now: DateTime<Utc>,
+        worker_id: &str,
+        lease: Duration,
+    ) -> Result<Option<InboxEntry>, String> {
+        let Some(mut con) = self.connection().await? else {
+            let mut inbox = self.fallback.write().await;
+            let next = inbox
+                .queues
+                .iter()
+                .filter(|(key, _)| !inbox.locked.contains(*key))
+                .filter_map(|(_, queue)| queue.first())
+                .filter_map(|(_, id)| inbox.entries.get(id))
+                .find(|entry| entry.next_attempt_at <= now)
+                .cloned();
+            if let Some(entry) = &next {
+                inbox.locked.insert(entry.ordering_key.clone());
+            }
+            return Ok(next);
+        };
+
+        let keys: Vec<String> = redis::cmd("SMEMBERS")
+            .arg("inbox:keys")
+            .query_async(&mut con)
+            .await
+            .map_err(redis_err)?;
+
+        for key in keys {
+            let lock = format!("inbox:lock:{}", key);
+            let acquired: Option<String> = redis::cmd("SET")
+                .arg(&lock)
+                .arg(worker_id)
+                .arg("NX")
+                .arg("PX")
+                .arg(lease.as_millis() as u64)
+                .query_async(&mut con)
+                .await
+                .map_err(redis_err)?;
+            if acquired.is_none() {
+                continue;
+            }
+
+            let head: Vec<String> = redis::cmd("ZRANGE")
+                .arg(format!("inbox:queue:{}", key))
+                .arg(0)
+                .arg(0)
+                .query_async(&mut con)
+                .await
+                .map_err(redis_err)?;
+            let entry = match head.first() {
+                Some(id) => {
+                    let raw: Option<String> = redis::cmd("GET")
+                        .arg(format!("inbox:entry:{}", id))
+                        .query_async(&mut con)
+                        .await
+                        .map_err(redis_err)?;
+                    raw.and_then(|r| serde_json::from_str::<InboxEntry>(&r).ok())
+                }
+                None => None,
+            };
+
+            match entry {
+                Some(entry) if entry.next_attempt_at <= now => return Ok(Some(entry)),
+                _ => self.unlock(&mut con, &key, worker_id).await?,
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn unlock(
+        &self,
+        con: &mut redis::aio::MultiplexedConnection,
+        key: &str,
+        worker_id: &str,
+    ) -> Result<(), String> {
+        redis::Script::new(UNLOCK_SCRIPT)
+            .key(format!("inbox:lock:{}", key))
+            .arg(worker_id)
+            .invoke_async::<_, i64>(con)
+            .await
+            .map(|_| ())
+            .map_err(redis_err)
+    }
+
+    /// Remove a claimed entry from its queue and release the key
+    async fn dequeue(&self, entry: &InboxEntry, worker_id: &str) -> Result<(), String> {
+        if let Some(mut con) = self.connection().await? {
+            redis::Script::new(DEQUEUE_SCRIPT)
+                .key(format!("inbox:queue:{}", entry.ordering_key))
+                .key(format!("inbox:entry:{}", entry.event_id))
+                .key("inbox:keys")
+                .arg(&entry.event_id)
+                .arg(&entry.ordering_key)
+                .invoke_async::<_, i64>(&mut con)
+                .await
+                .map_err(redis_err)?;
+            return self.unlock(&mut con, &entry.ordering_key, worker_id).await;
+        }
+
+        let mut inbox = self.fallback.write().await;
+        inbox.entries.remove(&entry.event_id);
+        if let Some(queue) = inbox.queues.get_mut(&entry.ordering_key) {
+            queue.remove(&(entry.created, entry.event_id.clone()));
+            if queue.is_empty() {
+                inbox.queues.remove(&entry.ordering_key);
+            }
+        }
+        inbox.locked.remove(&entry.ordering_key);
+        Ok(())
+    }
+
+    /// Processed: drop the entry and let the key's next event through. A dead-lettered copy
+    /// (Stripe redelivered the event after we gave up on it) is resolved too.
+    pub async fn complete(&self, entry: &InboxEntry, worker_id: &str) -> Result<(), String> {
+        self.dequeue(entry, worker_id).await?;
+        self.take_dead_letter(&entry.event_id).await.map(|_| ())
+    }
+
+    /// Failed but retryable: keep the entry at the head of its queue until `next_attempt_at`
+    pub async fn retry_later(&self, entry: &InboxEntry, worker_id: &str) -> Result<(), String> {
+        if let Some(mut con) = self.connection().await? {
+            let json = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+            redis::cmd("SET")
+                .arg(format!("inbox:entry:{}", entry.event_id))
+                .arg(json)
+                .arg("XX")
+                .query_async::<_, Option<String>>(&mut con)
+                .await
+                .map_err(redis_err)?;
+            return self.unlock(&mut con, &entry.ordering_key, worker_id).await;
+        }
+
+        let mut inbox = self.fallback.write().await;
+        if let Some(stored) = inbox.entries.get_mut(&entry.event_id) {
+            *stored = entry.clone();
+        }
+        inbox.locked.remove(&entry.ordering_key);
+        Ok(())
+    }
+
+    /// Retries exhausted: move the entry to the dead-letter store so the key's later events can proceed
+    pub async fn dead_letter(&self, entry: &InboxEntry, worker_id: &str) -> Result<(), String> {
+        if let Some(mut con) = self.connection().await? {
+            let json = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+            redis::cmd("HSET")
+                .arg("inbox:dead_letters")
+                .arg(&entry.event_id)
+                .arg(json)
+                .query_async::<_, i64>(&mut con)
+                .await
+                .map_err(redis_err)?;
+        } else {
+            self.fallback
+                .write()
+                .await
+                .dead_letters
+                .insert(entry.event_id.clone(), entry.clone());
+        }
+        self.dequeue(entry, worker_id).await
+    }
+
+    pub async fn dead_letters(&self) -> Result<Vec<InboxEntry>, String> {
+        let mut entries: Vec<InboxEntry> = if let Some(mut con) = self.connection().await? {
+            let raw: Vec<String> = redis::cmd("HVALS")
+                .arg("inbox:dead_letters")
+                .query_async(&mut con)
+                .await
+                .map_err(redis_err)?;
+            raw.iter().filter_map(|r| serde_json::from_str(r).ok()).collect()
+        } else {
+            self.fallback.read().await.dead_letters.values().cloned().collect()
+        };
+
+        entries.sort_by_key(|e| e.dead_lettered_at);
+        Ok(entries)
+    }
+
+    pub async fn get_dead_letter(&self, event_id: &str) -> Result<Option<InboxEntry>, String> {
+        if let Some(mut con) = self.connection().await? {
+            let raw: Option<String> = redis::cmd("HGET")
+                .arg("inbox:dead_letters")
+                .arg(event_id)
+                .query_async(&mut con)
+                .await
+                .map_err(redis_err)?;
+            return Ok(raw.and_then(|r| serde_json::from_str(&r).ok()));
+        }
+
+        Ok(self.fallback.read().await.dead_letters.get(event_id).cloned())
+    }
+
+    /// Remove a dead letter, returning it if it was there
+    pub async fn take_dead_letter(&self, event_id: &str) -> Result<Option<InboxEntry>, String> {
+        if let Some(mut con) = self.connection().await? {
+            let raw: Option<String> = redis::Script::new(TAKE_SCRIPT)
+                .key("inbox:dead_letters")
+                .arg(event_id)
+                .invoke_async(&mut con)
+                .await
+                .map_err(redis_err)?;
+            return Ok(raw.and_then(|r| serde_json::from_str(&r).ok()));
+        }
+
+        Ok(self.fallback.write().await.dead_letters.remove(event_id))
+    }
+
+    /// Put a dead letter back in the inbox with a fresh attempt budget (history is kept).
+    /// Returns `false` when the event is already queued, e.g. redelivered by Stripe.
+    pub async fn requeue(&self, mut entry: InboxEntry, now: DateTime<Utc>) -> Result<bool, String> {
+        entry.attempts = 0;
+        entry.next_attempt_at = now;
+        entry.dead_lettered_at = None;
+        self.enqueue(entry).await
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// WORKER POOL
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Process one due inbox event, if any. Returns whether an event was claimed.
+pub async fn work_once(state: &StripeWebhookState, worker_id: &str) -> Result<bool, String> {
+    let config = &state.config.inbox;
+    let Some(mut entry) = state.inbox.claim_next(state.clock.now(), worker_id, config.lease).await? else {
+        return Ok(false);
+    };
+
+    if entry.attempts == 0 {
+        let waited = (state.clock.now() - entry.received_at).num_milliseconds().max(0) as f64 / 1000.0;
+        metrics().observe("inbox_queue_delay_seconds", &[], waited);
+    }
+
+    let started = Instant::now();
+    let result = match serde_json::from_str::<StripeEvent>(&entry.payload) {
+        Ok(event) => process_event(state, event).await,
+        Err(e) => Err(format!("Stored payload no longer parses: {}", e)),
+    };
+    metrics().observe(
+        "webhook_processing_duration_seconds",
+        &[("provider", "stripe"), ("event_type", &entry.event_type)],
+        started.elapsed().as_secs_f64(),
+    );
+
+    entry.attempts += 1;
+    if let Err(e) = &result {
+        entry.history.push(AttemptRecord {
+            at: state.clock.now(),
+            error: e.clone(),
+        });
+    }
+    match result {
+        Ok(()) => {
+            metrics().inc_counter("inbox_processed_total", &[("outcome", "success")]);
+            state.inbox.complete(&entry, worker_id).await?;
+            state.archive.set_outcome(&entry.event_id, ArchiveOutcome::Processed, None, state.clock.now()).await;
+        }
+        Err(e) if entry.attempts >= config.max_attempts => {
+            metrics().inc_counter("inbox_processed_total", &[("outcome", "dead_lettered")]);
+            println!(
+                "[INBOX] ☠️ {} ({}) failed {} times, dead-lettered: {}",
+                entry.event_id, entry.event_type, entry.attempts, e
+            );
+            state.archive.set_outcome(&entry.event_id, ArchiveOutcome::DeadLettered, Some(e.clone()), state.clock.now()).await;
+            state.alerter.processing_failed(&entry.event_id, &entry.event_type, &e, state.clock.now());
+            entry.last_error = Some(e);
+            entry.dead_lettered_at = Some(state.clock.now());
+            state.inbox.dead_letter(&entry, worker_id).await?;
+        }
+        Err(e) => {
+            metrics().inc_counter("inbox_processed_total", &[("outcome", "retry")]);
+            let delay = config.backoff(entry.attempts);
+            println!(
+                "[INBOX] 🔁 {} ({}) attempt {}/{} failed, retry in {:?}: {}",
+                entry.event_id, entry.event_type, entry.attempts, config.max_attempts, delay, e
+            );
+            state.archive.set_outcome(&entry.event_id, ArchiveOutcome::Retrying, Some(e.clone()), state.clock.now()).await;
+            state.alerter.processing_failed(&entry.event_id, &entry.event_type, &e, state.clock.now());
+            entry.last_error = Some(e);
+            entry.next_attempt_at = state.clock.now()
+                + chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::seconds(60));
+            state.inbox.retry_later(&entry, worker_id).await?;
+        }
+    }
+
+    Ok(true)
+}
+
+async fn worker(state: Arc<StripeWebhookState>, worker_id: String) {
+    loop {
+        match work_once(&state, &worker_id).await {
+            Ok(true) => continue,
+            Ok(false) => {}
+            Err(e) => println!("[INBOX] ❌ {}: {}", worker_id, e),
+        }
+        tokio::select! {
+            _ = state.inbox.notify.notified() => {}
+            _ = tokio::time::sleep(state.config.inbox.poll_interval) => {}
+        }
+    }
+}
+
+/// Start `INBOX_WORKERS` workers draining the inbox
+pub fn spawn_workers(state: Arc<StripeWebhookState>) {
+    for n in 0..state.config.inbox.workers {
+        tokio::spawn(worker(state.clone(), format!("{}#{}", instance_id(), n)));
+    }
+    println!("[INBOX] 👷 {} workers started", state.config.inbox.workers);
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DEAD-LETTER ADMIN
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Selects dead letters by event type and by when Stripe delivered them
+#[derive(Deserialize, Default)]
+pub struct DeadLetterFilter {
+    pub event_type: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl DeadLetterFilter {
+    fn matches(&self, entry: &InboxEntry) -> bool {
+        self.event_type.as_ref().is_none_or(|t| *t == entry.event_type)
+            && self.since.is_none_or(|since| entry.received_at >= since)
+            && self.until.is_none_or(|until| entry.received_at < until)
+    }
+}
+
+#[derive(Serialize)]
+pub struct DeadLetterSummary {
+    pub event_id: String,
+    pub event_type: String,
+    pub ordering_key: String,
+    pub received_at: DateTime<Utc>,
+    pub dead_lettered_at: Option<DateTime<Utc>>,
+    pub attempts: usize,
+    pub last_error: Option<String>,
+}
+
+impl From<InboxEntry> for DeadLetterSummary {
+    fn from(entry: InboxEntry) -> Self {
+        Self {
+            event_id: entry.event_id,
+            event_type: entry.event_type,
+            ordering_key: entry.ordering_key,
+            received_at: entry.received_at,
+            dead_lettered_at: entry.dead_lettered_at,
+            attempts: entry.history.len(),
+            last_error: entry.last_error,
+        }
+    }
+}
+
+pub async fn list_dead_letters(
+    State(state): State<Arc<StripeWebhookState>>,
+    headers: HeaderMap,
+    Query(filter): Query<DeadLetterFilter>,
+) -> impl IntoResponse {
+    if let Err(e) = state.auth.require_admin(&headers) {
+        return e.into_response();
+    }
+
+    match state.inbox.dead_letters().await {
+        Ok(entries) => Json(
+            entries
+                .into_iter()
+                .filter(|e| filter.matches(e))
+                .map(DeadLetterSummary::from)
+                .collect::<Vec<_>>(),
+        )
+        .into_response(),
+        Err(e) => (StatusCode::SERVICE_UNAVAILABLE, e).into_response(),
+    }
+}
+
+/// Full entry: payload, attempt history and errors
+pub async fn get_dead_letter(
+    State(state): State<Arc<StripeWebhookState>>,
+    headers: HeaderMap,
+    Path(event_id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(e) = state.auth.require_admin(&headers) {
+        return e.into_response();
+    }
+
+    match state.inbox.get_dead_letter(&event_id).await {
+        Ok(Some(entry)) => Json(entry).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "No such dead letter").into_response(),
+        Err(e) => (StatusCode::SERVICE_UNAVAILABLE, e).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RetryDeadLetterRequest {
+    /// Corrected event to process instead of the stored one; must keep the event id.
+    /// Ignored in effect when `STRIPE_VERIFY_BY_REFETCH` is on, as the API copy wins.
+    pub payload: Option<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+pub struct ReplayResponse {
+    pub replayed: Vec<String>,
+}
+
+/// Requeue one dead letter, optionally with an edited payload
+pub async fn retry_dead_letter(
+    State(state): State<Arc<StripeWebhookState>>,
+    headers: HeaderMap,
+    Path(event_id): Path<String>,
+    body: Option<Json<RetryDeadLetterRequest>>,
+) -> impl IntoResponse {
+    if let Err(e) = state.auth.require_admin(&headers) {
+        return e.into_response();
+    }
+
+    let edited = match body.and_then(|Json(b)| b.payload) {
+        Some(payload) => match serde_json::from_value::<StripeEvent>(payload.clone()) {
+            Ok(event) if event.id != event_id => {
+                return (StatusCode::BAD_REQUEST, "Edited payload must keep the event id").into_response()
+            }
+            Ok(event) if event.livemode != state.config.expected_livemode() => {
+                return (StatusCode::BAD_REQUEST, "Edited payload livemode does not match configured key")
+                    .into_response()
+            }
+            Ok(event) => Some((event, payload.to_string())),
+            Err(e) => return (StatusCode::BAD_REQUEST, format!("Invalid event: {}", e)).into_response(),
+        },
+        None => None,
+    };
+
+    let mut entry = match state.inbox.take_dead_letter(&event_id).await {
+        Ok(Some(entry)) => entry,
+        Ok(None) => return (StatusCode::NOT_FOUND, "No such dead letter").into_response(),
+        Err(e) => return (StatusCode::SERVICE_UNAVAILABLE, e).into_response(),
+    };
+
+    let source = if let Some((event, payload)) = edited {
+        let history = std::mem::take(&mut entry.history);
+        entry = InboxEntry {
+            history,
+            last_error: entry.last_error,
+            ..InboxEntry::new(&event, payload, entry.received_at)
+        };
+        "edit"
+    } else {
+        "retry"
+    };
+
+    println!("[INBOX] ♻️ Dead letter {} requeued ({})", event_id, source);
+    if let Err(e) = state.inbox.requeue(entry.clone(), state.clock.now()).await {
+        // Don't lose it: put it back where it was
+        let _ = state.inbox.dead_letter(&entry, "admin").await;
+        return (StatusCode::SERVICE_UNAVAILABLE, e).into_response();
+    }
+    metrics().inc_counter("inbox_replayed_total", &[("source", source)]);
+    state.archive.set_outcome(&event_id, ArchiveOutcome::Queued, None, state.clock.now()).await;
+
+    (StatusCode::ACCEPTED, Json(ReplayResponse { replayed: vec![event_id] })).into_response()
+}
+
+/// Drop a dead letter for good
+pub async fn discard_dead_letter(
+    State(state): State<Arc<StripeWebhookState>>,
+    headers: HeaderMap,
+    Path(event_id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(e) = state.auth.require_admin(&headers) {
+        return e.into_response();
+    }
+
+    match state.inbox.take_dead_letter(&event_id).await {
+        Ok(Some(entry)) => {
+            println!("[INBOX] 🗑️ Dead letter {} ({}) discarded", entry.event_id, entry.event_type);
+            state.archive.set_outcome(&entry.event_id, ArchiveOutcome::Discarded, None, state.clock.now()).await;
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Ok(None) => (StatusCode::NOT_FOUND, "No such dead letter").into_response(),
+        Err(e) => (StatusCode::SERVICE_UNAVAILABLE, e).into_response(),
+    }
+}
+
+/// Requeue every dead letter matching the filter, e.g. everything from an outage window
+pub async fn replay_dead_letters(
+    State(state): State<Arc<StripeWebhookState>>,
+    headers: HeaderMap,
+    Json(filter): Json<DeadLetterFilter>,
+) -> impl IntoResponse {
+    if let Err(e) = state.auth.require_admin(&headers) {
+        return e.into_response();
+    }
+
+    let entries = match state.inbox.dead_letters().await {
+        Ok(entries) => entries,
+        Err(e) => return (StatusCode::SERVICE_UNAVAILABLE, e).into_response(),
+    };
+
+    let mut replayed = Vec::new();
+    for candidate in entries.into_iter().filter(|e| filter.matches(e)) {
+        // Re-read via take: another admin may have replayed or discarded it meanwhile
+        let entry = match state.inbox.take_dead_letter(&candidate.event_id).await {
+            Ok(Some(entry)) => entry,
+            Ok(None) => continue,
+            Err(e) => return (StatusCode::SERVICE_UNAVAILABLE, e).into_response(),
+        };
+        if let Err(e) = state.inbox.requeue(entry.clone(), state.clock.now()).await {
+            let _ = state.inbox.dead_letter(&entry, "admin").await;
+            return (StatusCode::SERVICE_UNAVAILABLE, e).into_response();
+        }
+        metrics().inc_counter("inbox_replayed_total", &[("source", "bulk")]);
+        state.archive.set_outcome(&entry.event_id, ArchiveOutcome::Queued, None, state.clock.now()).await;
+        replayed.push(entry.event_id);
+    }
+
+    println!("[INBOX] ♻️ Bulk replay requeued {} dead letters", replayed.len());
+    (StatusCode::ACCEPTED, Json(ReplayResponse { replayed })).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, FixedClock};
+
+    fn stripe_event(id: &str, created: i64, customer: &str) -> (StripeEvent, String) {
+        let raw = serde_json::json!({
+            "id": id, "type": "customer.updated", "created": created, "livemode": false,
+            "data": { "object": { "id": customer, "object": "customer" } }
+        })
+        .to_string();
+        (serde_json::from_str(&raw).unwrap(), raw)
+    }
+
+    async fn enqueue(inbox: &WebhookInbox, id: &str, created: i64, customer: &str) {
+        let (event, raw) = stripe_event(id, created, customer);
+        inbox.enqueue(InboxEntry::new(&event, raw, DateTime::from_timestamp(0, 0).unwrap())).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn claims_oldest_per_customer_one_at_a_time() {
+        let inbox = WebhookInbox::new(None);
+        let now = Utc::now();
+        let lease = Duration::from_secs(60);
+        enqueue(&inbox, "evt_b2", 200, "cus_b").await;
+        enqueue(&inbox, "evt_a1", 100, "cus_a").await;
+        enqueue(&inbox, "evt_b1", 100, "cus_b").await;
+        enqueue(&inbox, "evt_b1", 100, "cus_b").await;
+
+        let first = inbox.claim_next(now, "w1", lease).await.unwrap().unwrap();
+        let second = inbox.claim_next(now, "w2", lease).await.unwrap().unwrap();
+        let mut claimed = [first.event_id.clone(), second.event_id.clone()];
+        claimed.sort();
+        assert_eq!(claimed, ["evt_a1", "evt_b1"]);
+        // Both keys are busy
+        assert!(inbox.claim_next(now, "w3", lease).await.unwrap().is_none());
+
+        let b1 = if first.event_id == "evt_b1" { first } else { second };
+        inbox.complete(&b1, "w1").await.unwrap();
+        let next = inbox.claim_next(now, "w3", lease).await.unwrap().unwrap();
+        assert_eq!(next.event_id, "evt_b2");
+    }
+
+    fn memory_state(clock: Arc<FixedClock>) -> StripeWebhookState {
+        let mut state = StripeWebhookState::new(crate::test_support::authenticator());
+        state.config.redis_url = None;
+        state.inbox = WebhookInbox::new(None);
+        state.clock = clock;
+        state.config.inbox.max_attempts = 3;
+        state.config.inbox.backoff_base = Duration::from_secs(10);
+        state.config.inbox.backoff_max = Duration::from_secs(15);
+        state
+    }
+
+    /// Without an email (none on the invoice, unknown customer) these fail every time
+    fn invoice_event(id: &str, event_type: &str, email: Option<&str>) -> serde_json::Value {
+        serde_json::json!({
+            "id": id, "type": event_type, "created": 100, "livemode": false,
+            "data": { "object": {
+                "id": "in_1", "customer": "cus_x", "customer_email": email, "amount_due": 900,
+                "amount_paid": 900, "currency": "eur", "attempt_count": 1
+            }}
+        })
+    }
+
+    async fn dead_letter_all(state: &StripeWebhookState, clock: &FixedClock, events: &[serde_json::Value]) {
+        for event in events {
+            let parsed: StripeEvent = serde_json::from_value(event.clone()).unwrap();
+            state.inbox.enqueue(InboxEntry::new(&parsed, event.to_string(), clock.now())).await.unwrap();
+        }
+        // Same customer: each event only starts once the one before it is dead-lettered
+        for _ in 0..state.config.inbox.max_attempts as usize * events.len() {
+            while work_once(state, "w").await.unwrap() {}
+            clock.set(clock.now() + chrono::Duration::seconds(60));
+        }
+    }
+
+    fn admin_headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer admin_test_token".parse().unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn failing_event_is_retried_with_backoff_then_dead_lettered() {
+        let clock = FixedClock::at(DateTime::from_timestamp(1_000, 0).unwrap());
+        let state = memory_state(clock.clone());
+        let raw = invoice_event("evt_fail", "invoice.paid", None);
+        let event: StripeEvent = serde_json::from_value(raw.clone()).unwrap();
+        state.inbox.enqueue(InboxEntry::new(&event, raw.to_string(), clock.now())).await.unwrap();
+
+        assert!(work_once(&state, "w").await.unwrap());
+        // Due again within the jittered backoff: at most the base, then at most the cap
+        let retry_at = |state: &StripeWebhookState| {
+            let inbox = state.inbox.clone();
+            async move { inbox.fallback.read().await.entries["evt_fail"].next_attempt_at.timestamp() }
+        };
+        assert!((1_000..=1_010).contains(&retry_at(&state).await));
+        clock.set(DateTime::from_timestamp(1_010, 0).unwrap());
+        assert!(work_once(&state, "w").await.unwrap());
+        assert!((1_010..=1_025).contains(&retry_at(&state).await));
+        clock.set(DateTime::from_timestamp(1_025, 0).unwrap());
+        assert!(work_once(&state, "w").await.unwrap());
+
+        let dead = state.inbox.dead_letters().await.unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].attempts, 3);
+        assert_eq!(dead[0].dead_lettered_at, Some(clock.now()));
+        let history: Vec<i64> = dead[0].history.iter().map(|a| a.at.timestamp()).collect();
+        assert_eq!(history, [1_000, 1_010, 1_025]);
+        assert!(dead[0].last_error.as_deref().unwrap().contains("No email"));
+        assert!(!work_once(&state, "w").await.unwrap());
+        assert!(!state.idempotency.is_processed("evt_fail").await);
+    }
+
+    #[tokio::test]
+    async fn edited_dead_letter_is_reprocessed() {
+        let clock = FixedClock::at(DateTime::from_timestamp(1_000, 0).unwrap());
+        let state = Arc::new(memory_state(clock.clone()));
+        dead_letter_all(&state, &clock, &[invoice_event("evt_edit", "invoice.paid", None)]).await;
+
+        // The id is what makes it the same event
+        let wrong_id = RetryDeadLetterRequest {
+            payload: Some(invoice_event("evt_other", "invoice.paid", Some("ada@example.com"))),
+        };
+        let resp = retry_dead_letter(State(state.clone()), admin_headers(), Path("evt_edit".into()), Some(Json(wrong_id)))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let fixed = RetryDeadLetterRequest {
+            payload: Some(invoice_event("evt_edit", "invoice.paid", Some("ada@example.com"))),
+        };
+        let resp = retry_dead_letter(State(state.clone()), admin_headers(), Path("evt_edit".into()), Some(Json(fixed)))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        assert!(state.inbox.dead_letters().await.unwrap().is_empty());
+
+        assert!(work_once(&state, "w").await.unwrap());
+        assert!(state.idempotency.is_processed("evt_edit").await);
+    }
+
+    #[tokio::test]
+    async fn bulk_replay_requeues_only_matching_dead_letters() {
+        let clock = FixedClock::at(DateTime::from_timestamp(1_000, 0).unwrap());
+        let state = Arc::new(memory_state(clock.clone()));
+        dead_letter_all(
+            &state,
+            &clock,
+            &[
+                invoice_event("evt_paid", "invoice.paid", None),
+                invoice_event("evt_failed", "invoice.payment_failed", None),
+            ],
+        )
+        .await;
+
+        let filter = DeadLetterFilter {
+            event_type: Some("invoice.payment_failed".into()),
+            ..Default::default()
+        };
+        let resp = replay_dead_letters(State(state.clone()), admin_headers(), Json(filter))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+
+        let remaining = state.inbox.dead_letters().await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].event_id, "evt_paid");
+        let requeued = state.inbox.claim_next(clock.now(), "w", Duration::from_secs(60)).await.unwrap().unwrap();
+        assert_eq!(requeued.event_id, "evt_failed");
+        assert_eq!(requeued.attempts, 0);
+        assert_eq!(requeued.history.len(), 3);
+
+        let resp = discard_dead_letter(State(state.clone()), admin_headers(), Path("evt_paid".into()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(state.inbox.dead_letters().await.unwrap().is_empty());
+    }
+}
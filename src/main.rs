@@ -8,11 +8,48 @@ use tokio::signal;
 use tower_http::trace::TraceLayer;
 use dotenv::dotenv;
 
+mod alerting;
+mod auth;
+mod clock;
+mod currency;
+mod dunning;
+mod event_archive;
+mod event_stream;
+mod events;
+mod http_client;
+mod inbox;
+mod metrics;
+mod notifications;
+mod outbound_webhooks;
+mod reconciliation;
+mod runtime;
+mod scheduler;
+mod stripe_api;
+mod stripe_disputes;
 mod stripe_handler;
+mod stripe_models;
+#[cfg(test)]
+mod test_support;
 mod paypal_handler;
 
-use stripe_handler::{create_portal_session, stripe_webhook_handler, StripeWebhookState};
-use paypal_handler::{paypal_webhook_handler, PayPalState};
+use auth::Authenticator;
+use event_archive::get_archived_event;
+use event_stream::stream_events;
+use metrics::metrics_handler;
+use outbound_webhooks::{list_webhook_deliveries, list_webhook_endpoints, retry_webhook_delivery};
+use stripe_handler::{
+    create_checkout_session, create_portal_session, create_refund, get_subscription,
+    stripe_webhook_handler, StripeWebhookState,
+};
+use stripe_disputes::{list_stripe_disputes, submit_stripe_dispute_evidence};
+use reconciliation::run_reconciliation;
+use inbox::{
+    discard_dead_letter, get_dead_letter, list_dead_letters, replay_dead_letters, retry_dead_letter,
+};
+use paypal_handler::{
+    accept_dispute_claim, list_disputes, paypal_webhook_handler, submit_dispute_evidence,
+    PayPalState,
+};
 
 #[tokio::main]
 async fn main() {
@@ -23,25 +60,80 @@ async fn main() {
     tracing_subscriber::fmt::init();
 
     // Load states
-    let stripe_state = Arc::new(StripeWebhookState::new());
-    let paypal_state = Arc::new(PayPalState::new());
+    let authenticator = Authenticator::from_env();
+    let stripe_state = Arc::new(StripeWebhookState::new(authenticator));
+    let paypal_state = Arc::new(PayPalState::new(
+        stripe_state.auth.clone(),
+        stripe_state.subscriptions.clone(),
+        stripe_state.paypal_disputes.clone(),
+        stripe_state.archive.clone(),
+        stripe_state.events.clone(),
+        stripe_state.notifier.clone(),
+        stripe_state.alerter.clone(),
+    ));
+
+    // Never run live with placeholder credentials
+    for check in [stripe_state.config.validate(), paypal_state.config.validate()] {
+        if let Err(e) = check {
+            eprintln!("❌ Refusing to start: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    // Time-based transitions: lapsed periods, trials, dunning, dispute deadlines, reconciliation, alerts, cleanup
+    tokio::spawn(scheduler::Scheduler::new(stripe_state.clone()).run());
+
+    // Webhooks are acknowledged once persisted; workers process them per customer, in order
+    inbox::spawn_workers(stripe_state.clone());
+
+    // Normalized billing events out to our own services
+    outbound_webhooks::spawn(stripe_state.clone());
 
     // Build Stripe sub-router
     let stripe_router = Router::new()
         .route("/webhook", post(stripe_webhook_handler))
         .route("/portal", post(create_portal_session))
-        .with_state(stripe_state);
+        .route("/checkout", post(create_checkout_session))
+        .route("/subscription", get(get_subscription))
+        .route("/refunds", post(create_refund))
+        .route("/disputes", get(list_stripe_disputes))
+        .route("/disputes/:id/evidence", post(submit_stripe_dispute_evidence))
+        .route("/dead-letters", get(list_dead_letters))
+        .route("/dead-letters/replay", post(replay_dead_letters))
+        .route("/dead-letters/:id", get(get_dead_letter).delete(discard_dead_letter))
+        .route("/dead-letters/:id/retry", post(retry_dead_letter))
+        .route("/reconcile", post(run_reconciliation))
+        .with_state(stripe_state.clone());
 
     // Build PayPal sub-router
     let paypal_router = Router::new()
         .route("/webhook", post(paypal_webhook_handler))
+        .route("/disputes", get(list_disputes))
+        .route("/disputes/:id/evidence", post(submit_dispute_evidence))
+        .route("/disputes/:id/accept", post(accept_dispute_claim))
         .with_state(paypal_state);
 
+    // Outbound webhook endpoints and their delivery logs
+    let webhooks_router = Router::new()
+        .route("/endpoints", get(list_webhook_endpoints))
+        .route("/endpoints/:id/deliveries", get(list_webhook_deliveries))
+        .route("/deliveries/:id/retry", post(retry_webhook_delivery))
+        .with_state(stripe_state.clone());
+
+    // Live domain events, and raw deliveries from both providers by event id
+    let events_router = Router::new()
+        .route("/stream", get(stream_events))
+        .route("/:id", get(get_archived_event))
+        .with_state(stripe_state.clone());
+
     // Combine into main app
     let app = Router::new()
         .nest("/stripe", stripe_router)
         .nest("/paypal", paypal_router)
+        .nest("/events", events_router)
+        .nest("/webhooks", webhooks_router)
         .route("/health", get(|| async { "OK" }))
+        .route("/metrics", get(metrics_handler).with_state(stripe_state.clone()))
         .layer(TraceLayer::new_for_http());
 
     // Get port from env or default to 3000
@@ -50,8 +142,19 @@ async fn main() {
 
     println!("🚀 Server listening on {}", addr);
     println!("   - Stripe Handler: http://{}/stripe/webhook", addr);
+    println!("   - Stripe Checkout: http://{}/stripe/checkout", addr);
+    println!("   - Stripe Subscription: http://{}/stripe/subscription", addr);
+    println!("   - Stripe Refunds: http://{}/stripe/refunds", addr);
+    println!("   - Stripe Disputes: http://{}/stripe/disputes", addr);
+    println!("   - Stripe Dead Letters: http://{}/stripe/dead-letters", addr);
+    println!("   - Stripe Reconcile: http://{}/stripe/reconcile", addr);
     println!("   - PayPal Handler: http://{}/paypal/webhook", addr);
+    println!("   - PayPal Disputes: http://{}/paypal/disputes", addr);
+    println!("   - Event Archive:  http://{}/events/:id", addr);
+    println!("   - Event Stream:   http://{}/events/stream", addr);
+    println!("   - Outbound Webhooks: http://{}/webhooks/endpoints", addr);
     println!("   - Health Check:   http://{}/health", addr);
+    println!("   - Metrics:        http://{}/metrics", addr);
 
     // Start server
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
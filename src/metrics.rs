@@ -0,0 +1,280 @@
+// lwas_economy/src/payments/metrics.rs
+// ARCHITECT: QANTUM AETERNA | STATUS: BETA
+// In-process metrics registry (counters & histograms) and the Prometheus /metrics endpoint
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap},
+    response::IntoResponse,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::stripe_handler::StripeWebhookState;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// REGISTRY
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Latency buckets in seconds
+const DEFAULT_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct MetricKey {
+    pub name: &'static str,
+    pub labels: Vec<(&'static str, String)>,
+}
+
+impl MetricKey {
+    fn new(name: &'static str, labels: &[(&'static str, &str)]) -> Self {
+        Self {
+            name,
+            labels: labels.iter().map(|(k, v)| (*k, v.to_string())).collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Histogram {
+    pub buckets: Vec<(f64, u64)>,
+    pub sum: f64,
+    pub count: u64,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            buckets: DEFAULT_BUCKETS.iter().map(|b| (*b, 0)).collect(),
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        for (bound, count) in self.buckets.iter_mut() {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+pub struct Metrics {
+    counters: Mutex<HashMap<MetricKey, u64>>,
+    histograms: Mutex<HashMap<MetricKey, Histogram>>,
+}
+
+/// Process-wide registry
+pub fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(|| Metrics {
+        counters: Mutex::new(HashMap::new()),
+        histograms: Mutex::new(HashMap::new()),
+    })
+}
+
+impl Metrics {
+    pub fn inc_counter(&self, name: &'static str, labels: &[(&'static str, &str)]) {
+        let mut counters = self.counters.lock().unwrap();
+        *counters.entry(MetricKey::new(name, labels)).or_insert(0) += 1;
+    }
+
+    pub fn observe(&self, name: &'static str, labels: &[(&'static str, &str)], value: f64) {
+        let mut histograms = self.histograms.lock().unwrap();
+        histograms
+            .entry(MetricKey::new(name, labels))
+            .or_insert_with(Histogram::new)
+            .observe(value);
+    }
+}
+
+/// A webhook delivery refused before processing; `event_type` is `unknown` until the
+/// payload is verified and parsed
+pub fn webhook_rejected(provider: &'static str, event_type: &str, reason: &'static str) {
+    metrics().inc_counter(
+        "webhooks_rejected_total",
+        &[("provider", provider), ("event_type", event_type), ("reason", reason)],
+    );
+}
+
+/// An operation a store served from its in-memory fallback instead of Redis
+pub fn redis_fallback(store: &'static str, configured: bool) {
+    let reason = if configured { "unavailable" } else { "unconfigured" };
+    metrics().inc_counter("redis_fallback_total", &[("store", store), ("reason", reason)]);
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// PROMETHEUS EXPOSITION
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// `{a="x",b="y"}`, or nothing without labels
+fn label_set(labels: &[(&'static str, String)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| format!("{}=\"{}\"", k, escape_label(v)))
+        .collect();
+    format!("{{{}}}", pairs.join(","))
+}
+
+/// Series sorted by name then labels, grouped under one `# TYPE` line per metric
+fn sorted<T>(map: &HashMap<MetricKey, T>) -> Vec<(&MetricKey, &T)> {
+    let mut series: Vec<_> = map.iter().collect();
+    series.sort_by(|a, b| (a.0.name, &a.0.labels).cmp(&(b.0.name, &b.0.labels)));
+    series
+}
+
+/// Append a gauge computed at scrape time
+pub fn render_gauge(out: &mut String, name: &str, series: &[(Vec<(&'static str, String)>, f64)]) {
+    out.push_str(&format!("# TYPE {} gauge\n", name));
+    for (labels, value) in series {
+        out.push_str(&format!("{}{} {}\n", name, label_set(labels), value));
+    }
+}
+
+impl Metrics {
+    /// Everything recorded so far, in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        let counters = self.counters.lock().unwrap();
+        let mut last = "";
+        for (key, value) in sorted(&counters) {
+            if key.name != last {
+                out.push_str(&format!("# TYPE {} counter\n", key.name));
+                last = key.name;
+            }
+            out.push_str(&format!("{}{} {}\n", key.name, label_set(&key.labels), value));
+        }
+        drop(counters);
+
+        let histograms = self.histograms.lock().unwrap();
+        let mut last = "";
+        for (key, histogram) in sorted(&histograms) {
+            if key.name != last {
+                out.push_str(&format!("# TYPE {} histogram\n", key.name));
+                last = key.name;
+            }
+            // Buckets already count every value <= bound, i.e. they are cumulative
+            let bucket = |le: String, count: u64| {
+                let mut labels = key.labels.clone();
+                labels.push(("le", le));
+                format!("{}_bucket{} {}\n", key.name, label_set(&labels), count)
+            };
+            for (bound, count) in &histogram.buckets {
+                out.push_str(&bucket(bound.to_string(), *count));
+            }
+            out.push_str(&bucket("+Inf".to_string(), histogram.count));
+            out.push_str(&format!("{}_sum{} {}\n", key.name, label_set(&key.labels), histogram.sum));
+            out.push_str(&format!("{}_count{} {}\n", key.name, label_set(&key.labels), histogram.count));
+        }
+
+        out
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// HTTP HANDLER
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// `GET /metrics`: the registry plus gauges read from current state
+pub async fn metrics_handler(
+    State(state): State<Arc<StripeWebhookState>>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(e) = state.auth.require_metrics_token(&headers) {
+        return e.into_response();
+    }
+
+    let mut by_plan: BTreeMap<(&'static str, &'static str), u64> = BTreeMap::new();
+    for subscription in state.subscriptions.all().await {
+        *by_plan
+            .entry((subscription.plan.name(), subscription.status.as_str()))
+            .or_insert(0) += 1;
+    }
+    let subscriptions: Vec<_> = by_plan
+        .into_iter()
+        .map(|((plan, status), count)| {
+            (vec![("plan", plan.to_string()), ("status", status.to_string())], count as f64)
+        })
+        .collect();
+
+    let mut body = metrics().render();
+    render_gauge(&mut body, "subscriptions", &subscriptions);
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_counters_and_cumulative_histograms() {
+        let registry = Metrics {
+            counters: Mutex::new(HashMap::new()),
+            histograms: Mutex::new(HashMap::new()),
+        };
+        registry.inc_counter("webhooks_received_total", &[("provider", "stripe")]);
+        registry.inc_counter("webhooks_received_total", &[("provider", "stripe")]);
+        registry.inc_counter("webhooks_received_total", &[("provider", "pay\"pal")]);
+        registry.observe("webhook_processing_duration_seconds", &[("provider", "stripe")], 0.02);
+        registry.observe("webhook_processing_duration_seconds", &[("provider", "stripe")], 3.0);
+
+        let text = registry.render();
+        assert_eq!(text.matches("# TYPE webhooks_received_total counter").count(), 1);
+        assert!(text.contains("webhooks_received_total{provider=\"stripe\"} 2\n"));
+        assert!(text.contains("webhooks_received_total{provider=\"pay\\\"pal\"} 1\n"));
+        assert!(text.contains("# TYPE webhook_processing_duration_seconds histogram"));
+        assert!(text.contains("webhook_processing_duration_seconds_bucket{provider=\"stripe\",le=\"0.01\"} 0\n"));
+        assert!(text.contains("webhook_processing_duration_seconds_bucket{provider=\"stripe\",le=\"0.025\"} 1\n"));
+        assert!(text.contains("webhook_processing_duration_seconds_bucket{provider=\"stripe\",le=\"5\"} 2\n"));
+        assert!(text.contains("webhook_processing_duration_seconds_bucket{provider=\"stripe\",le=\"+Inf\"} 2\n"));
+        assert!(text.contains("webhook_processing_duration_seconds_count{provider=\"stripe\"} 2\n"));
+    }
+
+    #[tokio::test]
+    async fn metrics_endpoint_reports_subscriptions_by_plan_and_status() {
+        use crate::stripe_handler::EventStamp;
+        use axum::{routing::get, Router};
+
+        let state = Arc::new(StripeWebhookState::new(crate::test_support::authenticator()));
+        for (email, plan) in [
+            ("a@example.com", "pro_monthly"),
+            ("b@example.com", "pro_monthly"),
+            ("c@example.com", "enterprise_annual"),
+        ] {
+            let stamp = EventStamp { id: format!("evt_{}", email), created: 1 };
+            state.subscriptions.activate_subscription(None, email, None, None, plan, stamp).await;
+        }
+
+        let url = crate::test_support::spawn_mock(
+            Router::new().route("/metrics", get(metrics_handler)).with_state(state),
+        )
+        .await;
+        let client = reqwest::Client::new();
+
+        let anonymous = client.get(format!("{}/metrics", url)).send().await.unwrap();
+        assert_eq!(anonymous.status(), 401);
+
+        let resp = client
+            .get(format!("{}/metrics", url))
+            .bearer_auth("admin_test_token")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+        let text = resp.text().await.unwrap();
+        assert!(text.contains("# TYPE subscriptions gauge\n"));
+        assert!(text.contains("subscriptions{plan=\"pro_monthly\",status=\"active\"} 2\n"));
+        assert!(text.contains("subscriptions{plan=\"enterprise_annual\",status=\"active\"} 1\n"));
+    }
+}
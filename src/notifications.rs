@@ -0,0 +1,541 @@
+// lwas_economy/src/payments/notifications.rs
+// ARCHITECT: QANTUM AETERNA | STATUS: BETA
+// Transactional customer emails: templated per language, sent over SMTP or to a local sink
+
+use chrono::{DateTime, Utc};
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncFileTransport, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use redis::AsyncCommands;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+use crate::currency::format_amount;
+use crate::metrics::{metrics, redis_fallback};
+use crate::stripe_handler::{SubscriptionPlan, UserSubscription};
+use crate::stripe_models::{Card, Invoice};
+
+/// Languages with templates; anything else falls back to the default locale
+pub const SUPPORTED_LOCALES: [&str; 4] = ["en", "de", "fr", "es"];
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// CONFIGURATION
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransportKind {
+    Smtp,
+    /// One `.eml` file per message in `file_dir` (development and tests)
+    File,
+    /// Nothing configured: log what would have been sent
+    Log,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmtpSecurity {
+    StartTls,
+    Tls,
+    /// Plain SMTP, e.g. MailHog on `localhost:1025`
+    None,
+}
+
+#[derive(Clone, Debug)]
+pub struct NotificationConfig {
+    pub transport: TransportKind,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_security: SmtpSecurity,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    pub file_dir: PathBuf,
+    pub from: String,
+    pub default_locale: String,
+    /// Where customers manage billing; linked when a message has no more specific page
+    pub account_url: Option<String>,
+}
+
+impl NotificationConfig {
+    pub fn from_env() -> Self {
+        let transport = match std::env::var("EMAIL_TRANSPORT").as_deref() {
+            Ok("smtp") => TransportKind::Smtp,
+            Ok("file") => TransportKind::File,
+            _ => TransportKind::Log,
+        };
+        let smtp_security = match std::env::var("SMTP_SECURITY").as_deref() {
+            Ok("tls") => SmtpSecurity::Tls,
+            Ok("none") => SmtpSecurity::None,
+            _ => SmtpSecurity::StartTls,
+        };
+        let default_port = match smtp_security {
+            SmtpSecurity::StartTls => 587,
+            SmtpSecurity::Tls => 465,
+            SmtpSecurity::None => 1025,
+        };
+
+        Self {
+            transport,
+            smtp_host: std::env::var("SMTP_HOST").unwrap_or_else(|_| "localhost".to_string()),
+            smtp_port: std::env::var("SMTP_PORT")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default_port),
+            smtp_security,
+            smtp_username: std::env::var("SMTP_USERNAME").ok().filter(|s| !s.is_empty()),
+            smtp_password: std::env::var("SMTP_PASSWORD").ok().filter(|s| !s.is_empty()),
+            file_dir: std::env::var("EMAIL_FILE_DIR")
+                .unwrap_or_else(|_| "./mail".to_string())
+                .into(),
+            from: std::env::var("EMAIL_FROM").unwrap_or_else(|_| "Qantum Billing <billing@localhost>".to_string()),
+            default_locale: std::env::var("EMAIL_DEFAULT_LOCALE")
+                .ok()
+                .and_then(|l| supported_locale(&l))
+                .unwrap_or("en")
+                .to_string(),
+            account_url: std::env::var("EMAIL_ACCOUNT_URL").ok().filter(|s| !s.is_empty()),
+        }
+    }
+}
+
+/// `de-DE` / `de_AT` / `DE` → `de`, when we have templates for it
+fn supported_locale(tag: &str) -> Option<&'static str> {
+    let language = tag.split(['-', '_']).next()?.to_lowercase();
+    SUPPORTED_LOCALES.into_iter().find(|l| *l == language)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// NOTIFICATIONS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotificationKind {
+    Receipt,
+    PaymentFailed,
+    SubscriptionCanceled,
+    TrialEnding,
+    CardExpiring,
+}
+
+impl NotificationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::Receipt => "receipt",
+            NotificationKind::PaymentFailed => "payment_failed",
+            NotificationKind::SubscriptionCanceled => "subscription_canceled",
+            NotificationKind::TrialEnding => "trial_ending",
+            NotificationKind::CardExpiring => "card_expiring",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Notification {
+    pub kind: NotificationKind,
+    pub to: String,
+    /// Template variables; `action_url` defaults to the account page
+    pub vars: HashMap<&'static str, String>,
+}
+
+fn format_date(date: DateTime<Utc>) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+fn plan_name(plan: &SubscriptionPlan) -> &'static str {
+    match plan {
+        SubscriptionPlan::Free => "Free",
+        SubscriptionPlan::Pro { .. } => "Pro",
+        SubscriptionPlan::Enterprise { .. } => "Enterprise",
+    }
+}
+
+impl Notification {
+    pub fn new(kind: NotificationKind, to: &str) -> Self {
+        Self {
+            kind,
+            to: to.to_string(),
+            vars: HashMap::new(),
+        }
+    }
+
+    pub fn var(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.vars.insert(name, value.into());
+        self
+    }
+
+    fn with_url(self, url: Option<&str>) -> Self {
+        match url {
+            Some(url) => self.var("action_url", url),
+            None => self,
+        }
+    }
+
+    pub fn receipt(email: &str, invoice: &Invoice) -> Self {
+        Self::new(NotificationKind::Receipt, email)
+            .var("amount", format_amount(invoice.amount_paid, &invoice.currency))
+            .var("invoice", invoice.number.as_deref().unwrap_or(&invoice.id))
+            .with_url(invoice.hosted_invoice_url.as_deref())
+    }
+
+    pub fn payment_failed(email: &str, amount: i64, currency: &str, invoice_url: Option<&str>) -> Self {
+        Self::new(NotificationKind::PaymentFailed, email)
+            .var("amount", format_amount(amount, currency))
+            .with_url(invoice_url)
+    }
+
+    pub fn subscription_canceled(sub: &UserSubscription) -> Self {
+        Self::new(NotificationKind::SubscriptionCanceled, &sub.email).var("plan", plan_name(&sub.plan))
+    }
+
+    pub fn trial_ending(sub: &UserSubscription) -> Self {
+        Self::new(NotificationKind::TrialEnding, &sub.email)
+            .var("plan", plan_name(&sub.plan))
+            .var("trial_end", sub.trial_end.map(format_date).unwrap_or_else(|| "-".into()))
+    }
+
+    pub fn card_expiring(email: &str, card: &Card) -> Self {
+        Self::new(NotificationKind::CardExpiring, email)
+            .var("brand", card.brand.as_deref().unwrap_or("card"))
+            .var("last4", card.last4.as_str())
+            .var("expiry", format!("{:02}/{}", card.exp_month, card.exp_year))
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// TEMPLATES
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// (subject, plain-text body) with `{{var}}` placeholders
+fn template(kind: NotificationKind, locale: &str) -> (&'static str, &'static str) {
+    use NotificationKind::*;
+
+    match (kind, locale) {
+        (Receipt, "de") => (
+            "Ihre Zahlungsbestätigung ({{amount}})",
+            "Hallo,\n\nvielen Dank für Ihre Zahlung über {{amount}}.\nRechnung: {{invoice}}\n\nRechnung ansehen oder herunterladen:\n{{action_url}}\n\nQantum Billing\n",
+        ),
+        (Receipt, "fr") => (
+            "Votre reçu de paiement ({{amount}})",
+            "Bonjour,\n\nmerci pour votre paiement de {{amount}}.\nFacture : {{invoice}}\n\nConsulter ou télécharger votre facture :\n{{action_url}}\n\nQantum Billing\n",
+        ),
+        (Receipt, "es") => (
+            "Su recibo de pago ({{amount}})",
+            "Hola:\n\ngracias por su pago de {{amount}}.\nFactura: {{invoice}}\n\nVer o descargar su factura:\n{{action_url}}\n\nQantum Billing\n",
+        ),
+        (Receipt, _) => (
+            "Your payment receipt ({{amount}})",
+            "Hello,\n\nthank you for your payment of {{amount}}.\nInvoice: {{invoice}}\n\nView or download your invoice:\n{{action_url}}\n\nQantum Billing\n",
+        ),
+
+        (PaymentFailed, "de") => (
+            "Ihre Zahlung über {{amount}} ist fehlgeschlagen",
+            "Hallo,\n\nwir konnten Ihre Zahlung über {{amount}} nicht einziehen.\n\nBitte aktualisieren Sie Ihre Zahlungsmethode oder begleichen Sie die Rechnung, damit Ihr Abonnement aktiv bleibt:\n{{action_url}}\n\nQantum Billing\n",
+        ),
+        (PaymentFailed, "fr") => (
+            "Votre paiement de {{amount}} a échoué",
+            "Bonjour,\n\nnous n'avons pas pu encaisser votre paiement de {{amount}}.\n\nMettez à jour votre moyen de paiement ou réglez la facture pour garder votre abonnement actif :\n{{action_url}}\n\nQantum Billing\n",
+        ),
+        (PaymentFailed, "es") => (
+            "Su pago de {{amount}} ha fallado",
+            "Hola:\n\nno hemos podido cobrar su pago de {{amount}}.\n\nActualice su método de pago o pague la factura para mantener su suscripción activa:\n{{action_url}}\n\nQantum Billing\n",
+        ),
+        (PaymentFailed, _) => (
+            "Your payment of {{amount}} failed",
+            "Hello,\n\nwe could not collect your payment of {{amount}}.\n\nPlease update your payment method or pay the invoice to keep your subscription active:\n{{action_url}}\n\nQantum Billing\n",
+        ),
+
+        (SubscriptionCanceled, "de") => (
+            "Ihr {{plan}}-Abonnement wurde gekündigt",
+            "Hallo,\n\nIhr {{plan}}-Abonnement wurde gekündigt; die enthaltenen Funktionen stehen nicht mehr zur Verfügung.\n\nSie können jederzeit wieder abonnieren:\n{{action_url}}\n\nQantum Billing\n",
+        ),
+        (SubscriptionCanceled, "fr") => (
+            "Votre abonnement {{plan}} a été résilié",
+            "Bonjour,\n\nvotre abonnement {{plan}} a été résilié et ses fonctionnalités ne sont plus disponibles.\n\nVous pouvez vous réabonner à tout moment :\n{{action_url}}\n\nQantum Billing\n",
+        ),
+        (SubscriptionCanceled, "es") => (
+            "Su suscripción {{plan}} ha sido cancelada",
+            "Hola:\n\nsu suscripción {{plan}} ha sido cancelada y sus funciones ya no están disponibles.\n\nPuede volver a suscribirse en cualquier momento:\n{{action_url}}\n\nQantum Billing\n",
+        ),
+        (SubscriptionCanceled, _) => (
+            "Your {{plan}} subscription has been canceled",
+            "Hello,\n\nyour {{plan}} subscription has been canceled and its features are no longer available.\n\nYou can subscribe again at any time:\n{{action_url}}\n\nQantum Billing\n",
+        ),
+
+        (TrialEnding, "de") => (
+            "Ihr Testzeitraum endet am {{trial_end}}",
+            "Hallo,\n\nIhr {{plan}}-Testzeitraum endet am {{trial_end}}.\n\nMit hinterlegter Zahlungsmethode läuft Ihr Abonnement automatisch weiter. Andernfalls fügen Sie eine hinzu, um den Zugang zu behalten:\n{{action_url}}\n\nQantum Billing\n",
+        ),
+        (TrialEnding, "fr") => (
+            "Votre essai se termine le {{trial_end}}",
+            "Bonjour,\n\nvotre essai {{plan}} se termine le {{trial_end}}.\n\nSi un moyen de paiement est enregistré, votre abonnement continue automatiquement. Sinon, ajoutez-en un pour garder l'accès :\n{{action_url}}\n\nQantum Billing\n",
+        ),
+        (TrialEnding, "es") => (
+            "Su periodo de prueba termina el {{trial_end}}",
+            "Hola:\n\nsu prueba de {{plan}} termina el {{trial_end}}.\n\nSi tiene un método de pago registrado, su suscripción continúa automáticamente. Si no, añada uno para mantener el acceso:\n{{action_url}}\n\nQantum Billing\n",
+        ),
+        (TrialEnding, _) => (
+            "Your trial ends on {{trial_end}}",
+            "Hello,\n\nyour {{plan}} trial ends on {{trial_end}}.\n\nIf you have a payment method on file, your subscription continues automatically. Otherwise, add one to keep access:\n{{action_url}}\n\nQantum Billing\n",
+        ),
+
+        (CardExpiring, "de") => (
+            "Ihre Karte mit Endziffern {{last4}} läuft bald ab",
+            "Hallo,\n\nIhre {{brand}}-Karte mit den Endziffern {{last4}} läuft Ende {{expiry}} ab.\n\nBitte aktualisieren Sie Ihre Zahlungsmethode, um Unterbrechungen zu vermeiden:\n{{action_url}}\n\nQantum Billing\n",
+        ),
+        (CardExpiring, "fr") => (
+            "Votre carte se terminant par {{last4}} expire bientôt",
+            "Bonjour,\n\nvotre carte {{brand}} se terminant par {{last4}} expire fin {{expiry}}.\n\nMettez à jour votre moyen de paiement pour éviter toute interruption :\n{{action_url}}\n\nQantum Billing\n",
+        ),
+        (CardExpiring, "es") => (
+            "Su tarjeta terminada en {{last4}} caduca pronto",
+            "Hola:\n\nsu tarjeta {{brand}} terminada en {{last4}} caduca a finales de {{expiry}}.\n\nActualice su método de pago para evitar interrupciones:\n{{action_url}}\n\nQantum Billing\n",
+        ),
+        (CardExpiring, _) => (
+            "Your card ending in {{last4}} expires soon",
+            "Hello,\n\nyour {{brand}} card ending in {{last4}} expires at the end of {{expiry}}.\n\nPlease update your payment method to avoid interruptions:\n{{action_url}}\n\nQantum Billing\n",
+        ),
+    }
+}
+
+/// Subject and body of `kind` in `locale`; unknown placeholders render empty
+pub fn render(kind: NotificationKind, locale: &str, vars: &HashMap<&'static str, String>) -> (String, String) {
+    let fill = |text: &str| {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            out.push_str(&rest[..start]);
+            let Some(len) = rest[start..].find("}}") else {
+                break;
+            };
+            let name = &rest[start + 2..start + len];
+            out.push_str(vars.get(name).map(|v| v.as_str()).unwrap_or(""));
+            rest = &rest[start + len + 2..];
+        }
+        out.push_str(rest);
+        out
+    };
+
+    let (subject, body) = template(kind, locale);
+    (fill(subject), fill(body))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// NOTIFIER
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone)]
+enum Transport {
+    Smtp(AsyncSmtpTransport<Tokio1Executor>),
+    File(AsyncFileTransport<Tokio1Executor>),
+    Log,
+}
+
+fn build_transport(config: &NotificationConfig) -> Result<Transport, String> {
+    match config.transport {
+        TransportKind::Log => Ok(Transport::Log),
+        TransportKind::File => {
+            std::fs::create_dir_all(&config.file_dir)
+                .map_err(|e| format!("Cannot create {}: {}", config.file_dir.display(), e))?;
+            Ok(Transport::File(AsyncFileTransport::new(&config.file_dir)))
+        }
+        TransportKind::Smtp => {
+            let builder = match config.smtp_security {
+                SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host),
+                SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_host),
+                SmtpSecurity::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.smtp_host)),
+            }
+            .map_err(|e| format!("Invalid SMTP relay {}: {}", config.smtp_host, e))?;
+
+            let mut builder = builder.port(config.smtp_port).timeout(Some(Duration::from_secs(10)));
+            if let (Some(user), Some(password)) = (&config.smtp_username, &config.smtp_password) {
+                builder = builder.credentials(Credentials::new(user.clone(), password.clone()));
+            }
+            Ok(Transport::Smtp(builder.build()))
+        }
+    }
+}
+
+/// Sends customer emails. Best effort: a mail outage is logged and counted, never
+/// fails the webhook or job that triggered the message.
+#[derive(Clone)]
+pub struct Notifier {
+    pub config: NotificationConfig,
+    transport: Transport,
+    from: Mailbox,
+    /// Customer email → template language, from Stripe's `preferred_locales`. Kept in the
+    /// `notify:locales` hash so it survives restarts; Stripe only resends it when it changes.
+    redis_client: Option<redis::Client>,
+    locales_fallback: Arc<RwLock<HashMap<String, &'static str>>>,
+}
+
+const LOCALES_KEY: &str = "notify:locales";
+
+impl Notifier {
+    pub fn new(config: NotificationConfig, redis_url: Option<String>) -> Self {
+        let transport = build_transport(&config).unwrap_or_else(|e| {
+            println!("❌ Email transport unavailable, logging emails instead: {}", e);
+            Transport::Log
+        });
+        let from = config.from.parse().unwrap_or_else(|e| {
+            println!("❌ Invalid EMAIL_FROM {:?}: {}", config.from, e);
+            "billing@localhost".parse().expect("valid fallback address")
+        });
+        if matches!(transport, Transport::Log) {
+            println!("⚠️ EMAIL_TRANSPORT not set: customer emails are only logged");
+        }
+        let redis_client = redis_url.and_then(|url| {
+            redis::Client::open(url).map_err(|e| println!("❌ Redis connect error: {}", e)).ok()
+        });
+
+        Self {
+            config,
+            transport,
+            from,
+            redis_client,
+            locales_fallback: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Remember the first of `preferred` we have templates for
+    pub async fn set_locale(&self, email: &str, preferred: &[String]) {
+        let locale = preferred.iter().find_map(|tag| supported_locale(tag));
+
+        if let Some(client) = &self.redis_client {
+            if let Ok(mut con) = client.get_multiplexed_async_connection().await {
+                let saved: redis::RedisResult<()> = match locale {
+                    Some(locale) => con.hset(LOCALES_KEY, email, locale).await,
+                    None => con.hdel(LOCALES_KEY, email).await,
+                };
+                if let Err(e) = saved {
+                    println!("[NOTIFY] ❌ Could not save locale for {}: {}", email, e);
+                }
+                return;
+            }
+        }
+
+        redis_fallback("locales", self.redis_client.is_some());
+        let mut locales = self.locales_fallback.write().await;
+        match locale {
+            Some(locale) => {
+                locales.insert(email.to_string(), locale);
+            }
+            None => {
+                locales.remove(email);
+            }
+        }
+    }
+
+    pub async fn locale_for(&self, email: &str) -> String {
+        if let Some(client) = &self.redis_client {
+            if let Ok(mut con) = client.get_multiplexed_async_connection().await {
+                let stored: Option<String> = con.hget(LOCALES_KEY, email).await.unwrap_or(None);
+                return stored
+                    .as_deref()
+                    .and_then(supported_locale)
+                    .map(|l| l.to_string())
+                    .unwrap_or_else(|| self.config.default_locale.clone());
+            }
+        }
+
+        redis_fallback("locales", self.redis_client.is_some());
+        match self.locales_fallback.read().await.get(email) {
+            Some(locale) => locale.to_string(),
+            None => self.config.default_locale.clone(),
+        }
+    }
+
+    pub async fn send(&self, notification: Notification) {
+        let kind = notification.kind.as_str();
+        let outcome = match self.deliver(notification.clone()).await {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                println!("[NOTIFY] ❌ {} to {} failed: {}", kind, notification.to, e);
+                "failed"
+            }
+        };
+        metrics().inc_counter("notifications_sent_total", &[("kind", kind), ("outcome", outcome)]);
+    }
+
+    async fn deliver(&self, mut notification: Notification) -> Result<&'static str, String> {
+        let locale = self.locale_for(&notification.to).await;
+        if let Some(url) = &self.config.account_url {
+            notification.vars.entry("action_url").or_insert_with(|| url.clone());
+        }
+        let (subject, body) = render(notification.kind, &locale, &notification.vars);
+
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(notification.to.parse().map_err(|e| format!("Invalid recipient: {}", e))?)
+            .subject(&subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body)
+            .map_err(|e| format!("Failed to build message: {}", e))?;
+
+        match &self.transport {
+            Transport::Smtp(smtp) => {
+                smtp.send(message).await.map_err(|e| format!("SMTP error: {}", e))?;
+            }
+            Transport::File(file) => {
+                file.send(message).await.map_err(|e| format!("File sink error: {}", e))?;
+            }
+            Transport::Log => {
+                println!("[NOTIFY] 📧 (not sent) {} [{}] to {}: {}", notification.kind.as_str(), locale, notification.to, subject);
+                return Ok("logged");
+            }
+        }
+
+        println!("[NOTIFY] 📧 {} [{}] sent to {}", notification.kind.as_str(), locale, notification.to);
+        Ok("sent")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_in_the_customers_language_with_fallback() {
+        let notification = Notification::payment_failed("ada@example.com", 1250, "eur", Some("https://invoice.stripe.com/i/1"));
+
+        let (subject, body) = render(notification.kind, "de", &notification.vars);
+        assert_eq!(subject, "Ihre Zahlung über 12.50 EUR ist fehlgeschlagen");
+        assert!(body.contains("https://invoice.stripe.com/i/1"));
+
+        let (subject, _) = render(notification.kind, "pt", &notification.vars);
+        assert_eq!(subject, "Your payment of 12.50 EUR failed");
+        let yen = Notification::payment_failed("ada@example.com", 1200, "jpy", None);
+        assert_eq!(render(yen.kind, "en", &yen.vars).0, "Your payment of 1200 JPY failed");
+        assert_eq!(supported_locale("fr-CA"), Some("fr"));
+        assert_eq!(supported_locale("pt-BR"), None);
+    }
+
+    #[tokio::test]
+    async fn file_sink_writes_one_message_per_email() {
+        let dir = std::env::temp_dir().join(format!("qantum-mail-{}", uuid::Uuid::new_v4()));
+        let notifier = Notifier::new(NotificationConfig {
+            transport: TransportKind::File,
+            file_dir: dir.clone(),
+            account_url: Some("https://app.example.com/billing".into()),
+            ..NotificationConfig::from_env()
+        }, None);
+        notifier.set_locale("ada@example.com", &["es-ES".into()]).await;
+
+        let card: Card = serde_json::from_value(serde_json::json!({
+            "id": "card_1", "customer": "cus_1", "brand": "Visa", "last4": "4242",
+            "exp_month": 8, "exp_year": 2026
+        }))
+        .unwrap();
+        notifier.send(Notification::card_expiring("ada@example.com", &card)).await;
+        notifier.send(Notification::new(NotificationKind::Receipt, "not an address")).await;
+
+        let files: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|e| e.unwrap().path()).collect();
+        assert_eq!(files.len(), 1);
+        let raw = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(raw.contains("To: ada@example.com"));
+        assert!(raw.contains("Subject: Su tarjeta terminada en 4242 caduca pronto"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
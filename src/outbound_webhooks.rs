@@ -0,0 +1,725 @@
+// lwas_economy/src/payments/outbound_webhooks.rs
+// ARCHITECT: QANTUM AETERNA | STATUS: BETA
+// Signed webhooks to our downstream services: fan-out, retries with backoff, delivery logs
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Notify, RwLock};
+
+use crate::events::{stable_id, DomainEvent};
+use crate::http_client::OutboundConfig;
+use crate::metrics::{metrics, redis_fallback};
+use crate::runtime::{env_u64, jittered_backoff};
+use crate::stripe_handler::StripeWebhookState;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Header carrying `t=<unix ts>,v1=<hex hmac>`, verified like Stripe's `Stripe-Signature`
+pub const SIGNATURE_HEADER: &str = "Qantum-Signature";
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// ENDPOINT CONFIGURATION
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct WebhookEndpoint {
+    pub id: String,
+    pub url: String,
+    pub secret: String,
+    /// Event types to deliver: exact (`payment.succeeded`), prefix (`subscription.*`) or `*`
+    #[serde(default = "all_events")]
+    pub events: Vec<String>,
+}
+
+fn all_events() -> Vec<String> {
+    vec!["*".to_string()]
+}
+
+impl WebhookEndpoint {
+    pub fn wants(&self, event_type: &str) -> bool {
+        self.events.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => event_type.starts_with(prefix),
+            None => pattern == event_type,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct OutboundWebhookConfig {
+    /// Loaded from `OUTBOUND_WEBHOOK_ENDPOINTS`:
+    /// `[{"id": "product-api", "url": "https://...", "secret": "...", "events": ["subscription.*"]}]`
+    pub endpoints: Vec<WebhookEndpoint>,
+    pub max_attempts: u32,
+    pub backoff_base: Duration,
+    pub backoff_max: Duration,
+    pub poll_interval: Duration,
+    /// Finished deliveries kept per endpoint
+    pub log_size: usize,
+}
+
+impl OutboundWebhookConfig {
+    pub fn from_env() -> Self {
+        let endpoints = match std::env::var("OUTBOUND_WEBHOOK_ENDPOINTS") {
+            Ok(raw) => serde_json::from_str(&raw).unwrap_or_else(|e| {
+                println!("❌ Invalid OUTBOUND_WEBHOOK_ENDPOINTS: {}", e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+
+        Self {
+            endpoints,
+            max_attempts: env_u64("OUTBOUND_WEBHOOK_MAX_ATTEMPTS", 12).max(1) as u32,
+            backoff_base: Duration::from_secs(env_u64("OUTBOUND_WEBHOOK_BACKOFF_BASE_SECS", 30)),
+            backoff_max: Duration::from_secs(env_u64("OUTBOUND_WEBHOOK_BACKOFF_MAX_SECS", 6 * 3600)),
+            poll_interval: Duration::from_millis(env_u64("OUTBOUND_WEBHOOK_POLL_MS", 1000)),
+            log_size: env_u64("OUTBOUND_WEBHOOK_LOG_SIZE", 200) as usize,
+        }
+    }
+
+    /// Delay before the next attempt after `attempts` failed ones
+    fn backoff(&self, attempts: u32) -> Duration {
+        jittered_backoff(self.backoff_base, self.backoff_max, attempts.saturating_sub(1))
+    }
+}
+
+/// `t=<ts>,v1=<hex HMAC-SHA256 of "<ts>.<body>">`
+pub fn sign_payload(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    format!("t={},v1={}", timestamp, hex::encode(mac.finalize().into_bytes()))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DELIVERIES
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    Succeeded,
+    /// Attempts exhausted; can be retried by an admin
+    Failed,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeliveryAttempt {
+    pub at: DateTime<Utc>,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Delivery {
+    /// Derived from the event and endpoint: republishing an event queues nothing new
+    pub id: String,
+    pub endpoint_id: String,
+    pub event: DomainEvent,
+    pub status: DeliveryStatus,
+    pub attempts: Vec<DeliveryAttempt>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DELIVERY STORE
+// ═══════════════════════════════════════════════════════════════════════════════
+// Redis layout:
+//   outbound:delivery:<id>          JSON Delivery
+//   outbound:pending                ZSET of delivery ids scored by next attempt (unix ms)
+//   outbound:finished:<endpoint>    ZSET of finished delivery ids scored by creation (unix ms)
+
+const PENDING_KEY: &str = "outbound:pending";
+
+/// Persist and schedule a new delivery; one already known (pending or logged) is a no-op
+const ENQUEUE_SCRIPT: &str = r#"
+if redis.call('set', KEYS[1], ARGV[1], 'NX') then
+    redis.call('zadd', KEYS[2], ARGV[2], ARGV[3])
+    return 1
+end
+return 0
+"#;
+
+/// Take up to ARGV[3] deliveries due by ARGV[1] and push them back to ARGV[2], so other
+/// replicas leave them alone while the attempt is in flight
+const CLAIM_SCRIPT: &str = r#"
+local ids = redis.call('zrangebyscore', KEYS[1], '-inf', ARGV[1], 'LIMIT', 0, ARGV[3])
+for _, id in ipairs(ids) do
+    redis.call('zadd', KEYS[1], ARGV[2], id)
+end
+return ids
+"#;
+
+/// Move a delivery from the pending set to its endpoint's log, dropping the oldest entries
+/// beyond ARGV[4]
+const FINISH_SCRIPT: &str = r#"
+redis.call('set', 'outbound:delivery:' .. ARGV[1], ARGV[2])
+redis.call('zrem', KEYS[1], ARGV[1])
+redis.call('zadd', KEYS[2], ARGV[3], ARGV[1])
+local excess = redis.call('zcard', KEYS[2]) - tonumber(ARGV[4])
+if excess > 0 then
+    for _, id in ipairs(redis.call('zrange', KEYS[2], 0, excess - 1)) do
+        redis.call('del', 'outbound:delivery:' .. id)
+    end
+    redis.call('zremrangebyrank', KEYS[2], 0, excess - 1)
+end
+return 1
+"#;
+
+#[derive(Default)]
+struct DeliveryLog {
+    pending: HashMap<String, Delivery>,
+    /// Finished deliveries per endpoint, newest last, capped at `log_size`
+    finished: HashMap<String, VecDeque<Delivery>>,
+}
+
+/// Deliveries are queued by `EventBus::publish` and persisted in Redis when configured:
+/// retries can be outstanding for days, and nothing re-derives them once the provider
+/// event that caused them is processed.
+#[derive(Clone)]
+pub struct OutboundDispatcher {
+    pub config: OutboundWebhookConfig,
+    http: reqwest::Client,
+    /// How long a claimed delivery is hidden from other replicas: two request timeouts
+    claim_lease: Duration,
+    redis_client: Option<redis::Client>,
+    log: Arc<RwLock<DeliveryLog>>,
+    notify: Arc<Notify>,
+}
+
+fn redis_err(e: redis::RedisError) -> String {
+    format!("Redis error: {}", e)
+}
+
+fn to_json(delivery: &Delivery) -> Result<String, String> {
+    serde_json::to_string(delivery).map_err(|e| e.to_string())
+}
+
+impl OutboundDispatcher {
+    pub fn new(config: OutboundWebhookConfig, redis_url: Option<String>) -> Self {
+        let timeouts = OutboundConfig::from_env();
+        let http = reqwest::Client::builder()
+            .connect_timeout(timeouts.connect_timeout)
+            .timeout(timeouts.request_timeout)
+            .build()
+            .expect("Failed to build HTTP client");
+        let redis_client = redis_url.and_then(|url| {
+            redis::Client::open(url).map_err(|e| println!("❌ Redis connect error: {}", e)).ok()
+        });
+        if redis_client.is_none() && !config.endpoints.is_empty() {
+            println!("⚠️ No REDIS_URL: outbound webhook deliveries are in-memory and lost on restart");
+        }
+
+        Self {
+            config,
+            http,
+            claim_lease: timeouts.request_timeout * 2,
+            redis_client,
+            log: Arc::new(RwLock::new(DeliveryLog::default())),
+            notify: Arc::new(Notify::new()),
+        }
+    }
+
+    async fn connection(&self) -> Result<Option<redis::aio::MultiplexedConnection>, String> {
+        match &self.redis_client {
+            Some(client) => client.get_multiplexed_async_connection().await.map(Some).map_err(redis_err),
+            None => Ok(None),
+        }
+    }
+
+    /// One delivery per endpoint subscribed to the event's type; returns how many were new
+    pub async fn enqueue(&self, event: &DomainEvent, now: DateTime<Utc>) -> Result<usize, String> {
+        let deliveries: Vec<Delivery> = self
+            .config
+            .endpoints
+            .iter()
+            .filter(|e| e.wants(&event.event_type))
+            .map(|endpoint| Delivery {
+                id: stable_id("whd", &[&event.id, &endpoint.id]),
+                endpoint_id: endpoint.id.clone(),
+                event: event.clone(),
+                status: DeliveryStatus::Pending,
+                attempts: Vec::new(),
+                next_attempt_at: now,
+                created_at: now,
+            })
+            .collect();
+        if deliveries.is_empty() {
+            return Ok(0);
+        }
+
+        let mut queued = 0;
+        if let Some(mut con) = self.connection().await? {
+            for delivery in &deliveries {
+                let added: i64 = redis::Script::new(ENQUEUE_SCRIPT)
+                    .key(format!("outbound:delivery:{}", delivery.id))
+                    .key(PENDING_KEY)
+                    .arg(to_json(delivery)?)
+                    .arg(now.timestamp_millis())
+                    .arg(&delivery.id)
+                    .invoke_async(&mut con)
+                    .await
+                    .map_err(redis_err)?;
+                queued += added as usize;
+            }
+        } else {
+            redis_fallback("outbound", false);
+            let mut log = self.log.write().await;
+            for delivery in deliveries {
+                let known = log.pending.contains_key(&delivery.id)
+                    || log.finished.values().flatten().any(|d| d.id == delivery.id);
+                if !known {
+                    log.pending.insert(delivery.id.clone(), delivery);
+                    queued += 1;
+                }
+            }
+        }
+
+        if queued > 0 {
+            self.notify.notify_one();
+        }
+        Ok(queued)
+    }
+
+    async fn attempt(&self, endpoint: &WebhookEndpoint, delivery: &Delivery, now: DateTime<Utc>) -> DeliveryAttempt {
+        let body = serde_json::to_string(&delivery.event).unwrap_or_default();
+        let signature = sign_payload(&endpoint.secret, now.timestamp(), &body);
+
+        let started = Instant::now();
+        let result = self
+            .http
+            .post(&endpoint.url)
+            .header("Content-Type", "application/json")
+            .header(SIGNATURE_HEADER, signature)
+            .header("Qantum-Delivery-Id", &delivery.id)
+            .body(body)
+            .send()
+            .await;
+        let duration_ms = started.elapsed().as_millis() as u64;
+
+        match result {
+            Ok(resp) if resp.status().is_success() => DeliveryAttempt {
+                at: now,
+                status_code: Some(resp.status().as_u16()),
+                error: None,
+                duration_ms,
+            },
+            Ok(resp) => DeliveryAttempt {
+                at: now,
+                status_code: Some(resp.status().as_u16()),
+                error: Some(format!("HTTP {}", resp.status())),
+                duration_ms,
+            },
+            Err(e) => DeliveryAttempt {
+                at: now,
+                status_code: None,
+                error: Some(format!("Request failed: {}", e)),
+                duration_ms,
+            },
+        }
+    }
+
+    /// Due deliveries, claimed so other replicas skip them while the attempt is in flight
+    async fn claim_due(&self, now: DateTime<Utc>) -> Result<Vec<Delivery>, String> {
+        let Some(mut con) = self.connection().await? else {
+            return Ok(self
+                .log
+                .read()
+                .await
+                .pending
+                .values()
+                .filter(|d| d.next_attempt_at <= now)
+                .cloned()
+                .collect());
+        };
+
+        let ids: Vec<String> = redis::Script::new(CLAIM_SCRIPT)
+            .key(PENDING_KEY)
+            .arg(now.timestamp_millis())
+            .arg(now.timestamp_millis() + self.claim_lease.as_millis() as i64)
+            .arg(100)
+            .invoke_async(&mut con)
+            .await
+            .map_err(redis_err)?;
+        self.load(&mut con, &ids).await
+    }
+
+    async fn load(
+        &self,
+        con: &mut redis::aio::MultiplexedConnection,
+        ids: &[String],
+    ) -> Result<Vec<Delivery>, String> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let keys: Vec<String> = ids.iter().map(|id| format!("outbound:delivery:{}", id)).collect();
+        let raw: Vec<Option<String>> = redis::cmd("MGET").arg(keys).query_async(con).await.map_err(redis_err)?;
+        Ok(raw.iter().flatten().filter_map(|r| serde_json::from_str(r).ok()).collect())
+    }
+
+    /// Attempt every due delivery (concurrently); returns how many were attempted
+    pub async fn deliver_due(&self, now: DateTime<Utc>) -> Result<usize, String> {
+        let due = self.claim_due(now).await?;
+
+        let mut attempts = tokio::task::JoinSet::new();
+        for delivery in due {
+            let Some(endpoint) = self.config.endpoints.iter().find(|e| e.id == delivery.endpoint_id).cloned() else {
+                continue;
+            };
+            let dispatcher = self.clone();
+            attempts.spawn(async move {
+                let attempt = dispatcher.attempt(&endpoint, &delivery, now).await;
+                (delivery, attempt)
+            });
+        }
+
+        let mut attempted = 0;
+        while let Some(Ok((delivery, attempt))) = attempts.join_next().await {
+            self.record(delivery, attempt, now).await?;
+            attempted += 1;
+        }
+        Ok(attempted)
+    }
+
+    async fn record(&self, mut delivery: Delivery, attempt: DeliveryAttempt, now: DateTime<Utc>) -> Result<(), String> {
+        let succeeded = attempt.error.is_none();
+        let outcome = match (succeeded, delivery.attempts.len() as u32 + 1 >= self.config.max_attempts) {
+            (true, _) => "success",
+            (false, true) => "failed",
+            (false, false) => "retry",
+        };
+        metrics().inc_counter(
+            "outbound_webhook_deliveries_total",
+            &[("endpoint", delivery.endpoint_id.as_str()), ("outcome", outcome)],
+        );
+        metrics().observe(
+            "outbound_webhook_duration_seconds",
+            &[("endpoint", delivery.endpoint_id.as_str())],
+            attempt.duration_ms as f64 / 1000.0,
+        );
+        if let Some(error) = &attempt.error {
+            println!(
+                "[OUTBOUND-WEBHOOK] ❌ {} {} to {} attempt {}: {}",
+                delivery.event.event_type,
+                delivery.id,
+                delivery.endpoint_id,
+                delivery.attempts.len() + 1,
+                error
+            );
+        }
+        delivery.attempts.push(attempt);
+
+        if outcome == "retry" {
+            let delay = self.config.backoff(delivery.attempts.len() as u32);
+            delivery.next_attempt_at = now + chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::hours(1));
+            return self.save_pending(delivery).await;
+        }
+
+        delivery.status = if succeeded { DeliveryStatus::Succeeded } else { DeliveryStatus::Failed };
+        if let Some(mut con) = self.connection().await? {
+            return redis::Script::new(FINISH_SCRIPT)
+                .key(PENDING_KEY)
+                .key(format!("outbound:finished:{}", delivery.endpoint_id))
+                .arg(&delivery.id)
+                .arg(to_json(&delivery)?)
+                .arg(delivery.created_at.timestamp_millis())
+                .arg(self.config.log_size)
+                .invoke_async::<_, i64>(&mut con)
+                .await
+                .map(|_| ())
+                .map_err(redis_err);
+        }
+
+        let mut log = self.log.write().await;
+        log.pending.remove(&delivery.id);
+        let finished = log.finished.entry(delivery.endpoint_id.clone()).or_default();
+        finished.push_back(delivery);
+        while finished.len() > self.config.log_size {
+            finished.pop_front();
+        }
+        Ok(())
+    }
+
+    /// Store a pending delivery and schedule it for `next_attempt_at`
+    async fn save_pending(&self, delivery: Delivery) -> Result<(), String> {
+        if let Some(mut con) = self.connection().await? {
+            return redis::pipe()
+                .atomic()
+                .set(format!("outbound:delivery:{}", delivery.id), to_json(&delivery)?)
+                .zadd(PENDING_KEY, &delivery.id, delivery.next_attempt_at.timestamp_millis())
+                .query_async(&mut con)
+                .await
+                .map_err(redis_err);
+        }
+        self.log.write().await.pending.insert(delivery.id.clone(), delivery);
+        Ok(())
+    }
+
+    /// Deliveries for an endpoint, newest first
+    pub async fn deliveries(&self, endpoint_id: &str) -> Result<Vec<Delivery>, String> {
+        let mut deliveries: Vec<Delivery> = if let Some(mut con) = self.connection().await? {
+            let mut ids: Vec<String> = redis::cmd("ZRANGE")
+                .arg(PENDING_KEY)
+                .arg(0)
+                .arg(-1)
+                .query_async(&mut con)
+                .await
+                .map_err(redis_err)?;
+            let finished: Vec<String> = redis::cmd("ZRANGE")
+                .arg(format!("outbound:finished:{}", endpoint_id))
+                .arg(0)
+                .arg(-1)
+                .query_async(&mut con)
+                .await
+                .map_err(redis_err)?;
+            ids.extend(finished);
+            self.load(&mut con, &ids).await?.into_iter().filter(|d| d.endpoint_id == endpoint_id).collect()
+        } else {
+            let log = self.log.read().await;
+            log.pending
+                .values()
+                .filter(|d| d.endpoint_id == endpoint_id)
+                .cloned()
+                .chain(log.finished.get(endpoint_id).into_iter().flatten().cloned())
+                .collect()
+        };
+        deliveries.sort_by_key(|d| std::cmp::Reverse(d.created_at));
+        Ok(deliveries)
+    }
+
+    /// Attempt a delivery again now; a failed one gets a fresh attempt budget
+    pub async fn retry(&self, delivery_id: &str, now: DateTime<Utc>) -> Result<bool, String> {
+        let found = if let Some(mut con) = self.connection().await? {
+            let found = self.load(&mut con, &[delivery_id.to_string()]).await?.pop();
+            if let Some(delivery) = found.as_ref().filter(|d| d.status != DeliveryStatus::Pending) {
+                redis::cmd("ZREM")
+                    .arg(format!("outbound:finished:{}", delivery.endpoint_id))
+                    .arg(&delivery.id)
+                    .query_async::<_, i64>(&mut con)
+                    .await
+                    .map_err(redis_err)?;
+            }
+            found
+        } else {
+            let mut log = self.log.write().await;
+            log.pending.remove(delivery_id).or_else(|| {
+                log.finished.values_mut().find_map(|finished| {
+                    let index = finished.iter().position(|d| d.id == delivery_id)?;
+                    finished.remove(index)
+                })
+            })
+        };
+        let Some(mut delivery) = found else {
+            return Ok(false);
+        };
+
+        if delivery.status != DeliveryStatus::Pending {
+            delivery.status = DeliveryStatus::Pending;
+            delivery.attempts.clear();
+        }
+        delivery.next_attempt_at = now;
+        self.save_pending(delivery).await?;
+        self.notify.notify_one();
+        Ok(true)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DISPATCH LOOP
+// ═══════════════════════════════════════════════════════════════════════════════
+
+async fn deliver(state: Arc<StripeWebhookState>) {
+    loop {
+        if let Err(e) = state.outbound.deliver_due(state.clock.now()).await {
+            println!("[OUTBOUND-WEBHOOK] ❌ Delivery pass failed: {}", e);
+        }
+        tokio::select! {
+            _ = state.outbound.notify.notified() => {}
+            _ = tokio::time::sleep(state.outbound.config.poll_interval) => {}
+        }
+    }
+}
+
+/// Start delivering queued events to the configured endpoints
+pub fn spawn(state: Arc<StripeWebhookState>) {
+    if state.outbound.config.endpoints.is_empty() {
+        println!("[OUTBOUND-WEBHOOK] ℹ️ No OUTBOUND_WEBHOOK_ENDPOINTS configured");
+        return;
+    }
+    println!(
+        "[OUTBOUND-WEBHOOK] 📤 Delivering to {} endpoint(s)",
+        state.outbound.config.endpoints.len()
+    );
+    tokio::spawn(deliver(state));
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DELIVERY LOGS (ADMIN)
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Serialize)]
+pub struct EndpointSummary {
+    pub id: String,
+    pub url: String,
+    pub events: Vec<String>,
+    pub pending: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+pub async fn list_webhook_endpoints(
+    State(state): State<Arc<StripeWebhookState>>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(e) = state.auth.require_admin(&headers) {
+        return e.into_response();
+    }
+
+    let mut summaries = Vec::new();
+    for endpoint in &state.outbound.config.endpoints {
+        let deliveries = match state.outbound.deliveries(&endpoint.id).await {
+            Ok(deliveries) => deliveries,
+            Err(e) => return (StatusCode::SERVICE_UNAVAILABLE, e).into_response(),
+        };
+        let count = |status| deliveries.iter().filter(|d| d.status == status).count();
+        summaries.push(EndpointSummary {
+            id: endpoint.id.clone(),
+            url: endpoint.url.clone(),
+            events: endpoint.events.clone(),
+            pending: count(DeliveryStatus::Pending),
+            succeeded: count(DeliveryStatus::Succeeded),
+            failed: count(DeliveryStatus::Failed),
+        });
+    }
+    Json(summaries).into_response()
+}
+
+pub async fn list_webhook_deliveries(
+    State(state): State<Arc<StripeWebhookState>>,
+    headers: HeaderMap,
+    Path(endpoint_id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(e) = state.auth.require_admin(&headers) {
+        return e.into_response();
+    }
+
+    if !state.outbound.config.endpoints.iter().any(|e| e.id == endpoint_id) {
+        return (StatusCode::NOT_FOUND, "Unknown endpoint").into_response();
+    }
+    match state.outbound.deliveries(&endpoint_id).await {
+        Ok(deliveries) => Json(deliveries).into_response(),
+        Err(e) => (StatusCode::SERVICE_UNAVAILABLE, e).into_response(),
+    }
+}
+
+pub async fn retry_webhook_delivery(
+    State(state): State<Arc<StripeWebhookState>>,
+    headers: HeaderMap,
+    Path(delivery_id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(e) = state.auth.require_admin(&headers) {
+        return e.into_response();
+    }
+
+    match state.outbound.retry(&delivery_id, state.clock.now()).await {
+        Ok(true) => StatusCode::ACCEPTED.into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, "No such delivery").into_response(),
+        Err(e) => (StatusCode::SERVICE_UNAVAILABLE, e).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::DomainEventType;
+    use axum::{routing::post, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Clone, Default)]
+    struct Receiver {
+        calls: Arc<AtomicUsize>,
+        signatures: Arc<RwLock<Vec<(String, String)>>>,
+    }
+
+    async fn receive(State(receiver): State<Receiver>, headers: HeaderMap, body: String) -> StatusCode {
+        let signature = headers[SIGNATURE_HEADER].to_str().unwrap().to_string();
+        receiver.signatures.write().await.push((signature, body));
+        // Down for the first delivery attempt
+        if receiver.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else {
+            StatusCode::OK
+        }
+    }
+
+    #[tokio::test]
+    async fn signed_delivery_is_retried_with_backoff_and_logged() {
+        let receiver = Receiver::default();
+        let base = crate::test_support::spawn_mock(
+            Router::new().route("/hooks", post(receive)).with_state(receiver.clone()),
+        )
+        .await;
+
+        let mut config = OutboundWebhookConfig::from_env();
+        config.endpoints = vec![
+            WebhookEndpoint {
+                id: "product-api".into(),
+                url: format!("{}/hooks", base),
+                secret: "whsec_downstream".into(),
+                events: vec!["subscription.*".into()],
+            },
+            WebhookEndpoint {
+                id: "ledger".into(),
+                url: format!("{}/unused", base),
+                secret: "whsec_ledger".into(),
+                events: vec!["payment.succeeded".into()],
+            },
+        ];
+        config.backoff_base = Duration::from_secs(30);
+        let dispatcher = OutboundDispatcher::new(config, None);
+
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let event = DomainEvent::new(
+            DomainEventType::SubscriptionActivated,
+            Some("evt_1"),
+            serde_json::json!({ "email": "ada@example.com" }),
+            now,
+        );
+        assert_eq!(dispatcher.enqueue(&event, now).await, Ok(1));
+        // The same event republished by a reprocessed handler is already queued
+        assert_eq!(dispatcher.enqueue(&event, now).await, Ok(0));
+
+        assert_eq!(dispatcher.deliver_due(now).await, Ok(1));
+        // Backing off, jittered below the 30s base for the first retry
+        assert_eq!(dispatcher.deliver_due(now + chrono::Duration::seconds(30)).await, Ok(1));
+        assert_eq!(dispatcher.enqueue(&event, now).await, Ok(0));
+
+        let deliveries = dispatcher.deliveries("product-api").await.unwrap();
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].status, DeliveryStatus::Succeeded);
+        let codes: Vec<_> = deliveries[0].attempts.iter().map(|a| a.status_code).collect();
+        assert_eq!(codes, [Some(503), Some(200)]);
+        assert!(dispatcher.deliveries("ledger").await.unwrap().is_empty());
+
+        // The receiver can verify what it got
+        let (signature, body) = receiver.signatures.read().await[1].clone();
+        let ts = now.timestamp() + 30;
+        assert_eq!(signature, sign_payload("whsec_downstream", ts, &body));
+        let delivered: DomainEvent = serde_json::from_str(&body).unwrap();
+        assert_eq!(delivered.event_type, "subscription.activated");
+        assert_eq!(delivered.id, event.id);
+    }
+}
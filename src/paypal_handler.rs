@@ -1,19 +1,31 @@
 // lwas_economy/src/payments/paypal_handler.rs
 // ARCHITECT: QANTUM AETERNA | STATUS: BETA
-// PayPal Webhook Handler & Order Management
+// PayPal Webhook Handler, Order Management & Dispute Workflow
 
 use axum::{
-    extract::{Json, State},
+    extract::{Json, Path, Query, State},
     http::{HeaderMap, StatusCode},
     response::IntoResponse,
 };
+use base64::Engine;
 use chrono::{DateTime, Utc};
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::RwLock;
 
+use crate::alerting::Alerter;
+use crate::auth::Authenticator;
+use crate::currency;
+use crate::event_archive::{ArchiveOutcome, ArchivedEvent, EventArchive, Provider};
+use crate::events::{self, DomainEvent, DomainEventType, EventBus};
+use crate::http_client::{OutboundClient, OutboundConfig};
+use crate::metrics::{metrics, webhook_rejected};
+use crate::notifications::{Notification, Notifier};
+use crate::stripe_handler::{StripeWebhookState, SubscriptionManager};
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // PAYPAL CONFIGURATION
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -23,27 +35,91 @@ pub struct PayPalConfig {
     pub client_id: String,
     pub client_secret: String,
     pub mode: String, // "sandbox" or "live"
+    pub api_base: String,
     pub webhook_id: String,
+    pub dispute_policy: DisputePolicy,
 }
 
 impl PayPalConfig {
     pub fn from_env() -> Self {
+        let mode = std::env::var("PAYPAL_MODE").unwrap_or_else(|_| "sandbox".to_string());
+        let default_base = if mode == "live" {
+            "https://api-m.paypal.com"
+        } else {
+            "https://api-m.sandbox.paypal.com"
+        };
+
         Self {
             client_id: std::env::var("PAYPAL_CLIENT_ID")
                 .unwrap_or_else(|_| "sb_client_id_placeholder".to_string()),
             client_secret: std::env::var("PAYPAL_CLIENT_SECRET")
                 .unwrap_or_else(|_| "sb_client_secret_placeholder".to_string()),
-            mode: std::env::var("PAYPAL_MODE").unwrap_or_else(|_| "sandbox".to_string()),
+            api_base: std::env::var("PAYPAL_API_BASE").unwrap_or_else(|_| default_base.to_string()),
+            mode,
             webhook_id: std::env::var("PAYPAL_WEBHOOK_ID")
                 .unwrap_or_else(|_| "wh_id_placeholder".to_string()),
+            dispute_policy: std::env::var("PAYPAL_DISPUTE_POLICY")
+                .map(|v| DisputePolicy::parse(&v))
+                .unwrap_or(DisputePolicy::SuspendOnClaim),
         }
     }
 
-    pub fn base_url(&self) -> &str {
-        if self.mode == "live" {
-            "https://api-m.paypal.com"
+    /// Refuse live mode while any value is still a placeholder default
+    pub fn validate(&self) -> Result<(), String> {
+        if self.mode != "live" {
+            return Ok(());
+        }
+
+        let placeholders: Vec<&str> = [
+            ("PAYPAL_CLIENT_ID", &self.client_id),
+            ("PAYPAL_CLIENT_SECRET", &self.client_secret),
+            ("PAYPAL_WEBHOOK_ID", &self.webhook_id),
+        ]
+        .iter()
+        .filter(|(_, value)| value.contains("placeholder"))
+        .map(|(name, _)| *name)
+        .collect();
+
+        if placeholders.is_empty() {
+            Ok(())
         } else {
-            "https://api-m.sandbox.paypal.com"
+            Err(format!(
+                "PayPal live mode configured but placeholders remain: {}",
+                placeholders.join(", ")
+            ))
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.api_base
+    }
+}
+
+/// When a dispute should cost the buyer their entitlement
+#[derive(Clone, Debug, PartialEq)]
+pub enum DisputePolicy {
+    /// Suspend as soon as any dispute (including an inquiry) is opened
+    SuspendOnOpen,
+    /// Suspend only once the dispute escalates past the INQUIRY stage
+    SuspendOnClaim,
+    /// Track disputes but never touch entitlements
+    Ignore,
+}
+
+impl DisputePolicy {
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "suspend_on_open" => DisputePolicy::SuspendOnOpen,
+            "ignore" => DisputePolicy::Ignore,
+            _ => DisputePolicy::SuspendOnClaim,
+        }
+    }
+
+    pub fn should_suspend(&self, stage: Option<&str>) -> bool {
+        match self {
+            DisputePolicy::SuspendOnOpen => true,
+            DisputePolicy::SuspendOnClaim => stage.is_some_and(|s| s != "INQUIRY"),
+            DisputePolicy::Ignore => false,
         }
     }
 }
@@ -62,23 +138,213 @@ pub struct PayPalEvent {
     pub summary: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayPalMoney {
+    pub currency_code: String,
+    pub value: String,
+}
+
+/// `resource` payload of CUSTOMER.DISPUTE.* events
+#[derive(Debug, Clone, Deserialize)]
+pub struct DisputeResource {
+    pub dispute_id: String,
+    pub reason: Option<String>,
+    pub status: Option<String>,
+    pub dispute_life_cycle_stage: Option<String>,
+    pub dispute_amount: Option<PayPalMoney>,
+    pub seller_response_due_date: Option<String>,
+    pub update_time: Option<String>,
+    #[serde(default)]
+    pub disputed_transactions: Vec<DisputedTransaction>,
+    pub dispute_outcome: Option<DisputeOutcome>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DisputedTransaction {
+    pub seller_transaction_id: Option<String>,
+    /// What checkout passed as `custom_id` on the disputed payment
+    #[serde(alias = "custom")]
+    pub custom_id: Option<String>,
+    pub buyer: Option<DisputeBuyer>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DisputeBuyer {
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DisputeOutcome {
+    pub outcome_code: String,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DISPUTE STORE
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PayPalDispute {
+    pub dispute_id: String,
+    pub reason: Option<String>,
+    pub status: String,
+    pub stage: Option<String>,
+    pub amount: Option<PayPalMoney>,
+    pub buyer_email: Option<String>,
+    /// Our user, when the disputed payment is one we recorded; only then are entitlements touched
+    #[serde(default)]
+    pub user_id: Option<uuid::Uuid>,
+    pub seller_transaction_id: Option<String>,
+    pub seller_response_due: Option<DateTime<Utc>>,
+    pub outcome: Option<String>,
+    pub entitlement_suspended: bool,
+    /// Smallest `dispute_alert_hours` threshold already alerted for
+    #[serde(default)]
+    pub alerted_within_hours: Option<u64>,
+    pub provider_updated_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone)]
+pub struct PayPalDisputeStore {
+    // Same in-memory model as SubscriptionManager; swap for DB alongside it.
+    disputes: Arc<RwLock<HashMap<String, PayPalDispute>>>,
+    /// Capture id → our user, for PayPal payments checkout made for that user
+    captures: Arc<RwLock<HashMap<String, uuid::Uuid>>>,
+}
+
+impl PayPalDisputeStore {
+    pub fn new() -> Self {
+        Self {
+            disputes: Arc::new(RwLock::new(HashMap::new())),
+            captures: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub async fn record_capture(&self, capture_id: &str, user_id: uuid::Uuid) {
+        let mut captures = self.captures.write().await;
+        captures.insert(capture_id.to_string(), user_id);
+    }
+
+    /// The user a disputed payment belongs to: the owner of the capture we recorded, or the
+    /// `custom_id` user if they paid us through PayPal at all. `None` for anything else.
+    pub async fn paypal_user(&self, capture_id: Option<&str>, custom_id: Option<uuid::Uuid>) -> Option<uuid::Uuid> {
+        let captures = self.captures.read().await;
+        capture_id
+            .and_then(|id| captures.get(id).copied())
+            .or_else(|| custom_id.filter(|user| captures.values().any(|owner| owner == user)))
+    }
+
+    pub async fn get(&self, dispute_id: &str) -> Option<PayPalDispute> {
+        let store = self.disputes.read().await;
+        store.get(dispute_id).cloned()
+    }
+
+    pub async fn upsert(&self, dispute: PayPalDispute) {
+        let mut store = self.disputes.write().await;
+        store.insert(dispute.dispute_id.clone(), dispute);
+    }
+
+    /// All disputes, most urgent seller deadline first
+    pub async fn list(&self, status: Option<&str>) -> Vec<PayPalDispute> {
+        let store = self.disputes.read().await;
+        let mut disputes: Vec<PayPalDispute> = store
+            .values()
+            .filter(|d| status.is_none_or(|s| d.status == s))
+            .cloned()
+            .collect();
+        disputes.sort_by_key(|d| d.seller_response_due.unwrap_or(DateTime::<Utc>::MAX_UTC));
+        disputes
+    }
+
+    /// Open disputes whose seller response deadline crossed a new threshold since the last
+    /// pass, with the threshold crossed (hours). Each threshold fires once per dispute.
+    pub async fn take_deadline_alerts(
+        &self,
+        now: DateTime<Utc>,
+        thresholds_hours: &[u64],
+    ) -> Vec<(PayPalDispute, u64)> {
+        let mut store = self.disputes.write().await;
+        let mut due = Vec::new();
+
+        for dispute in store.values_mut() {
+            let Some(due_by) = dispute.seller_response_due.filter(|_| dispute.outcome.is_none()) else {
+                continue;
+            };
+            let remaining = due_by - now;
+            let crossed = thresholds_hours
+                .iter()
+                .copied()
+                .filter(|h| remaining <= chrono::Duration::hours(*h as i64))
+                .filter(|h| dispute.alerted_within_hours.is_none_or(|alerted| *h < alerted))
+                .min();
+
+            if let Some(hours) = crossed {
+                dispute.alerted_within_hours = Some(hours);
+                due.push((dispute.clone(), hours));
+            }
+        }
+
+        due
+    }
+}
+
+/// Body of `verify-webhook-signature`. PayPal checks the signature against `webhook_event`,
+/// so it goes back exactly as delivered: re-serializing a `Value` would reorder its keys.
+#[derive(Serialize)]
+struct VerifySignatureRequest<'a> {
+    auth_algo: String,
+    cert_url: String,
+    transmission_id: String,
+    transmission_sig: String,
+    transmission_time: String,
+    webhook_id: &'a str,
+    webhook_event: &'a RawValue,
+}
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // PAYPAL STATE
 // ═══════════════════════════════════════════════════════════════════════════════
 
+type CachedToken = Arc<RwLock<Option<(String, DateTime<Utc>)>>>;
+
 #[derive(Clone)]
 pub struct PayPalState {
     pub config: PayPalConfig,
-    pub http_client: Client,
-    pub auth_token: Arc<RwLock<Option<(String, DateTime<Utc>)>>>, 
+    pub http_client: OutboundClient,
+    pub auth_token: CachedToken,
+    /// Shared with the Stripe side for the admin token
+    pub auth: Authenticator,
+    pub subscriptions: SubscriptionManager,
+    pub disputes: PayPalDisputeStore,
+    pub archive: EventArchive,
+    /// Shared with the Stripe side: consumers see one stream whatever the provider
+    pub events: EventBus,
+    pub notifier: Notifier,
+    pub alerter: Alerter,
 }
 
 impl PayPalState {
-    pub fn new() -> Self {
+    pub fn new(
+        auth: Authenticator,
+        subscriptions: SubscriptionManager,
+        disputes: PayPalDisputeStore,
+        archive: EventArchive,
+        events: EventBus,
+        notifier: Notifier,
+        alerter: Alerter,
+    ) -> Self {
         Self {
             config: PayPalConfig::from_env(),
-            http_client: Client::new(),
+            http_client: OutboundClient::new("paypal", OutboundConfig::from_env()),
             auth_token: Arc::new(RwLock::new(None)),
+            auth,
+            subscriptions,
+            disputes,
+            archive,
+            events,
+            notifier,
+            alerter,
         }
     }
 
@@ -96,19 +362,19 @@ impl PayPalState {
 
         // Refresh token
         let auth_str = format!("{}:{}", self.config.client_id, self.config.client_secret);
-        let auth_basic = base64::encode(auth_str);
+        let auth_basic = base64::engine::general_purpose::STANDARD.encode(auth_str);
 
         let url = format!("{}/v1/oauth2/token", self.config.base_url());
         let params = [("grant_type", "client_credentials")];
 
         let resp = self
             .http_client
-            .post(&url)
-            .header("Authorization", format!("Basic {}", auth_basic))
-            .form(&params)
-            .send()
-            .await
-            .map_err(|e| format!("Request failed: {}", e))?;
+            .send("POST /v1/oauth2/token", |c| {
+                c.post(&url)
+                    .header("Authorization", format!("Basic {}", auth_basic))
+                    .form(&params)
+            })
+            .await?;
 
         if !resp.status().is_success() {
             return Err(format!("Auth failed: {}", resp.status()));
@@ -130,6 +396,46 @@ impl PayPalState {
 
         Ok(access_token)
     }
+
+    /// Verify a webhook delivery via PayPal's `verify-webhook-signature` API
+    pub async fn verify_webhook(&self, headers: &HeaderMap, body: &str) -> Result<(), String> {
+        let header = |name: &str| -> Result<String, String> {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+                .ok_or(format!("Missing {} header", name))
+        };
+
+        let request = VerifySignatureRequest {
+            auth_algo: header("paypal-auth-algo")?,
+            cert_url: header("paypal-cert-url")?,
+            transmission_id: header("paypal-transmission-id")?,
+            transmission_sig: header("paypal-transmission-sig")?,
+            transmission_time: header("paypal-transmission-time")?,
+            webhook_id: &self.config.webhook_id,
+            webhook_event: serde_json::from_str(body).map_err(|e| format!("Invalid event JSON: {}", e))?,
+        };
+
+        let token = self.get_access_token().await?;
+        let url = format!("{}/v1/notifications/verify-webhook-signature", self.config.base_url());
+        let resp = self
+            .http_client
+            .send("POST /v1/notifications/verify-webhook-signature", |c| {
+                c.post(&url).bearer_auth(&token).json(&request)
+            })
+            .await?;
+
+        if !resp.status().is_success() {
+            return Err(format!("Verification call failed: {}", resp.status()));
+        }
+
+        let body: serde_json::Value = resp.json().await.map_err(|e| format!("JSON error: {}", e))?;
+        match body["verification_status"].as_str() {
+            Some("SUCCESS") => Ok(()),
+            other => Err(format!("Verification status: {}", other.unwrap_or("missing"))),
+        }
+    }
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -139,29 +445,792 @@ impl PayPalState {
 pub async fn paypal_webhook_handler(
     State(state): State<Arc<PayPalState>>,
     headers: HeaderMap,
-    Json(event): Json<PayPalEvent>,
+    body: String,
 ) -> impl IntoResponse {
-    println!("[PAYPAL] 📬 Received: {} ({})", event.event_type, event.id);
+    metrics().inc_counter("webhooks_received_total", &[("provider", "paypal")]);
+
+    let raw: serde_json::Value = match serde_json::from_str(&body) {
+        Ok(v) => v,
+        Err(e) => {
+            println!("[PAYPAL] ❌ Failed to parse event: {}", e);
+            webhook_rejected("paypal", "unknown", "invalid_payload");
+            return (StatusCode::BAD_REQUEST, "Invalid event").into_response();
+        }
+    };
 
-    // TODO: Implement signature verification using PayPal's 'verify-webhook-signature' API
-    // This is critical for production but omitted for brevity in this initial deployment.
-    // Ideally, we post the headers and body back to PayPal to verify.
+    // Disputes change entitlements, so every delivery must come from PayPal
+    if let Err(e) = state.verify_webhook(&headers, &body).await {
+        println!("[PAYPAL] ❌ Signature verification failed: {}", e);
+        webhook_rejected("paypal", "unknown", "invalid_signature");
+        state.alerter.signature_failed(Provider::PayPal, Utc::now());
+        return (StatusCode::UNAUTHORIZED, "Invalid signature").into_response();
+    }
+
+    let event: PayPalEvent = match serde_json::from_value(raw) {
+        Ok(e) => e,
+        Err(e) => {
+            println!("[PAYPAL] ❌ Failed to parse event: {}", e);
+            webhook_rejected("paypal", "unknown", "invalid_payload");
+            return (StatusCode::BAD_REQUEST, "Invalid event").into_response();
+        }
+    };
 
-    match event.event_type.as_str() {
+    println!("[PAYPAL] 📬 Received: {} ({})", event.event_type, event.id);
+    metrics().inc_counter(
+        "webhooks_verified_total",
+        &[("provider", "paypal"), ("event_type", &event.event_type)],
+    );
+    state.alerter.webhook_received(Utc::now());
+    state
+        .archive
+        .record(ArchivedEvent::new(Provider::PayPal, &event.id, &event.event_type, &headers, body, Utc::now()))
+        .await;
+
+    let started = Instant::now();
+    let result = match event.event_type.as_str() {
         "PAYMENT.CAPTURE.COMPLETED" => {
             println!("[PAYPAL] 💰 Payment Captured: {:?}", event.resource["amount"]);
+            if let (Some(capture_id), Some(user_id)) = (
+                event.resource["id"].as_str(),
+                custom_user_id(&event.resource).and_then(|id| uuid::Uuid::parse_str(&id).ok()),
+            ) {
+                state.disputes.record_capture(capture_id, user_id).await;
+            }
             // Trigger logic: update DB, grant access, etc.
+            state.events.publish(DomainEvent::new(
+                DomainEventType::PaymentSucceeded,
+                Some(&event.id),
+                serde_json::json!({
+                    "provider": "paypal",
+                    "user_id": custom_user_id(&event.resource),
+                    "capture_id": event.resource["id"],
+                    "amount": amount_minor_units(&event.resource["amount"]),
+                    "currency": event.resource["amount"]["currency_code"].as_str().map(|c| c.to_lowercase()),
+                }),
+                Utc::now(),
+            )).await;
+            Ok(())
         }
         "BILLING.SUBSCRIPTION.CREATED" => {
-             println!("[PAYPAL] 📋 Subscription Created: {:?}", event.resource["id"]);
+            println!("[PAYPAL] 📋 Subscription Created: {:?}", event.resource["id"]);
+            Ok(())
         }
         "BILLING.SUBSCRIPTION.CANCELLED" => {
-             println!("[PAYPAL] ❌ Subscription Cancelled: {:?}", event.resource["id"]);
+            println!("[PAYPAL] ❌ Subscription Cancelled: {:?}", event.resource["id"]);
+            state.events.publish(DomainEvent::new(
+                DomainEventType::SubscriptionCanceled,
+                Some(&event.id),
+                serde_json::json!({
+                    "provider": "paypal",
+                    "user_id": custom_user_id(&event.resource),
+                    "email": event.resource["subscriber"]["email_address"],
+                    "subscription_id": event.resource["id"],
+                    "status": "canceled",
+                }),
+                Utc::now(),
+            )).await;
+            Ok(())
+        }
+        "CUSTOMER.DISPUTE.CREATED" | "CUSTOMER.DISPUTE.UPDATED" | "CUSTOMER.DISPUTE.RESOLVED" => {
+            handle_dispute_event(&state, &event).await
         }
         _ => {
-             println!("[PAYPAL] ℹ️ Unhandled: {}", event.event_type);
+            println!("[PAYPAL] ℹ️ Unhandled: {}", event.event_type);
+            Ok(())
+        }
+    };
+    metrics().observe(
+        "webhook_processing_duration_seconds",
+        &[("provider", "paypal"), ("event_type", &event.event_type)],
+        started.elapsed().as_secs_f64(),
+    );
+
+    let outcome = match &result {
+        Ok(_) => (ArchiveOutcome::Processed, None),
+        Err(e) => (ArchiveOutcome::Failed, Some(e.clone())),
+    };
+    state.archive.set_outcome(&event.id, outcome.0, outcome.1, Utc::now()).await;
+
+    match result {
+        Ok(_) => (StatusCode::OK, "Received").into_response(),
+        Err(e) => {
+            println!("[PAYPAL] ❌ Processing error: {}", e);
+            state.alerter.processing_failed(&event.id, &event.event_type, &e, Utc::now());
+            (StatusCode::INTERNAL_SERVER_ERROR, e).into_response()
         }
     }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DISPUTE HANDLING
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Our user id, which checkout passes to PayPal as `custom_id`
+fn custom_user_id(resource: &serde_json::Value) -> Option<String> {
+    resource["custom_id"]
+        .as_str()
+        .and_then(|id| uuid::Uuid::parse_str(id).ok())
+        .map(|id| id.to_string())
+}
+
+/// PayPal amounts are decimal strings (`{"value": "9.5", "currency_code": "USD"}`);
+/// domain events carry the smallest currency unit, like Stripe
+fn amount_minor_units(amount: &serde_json::Value) -> Option<i64> {
+    currency::parse_minor_units(amount["value"].as_str()?, amount["currency_code"].as_str()?)
+}
 
-    (StatusCode::OK, "Received").into_response()
+fn parse_paypal_time(value: Option<&str>) -> Option<DateTime<Utc>> {
+    value
+        .and_then(|v| DateTime::parse_from_rfc3339(v).ok())
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+async fn handle_dispute_event(state: &PayPalState, event: &PayPalEvent) -> Result<(), String> {
+    let resource: DisputeResource = serde_json::from_value(event.resource.clone())
+        .map_err(|e| format!("Failed to parse dispute: {}", e))?;
+
+    let existing = state.disputes.get(&resource.dispute_id).await;
+    let provider_updated_at = parse_paypal_time(resource.update_time.as_deref());
+
+    // PayPal may redeliver an older snapshot after a newer one
+    if let (Some(prev), Some(incoming)) = (
+        existing.as_ref().and_then(|d| d.provider_updated_at),
+        provider_updated_at,
+    ) {
+        if incoming < prev {
+            println!("[DISPUTE] ⚡ Skipping stale update for {}", resource.dispute_id);
+            return Ok(());
+        }
+    }
+
+    let transaction = resource.disputed_transactions.first();
+    let buyer_email = transaction
+        .and_then(|t| t.buyer.as_ref())
+        .and_then(|b| b.email.clone())
+        .or_else(|| existing.as_ref().and_then(|d| d.buyer_email.clone()));
+
+    // Never by buyer email: the same address may hold an unrelated Stripe subscription
+    let linked_user = match state
+        .disputes
+        .paypal_user(
+            transaction.and_then(|t| t.seller_transaction_id.as_deref()),
+            transaction
+                .and_then(|t| t.custom_id.as_deref())
+                .and_then(|id| uuid::Uuid::parse_str(id).ok()),
+        )
+        .await
+    {
+        Some(user_id) => Some(user_id),
+        None => existing.as_ref().and_then(|d| d.user_id),
+    };
+    let linked = match linked_user {
+        Some(user_id) => state.subscriptions.get_by_user_id(user_id).await,
+        None => None,
+    };
+
+    let now = Utc::now();
+    let mut dispute = PayPalDispute {
+        dispute_id: resource.dispute_id.clone(),
+        reason: resource.reason.clone(),
+        status: resource.status.clone().unwrap_or_else(|| "OPEN".to_string()),
+        stage: resource.dispute_life_cycle_stage.clone(),
+        amount: resource.dispute_amount.clone(),
+        buyer_email: buyer_email.clone(),
+        user_id: linked_user,
+        seller_transaction_id: transaction.and_then(|t| t.seller_transaction_id.clone()),
+        seller_response_due: parse_paypal_time(resource.seller_response_due_date.as_deref()),
+        outcome: resource.dispute_outcome.as_ref().map(|o| o.outcome_code.clone()),
+        entitlement_suspended: existing.as_ref().is_some_and(|d| d.entitlement_suspended),
+        alerted_within_hours: existing.as_ref().and_then(|d| d.alerted_within_hours),
+        provider_updated_at,
+        created_at: existing.as_ref().map_or(now, |d| d.created_at),
+        updated_at: now,
+    };
+
+    if existing.is_none() {
+        let amount = dispute
+            .amount
+            .as_ref()
+            .map(|a| format!("{} {}", a.value, a.currency_code))
+            .unwrap_or_else(|| "-".to_string());
+        state.alerter.dispute_opened(
+            Provider::PayPal,
+            &dispute.dispute_id,
+            &amount,
+            dispute.reason.as_deref().unwrap_or("-"),
+            buyer_email.as_deref(),
+        );
+    }
+
+    println!(
+        "[DISPUTE] ⚖️ {} {} (stage: {:?}, due: {:?})",
+        event.event_type, dispute.dispute_id, dispute.stage, dispute.seller_response_due
+    );
+
+    let email = linked.as_ref().map(|sub| sub.email.as_str());
+    let changed = match (&dispute.outcome, email) {
+        (Some(outcome), Some(email)) => {
+            let changed = if outcome == "RESOLVED_BUYER_FAVOUR" {
+                // Money went back to the buyer: the entitlement goes with it
+                state
+                    .subscriptions
+                    .cancel_subscription(email)
+                    .await
+                    .then_some(DomainEventType::SubscriptionCanceled)
+            } else if dispute.entitlement_suspended {
+                state
+                    .subscriptions
+                    .reinstate_subscription(email)
+                    .await
+                    .then_some(DomainEventType::SubscriptionUpdated)
+            } else {
+                None
+            };
+            dispute.entitlement_suspended = false;
+            changed
+        }
+        (None, Some(email)) => {
+            let suspended = !dispute.entitlement_suspended
+                && state.config.dispute_policy.should_suspend(dispute.stage.as_deref())
+                && state.subscriptions.suspend_subscription(email).await;
+            dispute.entitlement_suspended |= suspended;
+            suspended.then_some(DomainEventType::SubscriptionUpdated)
+        }
+        (_, None) => {
+            println!(
+                "[DISPUTE] ⚠️ {} is not linked to a PayPal subscription, entitlements untouched",
+                dispute.dispute_id
+            );
+            None
+        }
+    };
+
+    state.events.publish(DomainEvent::new(
+        DomainEventType::DisputeUpdated,
+        Some(&event.id),
+        serde_json::json!({
+            "provider": "paypal",
+            "email": dispute.buyer_email,
+            "dispute_id": dispute.dispute_id,
+            "status": dispute.status,
+            "stage": dispute.stage,
+            "reason": dispute.reason,
+            "amount": dispute.amount.as_ref().and_then(|a| currency::parse_minor_units(&a.value, &a.currency_code)),
+            "currency": dispute.amount.as_ref().map(|a| a.currency_code.to_lowercase()),
+            "evidence_due_by": dispute.seller_response_due,
+        }),
+        now,
+    )).await;
+    if let (Some(event_type), Some(email)) = (changed, email) {
+        if let Some(sub) = state.subscriptions.get_by_email(email).await {
+            state
+                .events
+                .publish(DomainEvent::new(event_type, Some(&event.id), events::subscription_data(&sub), now))
+                .await;
+            if event_type == DomainEventType::SubscriptionCanceled {
+                state.notifier.send(Notification::subscription_canceled(&sub)).await;
+            }
+        }
+    }
+
+    state.disputes.upsert(dispute).await;
+    Ok(())
+}
+
+/// Alert on every dispute whose seller response deadline crossed a threshold; returns how
+/// many. Run by the scheduler next to the Stripe evidence deadlines, with the same thresholds.
+pub async fn alert_due_deadlines(state: &StripeWebhookState) -> usize {
+    let due = state
+        .paypal_disputes
+        .take_deadline_alerts(state.clock.now(), &state.config.dispute_alert_hours)
+        .await;
+
+    for (dispute, hours) in &due {
+        metrics().inc_counter(
+            "paypal_dispute_deadline_alerts_total",
+            &[("within_hours", hours.to_string().as_str())],
+        );
+        let amount = dispute
+            .amount
+            .as_ref()
+            .map(|a| format!("{} {}", a.value, a.currency_code))
+            .unwrap_or_else(|| "-".to_string());
+        if let Some(due_by) = dispute.seller_response_due {
+            state.alerter.dispute_deadline(
+                Provider::PayPal,
+                &dispute.dispute_id,
+                &amount,
+                dispute.buyer_email.as_deref(),
+                due_by,
+                *hours,
+            );
+        }
+    }
+
+    due.len()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DISPUTE ADMIN API
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Deserialize)]
+pub struct ListDisputesQuery {
+    pub status: Option<String>,
+}
+
+/// List tracked disputes (admin)
+pub async fn list_disputes(
+    State(state): State<Arc<PayPalState>>,
+    headers: HeaderMap,
+    Query(query): Query<ListDisputesQuery>,
+) -> impl IntoResponse {
+    if let Err(e) = state.auth.require_admin(&headers) {
+        return e.into_response();
+    }
+
+    Json(state.disputes.list(query.status.as_deref()).await).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct EvidenceDocument {
+    pub file_name: String,
+    pub content_base64: String,
+}
+
+#[derive(Deserialize)]
+pub struct SubmitEvidenceRequest {
+    pub notes: String,
+    pub evidence_type: Option<String>,
+    #[serde(default)]
+    pub documents: Vec<EvidenceDocument>,
+}
+
+/// Submit seller evidence for a dispute (admin)
+pub async fn submit_dispute_evidence(
+    State(state): State<Arc<PayPalState>>,
+    headers: HeaderMap,
+    Path(dispute_id): Path<String>,
+    Json(payload): Json<SubmitEvidenceRequest>,
+) -> impl IntoResponse {
+    if let Err(e) = state.auth.require_admin(&headers) {
+        return e.into_response();
+    }
+
+    let input = serde_json::json!({
+        "evidences": [{
+            "evidence_type": payload.evidence_type.as_deref().unwrap_or("OTHER"),
+            "notes": payload.notes,
+        }]
+    });
+
+    let mut documents = Vec::with_capacity(payload.documents.len());
+    for doc in payload.documents {
+        match base64::engine::general_purpose::STANDARD.decode(&doc.content_base64) {
+            Ok(bytes) => documents.push((doc.file_name, bytes)),
+            Err(_) => return (StatusCode::BAD_REQUEST, "Invalid document encoding").into_response(),
+        }
+    }
+
+    // Multipart bodies are consumed on send, so each retry rebuilds the form
+    let build_form = || {
+        let input_part = reqwest::multipart::Part::text(input.to_string())
+            .mime_str("application/json")
+            .expect("static mime type");
+        documents.iter().fold(
+            reqwest::multipart::Form::new().part("input", input_part),
+            |form, (name, bytes)| {
+                form.part(
+                    "evidence_file",
+                    reqwest::multipart::Part::bytes(bytes.clone()).file_name(name.clone()),
+                )
+            },
+        )
+    };
+
+    let path = format!("/v1/customer/disputes/{}/provide-evidence", dispute_id);
+    match call_dispute_api(&state, "POST /v1/customer/disputes/:id/provide-evidence", &path, |req| {
+        req.multipart(build_form())
+    })
+    .await
+    {
+        Ok(_) => {
+            println!("[DISPUTE] 📎 Evidence submitted for {}", dispute_id);
+            (StatusCode::OK, "Evidence submitted").into_response()
+        }
+        Err(e) => {
+            println!("[DISPUTE] ❌ Evidence submission failed for {}: {}", dispute_id, e);
+            (StatusCode::BAD_GATEWAY, e).into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AcceptClaimRequest {
+    pub note: String,
+}
+
+/// Accept liability for a dispute and refund the buyer (admin)
+pub async fn accept_dispute_claim(
+    State(state): State<Arc<PayPalState>>,
+    headers: HeaderMap,
+    Path(dispute_id): Path<String>,
+    Json(payload): Json<AcceptClaimRequest>,
+) -> impl IntoResponse {
+    if let Err(e) = state.auth.require_admin(&headers) {
+        return e.into_response();
+    }
+
+    let body = serde_json::json!({ "note": payload.note });
+    let path = format!("/v1/customer/disputes/{}/accept-claim", dispute_id);
+    match call_dispute_api(&state, "POST /v1/customer/disputes/:id/accept-claim", &path, |req| {
+        req.json(&body)
+    })
+    .await
+    {
+        Ok(_) => {
+            println!("[DISPUTE] 🏳️ Claim accepted for {}", dispute_id);
+            (StatusCode::OK, "Claim accepted").into_response()
+        }
+        Err(e) => {
+            println!("[DISPUTE] ❌ Accept claim failed for {}: {}", dispute_id, e);
+            (StatusCode::BAD_GATEWAY, e).into_response()
+        }
+    }
+}
+
+async fn call_dispute_api(
+    state: &PayPalState,
+    endpoint: &str,
+    path: &str,
+    build: impl Fn(reqwest::RequestBuilder) -> reqwest::RequestBuilder,
+) -> Result<serde_json::Value, String> {
+    let token = state.get_access_token().await?;
+    let url = format!("{}{}", state.config.base_url(), path);
+    // Same request id on every retry so PayPal applies the action once
+    let request_id = uuid::Uuid::new_v4().to_string();
+
+    let resp = state
+        .http_client
+        .send(endpoint, |c| {
+            build(
+                c.post(&url)
+                    .bearer_auth(&token)
+                    .header("PayPal-Request-Id", &request_id),
+            )
+        })
+        .await?;
+
+    let status = resp.status();
+    let body: serde_json::Value = resp.json().await.unwrap_or(serde_json::Value::Null);
+    if !status.is_success() {
+        return Err(format!("PayPal API error {}: {}", status, body));
+    }
+
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stripe_handler::{EventStamp, SubscriptionStatus};
+    use crate::test_support::ADMIN_TOKEN;
+    use axum::{routing::get, routing::post, Router};
+
+    /// Keys deliberately out of alphabetical order, with PayPal's own spacing
+    const DELIVERY: &str = r#"{"id":"WH-1","event_type":"PAYMENT.CAPTURE.COMPLETED","resource_type":"capture", "create_time":"2026-01-01T00:00:00Z","resource":{"id":"CAP-1","amount":{"value":"9.00","currency_code":"USD"}},"summary":"Payment completed"}"#;
+
+    fn oauth_mock() -> Router {
+        Router::new().route(
+            "/v1/oauth2/token",
+            post(|| async { Json(serde_json::json!({ "access_token": "A21", "expires_in": 3600 })) }),
+        )
+    }
+
+    async fn state_with_mock(mock: Router) -> PayPalState {
+        let stripe = StripeWebhookState::new(crate::test_support::authenticator());
+        let mut state = PayPalState::new(
+            stripe.auth.clone(),
+            stripe.subscriptions.clone(),
+            stripe.paypal_disputes.clone(),
+            stripe.archive.clone(),
+            stripe.events.clone(),
+            stripe.notifier.clone(),
+            stripe.alerter.clone(),
+        );
+        state.config.api_base = crate::test_support::spawn_mock(oauth_mock().merge(mock)).await;
+        state.config.dispute_policy = DisputePolicy::SuspendOnClaim;
+        let buyer = state
+            .subscriptions
+            .activate_subscription(
+                None,
+                "buyer@example.com",
+                None,
+                None,
+                "pro_monthly",
+                EventStamp { id: "evt_checkout".into(), created: 1 },
+            )
+            .await;
+        state.disputes.record_capture("CAP-1", buyer.user_id).await;
+        state
+    }
+
+    const SIGNATURE_HEADERS: [(&str, &str); 5] = [
+        ("paypal-auth-algo", "SHA256withRSA"),
+        ("paypal-cert-url", "https://api.paypal.com/v1/notifications/certs/CERT-1"),
+        ("paypal-transmission-id", "tx-1"),
+        ("paypal-transmission-sig", "sig"),
+        ("paypal-transmission-time", "2026-01-01T00:00:00Z"),
+    ];
+
+    fn dispute_event(id: &str, update_time: &str, stage: &str, outcome: Option<&str>) -> PayPalEvent {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "event_type": "CUSTOMER.DISPUTE.UPDATED",
+            "create_time": update_time,
+            "resource_type": "dispute",
+            "summary": null,
+            "resource": {
+                "dispute_id": "PP-D-1",
+                "reason": "MERCHANDISE_OR_SERVICE_NOT_RECEIVED",
+                "status": if outcome.is_some() { "RESOLVED" } else { "OPEN" },
+                "dispute_life_cycle_stage": stage,
+                "dispute_amount": { "currency_code": "USD", "value": "9.5" },
+                "seller_response_due_date": "2026-02-01T00:00:00Z",
+                "update_time": update_time,
+                "disputed_transactions": [{
+                    "seller_transaction_id": "CAP-1",
+                    "buyer": { "email": "buyer@example.com" }
+                }],
+                "dispute_outcome": outcome.map(|o| serde_json::json!({ "outcome_code": o })),
+            }
+        }))
+        .unwrap()
+    }
+
+    async fn status(state: &PayPalState) -> SubscriptionStatus {
+        state.subscriptions.get_by_email("buyer@example.com").await.unwrap().status
+    }
+
+    #[tokio::test]
+    async fn verification_sends_the_delivered_event_byte_for_byte() {
+        let mock = Router::new().route(
+            "/v1/notifications/verify-webhook-signature",
+            post(|body: String| async move {
+                let request: serde_json::Value = serde_json::from_str(&body).unwrap();
+                let exact = body.contains(&format!(r#""webhook_event":{}"#, DELIVERY));
+                let status = if exact && request["transmission_id"] == "tx-1" { "SUCCESS" } else { "FAILURE" };
+                Json(serde_json::json!({ "verification_status": status }))
+            }),
+        );
+        let state = state_with_mock(mock).await;
+        let url = crate::test_support::spawn_mock(
+            Router::new().route("/webhook", post(paypal_webhook_handler)).with_state(Arc::new(state)),
+        )
+        .await;
+        let client = reqwest::Client::new();
+
+        let resp = SIGNATURE_HEADERS
+            .iter()
+            .fold(client.post(format!("{}/webhook", url)), |req, (name, value)| req.header(*name, *value))
+            .body(DELIVERY)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+
+        let unsigned = client.post(format!("{}/webhook", url)).body(DELIVERY).send().await.unwrap();
+        assert_eq!(unsigned.status(), 401);
+    }
+
+    #[tokio::test]
+    async fn dispute_lifecycle_follows_policy() {
+        let state = state_with_mock(Router::new()).await;
+
+        // An inquiry alone does not suspend under suspend_on_claim
+        handle_dispute_event(&state, &dispute_event("WH-1", "2026-01-02T00:00:00Z", "INQUIRY", None)).await.unwrap();
+        assert_eq!(status(&state).await, SubscriptionStatus::Active);
+        // PayPal sent "9.5" USD: published in cents
+        let (published, _) = state.events.resume(Some("unknown"));
+        assert_eq!(published[0].data["amount"], 950);
+        assert_eq!(published[0].data["currency"], "usd");
+
+        handle_dispute_event(&state, &dispute_event("WH-2", "2026-01-03T00:00:00Z", "CHARGEBACK", None)).await.unwrap();
+        assert_eq!(status(&state).await, SubscriptionStatus::Suspended);
+        let dispute = state.disputes.get("PP-D-1").await.unwrap();
+        assert!(dispute.entitlement_suspended);
+        assert_eq!(dispute.seller_transaction_id.as_deref(), Some("CAP-1"));
+
+        // A redelivered older snapshot is skipped
+        let stale = dispute_event("WH-1", "2026-01-02T00:00:00Z", "INQUIRY", Some("RESOLVED_SELLER_FAVOUR"));
+        handle_dispute_event(&state, &stale).await.unwrap();
+        assert_eq!(status(&state).await, SubscriptionStatus::Suspended);
+
+        let won = dispute_event("WH-3", "2026-01-04T00:00:00Z", "CHARGEBACK", Some("RESOLVED_SELLER_FAVOUR"));
+        handle_dispute_event(&state, &won).await.unwrap();
+        assert_eq!(status(&state).await, SubscriptionStatus::Active);
+        assert!(!state.disputes.get("PP-D-1").await.unwrap().entitlement_suspended);
+
+        let lost = dispute_event("WH-4", "2026-01-05T00:00:00Z", "CHARGEBACK", Some("RESOLVED_BUYER_FAVOUR"));
+        handle_dispute_event(&state, &lost).await.unwrap();
+        assert_eq!(status(&state).await, SubscriptionStatus::Canceled);
+    }
+
+    #[tokio::test]
+    async fn dispute_on_a_payment_we_did_not_record_leaves_entitlements_alone() {
+        let state = state_with_mock(Router::new()).await;
+        // Same buyer email, but a PayPal payment that is not one of our captures
+        let mut event = dispute_event("WH-1", "2026-01-02T00:00:00Z", "CHARGEBACK", None);
+        event.resource["disputed_transactions"][0]["seller_transaction_id"] = "CAP-OTHER".into();
+        handle_dispute_event(&state, &event).await.unwrap();
+        assert_eq!(status(&state).await, SubscriptionStatus::Active);
+        let dispute = state.disputes.get("PP-D-1").await.unwrap();
+        assert!(!dispute.entitlement_suspended && dispute.user_id.is_none());
+
+        let mut lost = dispute_event("WH-2", "2026-01-03T00:00:00Z", "CHARGEBACK", Some("RESOLVED_BUYER_FAVOUR"));
+        lost.resource["disputed_transactions"][0]["seller_transaction_id"] = "CAP-OTHER".into();
+        handle_dispute_event(&state, &lost).await.unwrap();
+        assert_eq!(status(&state).await, SubscriptionStatus::Active);
+
+        // Linked by the user id checkout passed along instead
+        let user_id = state.subscriptions.get_by_email("buyer@example.com").await.unwrap().user_id;
+        let mut by_custom_id = dispute_event("WH-3", "2026-01-04T00:00:00Z", "CHARGEBACK", None);
+        by_custom_id.resource["dispute_id"] = "PP-D-2".into();
+        by_custom_id.resource["disputed_transactions"][0]["seller_transaction_id"] = "CAP-OTHER".into();
+        by_custom_id.resource["disputed_transactions"][0]["custom_id"] = user_id.to_string().into();
+        handle_dispute_event(&state, &by_custom_id).await.unwrap();
+        assert_eq!(status(&state).await, SubscriptionStatus::Suspended);
+    }
+
+    #[tokio::test]
+    async fn other_policies_suspend_on_open_or_never() {
+        let mut state = state_with_mock(Router::new()).await;
+        state.config.dispute_policy = DisputePolicy::Ignore;
+        handle_dispute_event(&state, &dispute_event("WH-1", "2026-01-02T00:00:00Z", "CHARGEBACK", None)).await.unwrap();
+        assert_eq!(status(&state).await, SubscriptionStatus::Active);
+
+        let buyer = state.subscriptions.get_by_email("buyer@example.com").await.unwrap();
+        state.disputes = PayPalDisputeStore::new();
+        state.disputes.record_capture("CAP-1", buyer.user_id).await;
+        state.config.dispute_policy = DisputePolicy::SuspendOnOpen;
+        handle_dispute_event(&state, &dispute_event("WH-1", "2026-01-02T00:00:00Z", "INQUIRY", None)).await.unwrap();
+        assert_eq!(status(&state).await, SubscriptionStatus::Suspended);
+
+        assert_eq!(DisputePolicy::parse("suspend_on_open"), DisputePolicy::SuspendOnOpen);
+        assert_eq!(DisputePolicy::parse("ignore"), DisputePolicy::Ignore);
+        assert_eq!(DisputePolicy::parse("anything else"), DisputePolicy::SuspendOnClaim);
+    }
+
+    #[tokio::test]
+    async fn response_deadlines_alert_once_per_threshold() {
+        let mut stripe = StripeWebhookState::new(crate::test_support::authenticator());
+        let (hook, mut posted) = crate::test_support::spawn_chat_hook().await;
+        stripe.alerter = Alerter::new(crate::alerting::AlertConfig {
+            webhook_url: Some(hook),
+            ..crate::alerting::AlertConfig::from_env()
+        }, None);
+        stripe.config.dispute_alert_hours = vec![72, 24];
+        let due = parse_paypal_time(Some("2026-02-01T00:00:00Z")).unwrap();
+        let clock = crate::clock::FixedClock::at(due - chrono::Duration::hours(100));
+        stripe.clock = clock.clone();
+        let mut state = state_with_mock(Router::new()).await;
+        state.disputes = stripe.paypal_disputes.clone();
+        state.alerter = stripe.alerter.clone();
+
+        handle_dispute_event(&state, &dispute_event("WH-1", "2026-01-02T00:00:00Z", "INQUIRY", None)).await.unwrap();
+        posted.recv().await.unwrap();
+        assert_eq!(alert_due_deadlines(&stripe).await, 0);
+
+        clock.set(due - chrono::Duration::hours(70));
+        assert_eq!(alert_due_deadlines(&stripe).await, 1);
+        assert_eq!(alert_due_deadlines(&stripe).await, 0);
+        let message = posted.recv().await.unwrap()["text"].as_str().unwrap().to_string();
+        assert!(message.contains("PayPal dispute PP-D-1") && message.contains("less than 72h"));
+
+        // Resolved disputes need no response
+        let won = dispute_event("WH-2", "2026-01-03T00:00:00Z", "INQUIRY", Some("RESOLVED_SELLER_FAVOUR"));
+        handle_dispute_event(&state, &won).await.unwrap();
+        clock.set(due - chrono::Duration::hours(1));
+        assert_eq!(alert_due_deadlines(&stripe).await, 0);
+    }
+
+    #[tokio::test]
+    async fn admin_endpoints_require_token_and_call_dispute_api() {
+        let mock = Router::new()
+            .route(
+                "/v1/customer/disputes/:id/accept-claim",
+                post(|headers: HeaderMap, Json(body): Json<serde_json::Value>| async move {
+                    assert_eq!(headers["authorization"], "Bearer A21");
+                    assert!(headers.contains_key("paypal-request-id"));
+                    assert_eq!(body["note"], "Refunding");
+                    Json(serde_json::json!({ "links": [] }))
+                }),
+            )
+            .route(
+                "/v1/customer/disputes/:id/provide-evidence",
+                post(|body: String| async move {
+                    assert!(body.contains("name=\"input\"") && body.contains("Tracking attached"));
+                    assert!(body.contains("filename=\"tracking.pdf\"") && body.contains("%PDF"));
+                    Json(serde_json::json!({ "links": [] }))
+                }),
+            );
+        let state = state_with_mock(mock).await;
+        handle_dispute_event(&state, &dispute_event("WH-1", "2026-01-02T00:00:00Z", "INQUIRY", None)).await.unwrap();
+        let app = Router::new()
+            .route("/disputes", get(list_disputes))
+            .route("/disputes/:id/evidence", post(submit_dispute_evidence))
+            .route("/disputes/:id/accept", post(accept_dispute_claim))
+            .with_state(Arc::new(state));
+        let url = crate::test_support::spawn_mock(app).await;
+        let client = reqwest::Client::new();
+
+        let anonymous = client.get(format!("{}/disputes", url)).send().await.unwrap();
+        assert_eq!(anonymous.status(), 401);
+
+        let open: Vec<serde_json::Value> = client
+            .get(format!("{}/disputes?status=OPEN", url))
+            .bearer_auth(ADMIN_TOKEN)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0]["dispute_id"], "PP-D-1");
+
+        let evidence = |content: &str| {
+            serde_json::json!({
+                "notes": "Tracking attached",
+                "documents": [{ "file_name": "tracking.pdf", "content_base64": content }]
+            })
+        };
+        let bad = client
+            .post(format!("{}/disputes/PP-D-1/evidence", url))
+            .bearer_auth(ADMIN_TOKEN)
+            .json(&evidence("not base64!"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(bad.status(), 400);
+
+        let pdf = base64::engine::general_purpose::STANDARD.encode("%PDF-1.4");
+        let submitted = client
+            .post(format!("{}/disputes/PP-D-1/evidence", url))
+            .bearer_auth(ADMIN_TOKEN)
+            .json(&evidence(&pdf))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(submitted.status(), 200);
+
+        let accepted = client
+            .post(format!("{}/disputes/PP-D-1/accept", url))
+            .bearer_auth(ADMIN_TOKEN)
+            .json(&serde_json::json!({ "note": "Refunding" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(accepted.status(), 200);
+    }
 }
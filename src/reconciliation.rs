@@ -0,0 +1,500 @@
+// lwas_economy/src/payments/reconciliation.rs
+// ARCHITECT: QANTUM AETERNA | STATUS: BETA
+// Reconciliation: diff local subscriptions against the Stripe list APIs, optionally repair
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::metrics::metrics;
+use crate::stripe_handler::{
+    log_payment_event, ApplyOutcome, EventStamp, StripeWebhookState, SubscriptionStatus, UserSubscription,
+};
+use crate::stripe_models::{Customer, Subscription};
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// RECONCILIATION CONFIGURATION
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Debug)]
+pub struct ReconcileConfig {
+    /// Run on the scheduler; the admin endpoint works either way
+    pub enabled: bool,
+    pub interval: Duration,
+    /// Scheduled runs fix what they find instead of only reporting it
+    pub repair: bool,
+    pub page_size: u32,
+}
+
+impl ReconcileConfig {
+    pub fn from_env() -> Self {
+        Self {
+            enabled: std::env::var("RECONCILE_ENABLED")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            interval: Duration::from_secs(
+                std::env::var("RECONCILE_INTERVAL_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(86400),
+            ),
+            repair: std::env::var("RECONCILE_REPAIR")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            page_size: std::env::var("RECONCILE_PAGE_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(100),
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// REPORT
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscrepancyKind {
+    /// Stripe has a live subscription we hold no record of
+    MissingLocally,
+    /// We grant a subscription Stripe does not know
+    MissingInStripe,
+    /// Both sides know it, but status or dates disagree
+    Drifted,
+}
+
+impl DiscrepancyKind {
+    fn name(&self) -> &'static str {
+        match self {
+            DiscrepancyKind::MissingLocally => "missing_locally",
+            DiscrepancyKind::MissingInStripe => "missing_in_stripe",
+            DiscrepancyKind::Drifted => "drifted",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FieldDiff {
+    pub field: &'static str,
+    pub local: Option<String>,
+    pub stripe: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Discrepancy {
+    pub kind: DiscrepancyKind,
+    pub subscription_id: String,
+    pub customer_id: Option<String>,
+    pub email: Option<String>,
+    pub fields: Vec<FieldDiff>,
+    pub repaired: bool,
+    /// Why a repair was not attempted
+    pub note: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ReconciliationReport {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub repair: bool,
+    pub customers_scanned: usize,
+    pub subscriptions_scanned: usize,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl ReconciliationReport {
+    pub fn repaired(&self) -> usize {
+        self.discrepancies.iter().filter(|d| d.repaired).count()
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DIFF & REPAIR
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn timestamp(value: Option<i64>) -> Option<DateTime<Utc>> {
+    value.and_then(|ts| DateTime::from_timestamp(ts, 0))
+}
+
+fn diff<T: PartialEq + std::fmt::Debug>(field: &'static str, local: Option<T>, stripe: Option<T>) -> Option<FieldDiff> {
+    (local != stripe).then(|| FieldDiff {
+        field,
+        local: local.map(|v| format!("{:?}", v)),
+        stripe: stripe.map(|v| format!("{:?}", v)),
+    })
+}
+
+/// Where the local record disagrees with Stripe, honoring deliberate local overrides
+/// (a dispute suspension, the end of our dunning grace period)
+fn drift(local: &UserSubscription, stripe: &Subscription) -> Vec<FieldDiff> {
+    let incoming = SubscriptionStatus::from_stripe(&stripe.status);
+    [
+        diff("status", Some(local.status.clone()), Some(local.resolve_status(incoming))),
+        // Periods are only ever advanced from Stripe, never cleared
+        stripe
+            .current_period_end
+            .and_then(|_| diff("current_period_end", local.current_period_end, timestamp(stripe.current_period_end))),
+        diff("trial_end", local.trial_end, timestamp(stripe.trial_end)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Statuses worth a local record; abandoned (`incomplete`) and ended subscriptions aren't
+fn is_live(stripe: &Subscription) -> bool {
+    matches!(stripe.status.as_str(), "active" | "trialing" | "past_due")
+}
+
+/// Plan recorded at checkout, else the catalog plan selling the subscription's price
+fn plan_of(state: &StripeWebhookState, stripe: &Subscription) -> Option<String> {
+    stripe.metadata.get("plan").cloned().or_else(|| {
+        stripe
+            .price_id()
+            .and_then(|price| state.config.catalog.plan_for_price(price))
+            .map(|plan| plan.to_string())
+    })
+}
+
+/// Page through Stripe customers and subscriptions and diff them against `SubscriptionManager`.
+/// With `repair`, Stripe's current state is applied as if it were the newest event.
+pub async fn reconcile(state: &StripeWebhookState, repair: bool) -> Result<ReconciliationReport, String> {
+    let started_at = state.clock.now();
+    let page_size = state.config.reconcile.page_size;
+    // Taken before listing: a checkout completing mid-run is then missing from both sides,
+    // not local-only (which would read as deleted in Stripe and be revoked)
+    let locals = state.subscriptions.all().await;
+
+    let customers: Vec<Customer> = state
+        .api
+        .list_all("GET /v1/customers", "/v1/customers", &[], page_size)
+        .await?
+        .into_iter()
+        .filter_map(|c| serde_json::from_value(c).ok())
+        .collect();
+    let emails: HashMap<String, String> = customers
+        .iter()
+        .filter_map(|c| Some((c.id.clone(), c.email.clone()?)))
+        .collect();
+
+    let mut subscriptions = Vec::new();
+    for raw in state
+        .api
+        .list_all("GET /v1/subscriptions", "/v1/subscriptions", &[("status", "all")], page_size)
+        .await?
+    {
+        match serde_json::from_value::<Subscription>(raw.clone()) {
+            Ok(sub) => subscriptions.push(sub),
+            Err(e) => println!("[RECONCILE] ⚠️ Skipping unparsable subscription {}: {}", raw["id"], e),
+        }
+    }
+
+    // Stripe's answer is as of now: any event created before this is older
+    let stamp = EventStamp {
+        id: format!("reconcile_{}", started_at.timestamp()),
+        created: started_at.timestamp(),
+    };
+    let mut discrepancies = Vec::new();
+
+    for stripe in &subscriptions {
+        let local = locals.iter().find(|l| l.stripe_subscription_id.as_deref() == Some(stripe.id.as_str()));
+        let email = local.map(|l| l.email.clone()).or_else(|| emails.get(&stripe.customer).cloned());
+        let mut discrepancy = Discrepancy {
+            kind: DiscrepancyKind::Drifted,
+            subscription_id: stripe.id.clone(),
+            customer_id: Some(stripe.customer.clone()),
+            email: email.clone(),
+            fields: Vec::new(),
+            repaired: false,
+            note: None,
+        };
+
+        match local {
+            Some(local) => {
+                discrepancy.fields = drift(local, stripe);
+                if discrepancy.fields.is_empty() {
+                    continue;
+                }
+                if repair {
+                    discrepancy.repaired =
+                        state.subscriptions.apply_subscription_event(stripe, stamp.clone()).await == ApplyOutcome::Applied;
+                }
+            }
+            None if is_live(stripe) => {
+                discrepancy.kind = DiscrepancyKind::MissingLocally;
+                discrepancy.fields = vec![FieldDiff {
+                    field: "status",
+                    local: None,
+                    stripe: Some(stripe.status.clone()),
+                }];
+                let entitled_elsewhere = match &email {
+                    Some(email) => locals.iter().any(|l| l.email == *email && l.is_entitled(started_at)),
+                    None => false,
+                };
+
+                match (&email, plan_of(state, stripe)) {
+                    (None, _) => discrepancy.note = Some("customer has no email".into()),
+                    (_, None) => discrepancy.note = Some("plan unknown (no metadata, price not in catalog)".into()),
+                    // One record per email: don't displace a subscription that still grants access
+                    (Some(_), Some(_)) if entitled_elsewhere => {
+                        discrepancy.note = Some("email already has an entitled subscription".into())
+                    }
+                    (Some(email), Some(plan)) if repair => {
+                        let user_id = stripe.metadata.get("user_id").and_then(|id| uuid::Uuid::parse_str(id).ok());
+                        state
+                            .subscriptions
+                            .activate_subscription(
+                                user_id,
+                                email,
+                                Some(stripe.customer.clone()),
+                                Some(stripe.id.clone()),
+                                &plan,
+                                stamp.clone(),
+                            )
+                            .await;
+                        state.subscriptions.apply_subscription_event(stripe, stamp.clone()).await;
+                        discrepancy.repaired = true;
+                    }
+                    _ => {}
+                }
+            }
+            None => continue,
+        }
+        discrepancies.push(discrepancy);
+    }
+
+    let known: HashSet<&str> = subscriptions.iter().map(|s| s.id.as_str()).collect();
+    for local in &locals {
+        let Some(sub_id) = local.stripe_subscription_id.as_deref() else {
+            continue;
+        };
+        if known.contains(sub_id) || local.status == SubscriptionStatus::Canceled {
+            continue;
+        }
+        discrepancies.push(Discrepancy {
+            kind: DiscrepancyKind::MissingInStripe,
+            subscription_id: sub_id.to_string(),
+            customer_id: local.stripe_customer_id.clone(),
+            email: Some(local.email.clone()),
+            fields: vec![FieldDiff {
+                field: "status",
+                local: Some(format!("{:?}", local.status)),
+                stripe: None,
+            }],
+            repaired: repair && state.subscriptions.revoke_subscription(&local.email, stamp.clone()).await,
+            note: None,
+        });
+    }
+
+    for d in &discrepancies {
+        metrics().inc_counter("reconcile_discrepancies_total", &[("kind", d.kind.name())]);
+        println!(
+            "[RECONCILE] {} {} {} ({:?}){}",
+            if d.repaired { "🔧" } else { "⚠️" },
+            d.kind.name(),
+            d.subscription_id,
+            d.email,
+            d.note.as_ref().map(|n| format!(": {}", n)).unwrap_or_default()
+        );
+        if let (true, Some(email)) = (d.repaired, &d.email) {
+            log_payment_event(email, &format!("reconcile.{}", d.kind.name()), None, None);
+        }
+    }
+
+    let report = ReconciliationReport {
+        started_at,
+        finished_at: state.clock.now(),
+        repair,
+        customers_scanned: customers.len(),
+        subscriptions_scanned: subscriptions.len(),
+        discrepancies,
+    };
+    println!(
+        "[RECONCILE] ✅ {} customers, {} subscriptions: {} discrepancies, {} repaired",
+        report.customers_scanned,
+        report.subscriptions_scanned,
+        report.discrepancies.len(),
+        report.repaired()
+    );
+    Ok(report)
+}
+
+/// Scheduled run (`Job::Reconcile`)
+pub async fn run_scheduled(state: &StripeWebhookState) {
+    if !state.config.reconcile.enabled {
+        return;
+    }
+    if let Err(e) = reconcile(state, state.config.reconcile.repair).await {
+        println!("[RECONCILE] ❌ Run failed: {}", e);
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// RECONCILIATION (ADMIN)
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Deserialize, Default)]
+pub struct ReconcileRequest {
+    /// Report only unless set
+    #[serde(default)]
+    pub repair: bool,
+}
+
+/// Run a reconciliation now and return the report
+pub async fn run_reconciliation(
+    State(state): State<Arc<StripeWebhookState>>,
+    headers: HeaderMap,
+    body: Option<Json<ReconcileRequest>>,
+) -> impl IntoResponse {
+    if let Err(e) = state.auth.require_admin(&headers) {
+        return e.into_response();
+    }
+
+    let repair = body.map(|Json(b)| b.repair).unwrap_or(false);
+    match reconcile(&state, repair).await {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => (StatusCode::BAD_GATEWAY, e).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use crate::stripe_api::StripeApiClient;
+    use axum::{extract::Query, routing::get, Router};
+
+    fn subscription(id: &str, customer: &str, status: &str, period_end: i64) -> serde_json::Value {
+        serde_json::json!({
+            "id": id, "object": "subscription", "customer": customer, "status": status,
+            "current_period_end": period_end, "metadata": { "plan": "pro_monthly" }
+        })
+    }
+
+    /// Mock list endpoint serving `objects` one per page
+    fn paged(objects: Vec<serde_json::Value>) -> impl Fn(Query<HashMap<String, String>>) -> std::future::Ready<Json<serde_json::Value>> + Clone {
+        move |Query(q): Query<HashMap<String, String>>| {
+            let start = match q.get("starting_after") {
+                Some(after) => objects.iter().position(|o| o["id"] == after.as_str()).unwrap() + 1,
+                None => 0,
+            };
+            std::future::ready(Json(serde_json::json!({
+                "object": "list",
+                "data": objects.get(start..start + 1).unwrap_or_default(),
+                "has_more": start + 1 < objects.len(),
+            })))
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_then_repairs_drift_against_stripe() {
+        let mock = Router::new()
+            .route(
+                "/v1/customers",
+                get(paged(vec![
+                    serde_json::json!({ "id": "cus_ada", "email": "ada@example.com" }),
+                    serde_json::json!({ "id": "cus_bob", "email": "bob@example.com" }),
+                    serde_json::json!({ "id": "cus_eve", "email": "eve@example.com" }),
+                ])),
+            )
+            .route(
+                "/v1/subscriptions",
+                get(paged(vec![
+                    // Canceled while we were down
+                    subscription("sub_ada", "cus_ada", "canceled", 2_000_000),
+                    // Checkout webhook never arrived
+                    subscription("sub_bob", "cus_bob", "active", 2_000_000),
+                    // In sync
+                    subscription("sub_eve", "cus_eve", "active", 2_000_000),
+                ])),
+            );
+        let base = crate::test_support::spawn_mock(mock).await;
+
+        let mut state = StripeWebhookState::new(crate::test_support::authenticator());
+        state.config.redis_url = None;
+        state.clock = FixedClock::at(DateTime::from_timestamp(1_500_000, 0).unwrap());
+        state.config.reconcile.page_size = 1;
+        state.api = StripeApiClient::new("sk_test_mock".into(), base.clone(), base);
+
+        for (email, customer, sub) in [
+            ("ada@example.com", "cus_ada", "sub_ada"),
+            ("eve@example.com", "cus_eve", "sub_eve"),
+            ("zed@example.com", "cus_zed", "sub_zed"),
+        ] {
+            state
+                .subscriptions
+                .activate_subscription(None, email, Some(customer.into()), Some(sub.into()), "pro_monthly", EventStamp { id: "evt_checkout".into(), created: 1 })
+                .await;
+        }
+        let eve: Subscription = serde_json::from_value(subscription("sub_eve", "cus_eve", "active", 2_000_000)).unwrap();
+        state.subscriptions.apply_subscription_event(&eve, EventStamp { id: "evt_eve".into(), created: 2 }).await;
+        let ada: Subscription = serde_json::from_value(subscription("sub_ada", "cus_ada", "active", 2_000_000)).unwrap();
+        state.subscriptions.apply_subscription_event(&ada, EventStamp { id: "evt_ada".into(), created: 2 }).await;
+
+        let report = reconcile(&state, false).await.unwrap();
+        assert_eq!((report.customers_scanned, report.subscriptions_scanned), (3, 3));
+        let mut found: Vec<_> = report.discrepancies.iter().map(|d| (d.subscription_id.as_str(), d.kind)).collect();
+        found.sort_by_key(|(id, _)| *id);
+        assert_eq!(
+            found,
+            [
+                ("sub_ada", DiscrepancyKind::Drifted),
+                ("sub_bob", DiscrepancyKind::MissingLocally),
+                ("sub_zed", DiscrepancyKind::MissingInStripe),
+            ]
+        );
+        assert_eq!(report.repaired(), 0);
+        assert!(state.subscriptions.get_by_email("bob@example.com").await.is_none());
+
+        let report = reconcile(&state, true).await.unwrap();
+        assert_eq!(report.repaired(), 3);
+        let status = |email: &'static str| {
+            let subs = state.subscriptions.clone();
+            async move { subs.get_by_email(email).await.unwrap().status }
+        };
+        assert_eq!(status("ada@example.com").await, SubscriptionStatus::Canceled);
+        assert_eq!(status("bob@example.com").await, SubscriptionStatus::Active);
+        assert_eq!(status("zed@example.com").await, SubscriptionStatus::Canceled);
+        assert_eq!(status("eve@example.com").await, SubscriptionStatus::Active);
+
+        assert!(reconcile(&state, false).await.unwrap().discrepancies.is_empty());
+    }
+
+    #[tokio::test]
+    async fn checkout_completing_mid_run_is_not_revoked() {
+        let mut state = StripeWebhookState::new(crate::test_support::authenticator());
+        state.config.redis_url = None;
+        let subscriptions = state.subscriptions.clone();
+        // The checkout lands after the subscription pages were fetched, before the diff
+        let mock = Router::new()
+            .route("/v1/customers", get(paged(vec![])))
+            .route(
+                "/v1/subscriptions",
+                get(move || {
+                    let subscriptions = subscriptions.clone();
+                    async move {
+                        subscriptions
+                            .activate_subscription(None, "new@example.com", Some("cus_new".into()), Some("sub_new".into()), "pro_monthly", EventStamp { id: "evt_checkout".into(), created: 1 })
+                            .await;
+                        Json(serde_json::json!({ "object": "list", "data": [], "has_more": false }))
+                    }
+                }),
+            );
+        let base = crate::test_support::spawn_mock(mock).await;
+        state.api = StripeApiClient::new("sk_test_mock".into(), base.clone(), base);
+
+        let report = reconcile(&state, true).await.unwrap();
+        assert!(report.discrepancies.is_empty());
+        let sub = state.subscriptions.get_by_email("new@example.com").await.unwrap();
+        assert_eq!(sub.status, SubscriptionStatus::Active);
+    }
+}
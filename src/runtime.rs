@@ -0,0 +1,49 @@
+// lwas_economy/src/payments/runtime.rs
+// ARCHITECT: QANTUM AETERNA | STATUS: BETA
+// Shared plumbing: numeric env settings, retry backoff, replica identity
+
+use rand::Rng;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Numeric setting from the environment; unset or unparsable falls back to `default`
+pub fn env_u64(name: &str, default: u64) -> u64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Full-jitter exponential backoff: uniform in [0, min(max, base * 2^exponent)]. The jitter
+/// keeps replicas that all failed during one outage from retrying in lockstep.
+pub fn jittered_backoff(base: Duration, max: Duration, exponent: u32) -> Duration {
+    let ceiling = base.saturating_mul(2u32.saturating_pow(exponent)).min(max);
+    let millis = ceiling.as_millis() as u64;
+    Duration::from_millis(rand::thread_rng().gen_range(0..=millis))
+}
+
+/// This replica's identity in locks and leases: the platform's instance id, else the
+/// hostname, else a random id fixed for the life of the process
+pub fn instance_id() -> &'static str {
+    static INSTANCE_ID: OnceLock<String> = OnceLock::new();
+    INSTANCE_ID.get_or_init(|| {
+        std::env::var("RENDER_INSTANCE_ID")
+            .or_else(|_| std::env::var("HOSTNAME"))
+            .unwrap_or_else(|_| uuid::Uuid::new_v4().to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_stays_under_the_capped_ceiling() {
+        let (base, max) = (Duration::from_millis(100), Duration::from_millis(1_000));
+        for (exponent, ceiling_ms) in [(0, 100), (2, 400), (4, 1_000), (u32::MAX, 1_000)] {
+            let samples: Vec<Duration> = (0..200).map(|_| jittered_backoff(base, max, exponent)).collect();
+            assert!(samples.iter().all(|d| *d <= Duration::from_millis(ceiling_ms)), "exponent {}", exponent);
+        }
+        assert_eq!(instance_id(), instance_id());
+    }
+}
@@ -0,0 +1,407 @@
+// lwas_economy/src/payments/scheduler.rs
+// ARCHITECT: QANTUM AETERNA | STATUS: BETA
+// In-process job scheduler: persisted due times & per-job leader election (Redis)
+
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+use crate::dunning;
+use crate::metrics::{metrics, redis_fallback};
+use crate::paypal_handler;
+use crate::reconciliation;
+use crate::runtime::{env_u64, instance_id};
+use crate::stripe_disputes;
+use crate::stripe_handler::StripeWebhookState;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// SCHEDULER CONFIGURATION
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn env_secs(name: &str, default: u64) -> Duration {
+    Duration::from_secs(env_u64(name, default))
+}
+
+#[derive(Clone, Debug)]
+pub struct SchedulerConfig {
+    /// How often due jobs are looked for
+    pub tick: Duration,
+    /// How long a replica holds a job's lock; must exceed the longest job run
+    pub lease: Duration,
+    /// Time after `current_period_end` / `trial_end` before we stop waiting for Stripe's event
+    pub lapse_grace: Duration,
+    pub expiry_interval: Duration,
+    pub cleanup_interval: Duration,
+    /// Replica identity written into job locks
+    pub instance_id: String,
+}
+
+impl SchedulerConfig {
+    pub fn from_env() -> Self {
+        Self {
+            tick: env_secs("SCHEDULER_TICK_SECS", 30),
+            lease: env_secs("SCHEDULER_LEASE_SECS", 300),
+            lapse_grace: env_secs("SUBSCRIPTION_LAPSE_GRACE_SECS", 48 * 3600),
+            expiry_interval: env_secs("SCHEDULER_EXPIRY_INTERVAL_SECS", 600),
+            cleanup_interval: env_secs("SCHEDULER_CLEANUP_INTERVAL_SECS", 3600),
+            instance_id: instance_id().to_string(),
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// JOBS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Job {
+    /// Cancel subscriptions whose paid period ended without a renewal event
+    ExpireLapsed,
+    /// End trials whose `trial_end` passed without a conversion event
+    ExpireTrials,
+    /// Dunning reminders and end of grace periods
+    Dunning,
+    /// Stripe evidence and PayPal seller response deadlines
+    DisputeDeadlines,
+    /// Diff local subscriptions against Stripe (when `RECONCILE_ENABLED`)
+    Reconcile,
+    /// Time-based alert rules (no webhooks received)
+    Alerts,
+    /// Prune in-memory bookkeeping that has outlived its purpose
+    Cleanup,
+}
+
+impl Job {
+    pub const ALL: [Job; 7] = [
+        Job::ExpireLapsed,
+        Job::ExpireTrials,
+        Job::Dunning,
+        Job::DisputeDeadlines,
+        Job::Reconcile,
+        Job::Alerts,
+        Job::Cleanup,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Job::ExpireLapsed => "expire_lapsed",
+            Job::ExpireTrials => "expire_trials",
+            Job::Dunning => "dunning",
+            Job::DisputeDeadlines => "dispute_deadlines",
+            Job::Reconcile => "reconcile",
+            Job::Alerts => "alerts",
+            Job::Cleanup => "cleanup",
+        }
+    }
+
+    fn interval(&self, state: &StripeWebhookState, config: &SchedulerConfig) -> Duration {
+        match self {
+            Job::ExpireLapsed | Job::ExpireTrials => config.expiry_interval,
+            Job::Dunning => state.config.dunning.check_interval,
+            Job::DisputeDeadlines => state.config.dispute_alert_interval,
+            Job::Reconcile => state.config.reconcile.interval,
+            Job::Alerts => state.config.alerts.check_interval,
+            Job::Cleanup => config.cleanup_interval,
+        }
+    }
+
+    async fn run(&self, state: &StripeWebhookState, config: &SchedulerConfig) {
+        let now = state.clock.now();
+        let grace = chrono::Duration::from_std(config.lapse_grace).unwrap_or(chrono::Duration::zero());
+
+        match self {
+            Job::ExpireLapsed => {
+                for email in state.subscriptions.expire_lapsed(now - grace).await {
+                    println!("[SCHEDULER] ⌛ Subscription for {} lapsed without renewal", email);
+                }
+            }
+            Job::ExpireTrials => {
+                for email in state.subscriptions.expire_trials(now - grace).await {
+                    println!("[SCHEDULER] ⌛ Trial for {} ended without conversion", email);
+                }
+            }
+            Job::Dunning => {
+                dunning::process_due(state).await;
+            }
+            Job::DisputeDeadlines => {
+                stripe_disputes::alert_due_deadlines(state).await;
+                paypal_handler::alert_due_deadlines(state).await;
+            }
+            Job::Reconcile => {
+                reconciliation::run_scheduled(state).await;
+            }
+            Job::Alerts => {
+                state.alerter.check_silence(now).await;
+            }
+            Job::Cleanup => {
+                let events = state.idempotency.prune_fallback(now - chrono::Duration::hours(24)).await;
+                let cases = state.dunning.prune_closed(now - chrono::Duration::days(30)).await;
+                let pending = state.subscriptions.prune_pending(now - chrono::Duration::days(7)).await;
+                let retention = chrono::Duration::from_std(state.archive.retention()).unwrap_or(chrono::Duration::days(30));
+                let archived = state.archive.prune_fallback(now - retention).await;
+                println!(
+                    "[SCHEDULER] 🧹 Pruned {} processed events, {} dunning cases, {} pending snapshots, {} archived deliveries",
+                    events, cases, pending, archived
+                );
+            }
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// JOB STORE (due times & locks)
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Release a lock only if this replica still holds it
+pub const UNLOCK_SCRIPT: &str = r#"
+if redis.call('get', KEYS[1]) == ARGV[1] then
+    return redis.call('del', KEYS[1])
+end
+return 0
+"#;
+
+#[derive(Clone)]
+pub struct JobStore {
+    redis_client: Option<redis::Client>,
+    next_runs_fallback: Arc<RwLock<HashMap<&'static str, DateTime<Utc>>>>,
+}
+
+impl JobStore {
+    pub fn new(redis_url: Option<String>) -> Self {
+        let redis_client = redis_url.and_then(|url| {
+            redis::Client::open(url).map_err(|e| println!("❌ Redis connect error: {}", e)).ok()
+        });
+
+        Self {
+            redis_client,
+            next_runs_fallback: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// When `job` is next due; `None` means never run (due now)
+    pub async fn next_run(&self, job: Job) -> Result<Option<DateTime<Utc>>, String> {
+        if let Some(client) = &self.redis_client {
+            let mut con = client
+                .get_multiplexed_async_connection()
+                .await
+                .map_err(|e| format!("Redis error: {}", e))?;
+            let ts: Option<i64> = redis::cmd("HGET")
+                .arg("scheduler:next_run")
+                .arg(job.name())
+                .query_async(&mut con)
+                .await
+                .map_err(|e| format!("Redis error: {}", e))?;
+            return Ok(ts.and_then(|ts| DateTime::from_timestamp(ts, 0)));
+        }
+
+        // Read every tick: only runs recorded in `set_next_run` count towards `redis_fallback_total`
+        Ok(self.next_runs_fallback.read().await.get(job.name()).copied())
+    }
+
+    pub async fn set_next_run(&self, job: Job, at: DateTime<Utc>) -> Result<(), String> {
+        if let Some(client) = &self.redis_client {
+            let mut con = client
+                .get_multiplexed_async_connection()
+                .await
+                .map_err(|e| format!("Redis error: {}", e))?;
+            return redis::cmd("HSET")
+                .arg("scheduler:next_run")
+                .arg(job.name())
+                .arg(at.timestamp())
+                .query_async(&mut con)
+                .await
+                .map_err(|e| format!("Redis error: {}", e));
+        }
+
+        redis_fallback("scheduler", false);
+        self.next_runs_fallback.write().await.insert(job.name(), at);
+        Ok(())
+    }
+
+    /// Leader election per job run: `SET NX PX` on a per-job key. Without Redis this
+    /// process is the only replica and always leads.
+    pub async fn try_lock(&self, job: Job, instance_id: &str, lease: Duration) -> Result<bool, String> {
+        let Some(client) = &self.redis_client else {
+            return Ok(true);
+        };
+
+        let mut con = client
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(|e| format!("Redis error: {}", e))?;
+        let acquired: Option<String> = redis::cmd("SET")
+            .arg(format!("scheduler:lock:{}", job.name()))
+            .arg(instance_id)
+            .arg("NX")
+            .arg("PX")
+            .arg(lease.as_millis() as u64)
+            .query_async(&mut con)
+            .await
+            .map_err(|e| format!("Redis error: {}", e))?;
+        Ok(acquired.is_some())
+    }
+
+    pub async fn unlock(&self, job: Job, instance_id: &str) -> Result<(), String> {
+        let Some(client) = &self.redis_client else {
+            return Ok(());
+        };
+
+        let mut con = client
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(|e| format!("Redis error: {}", e))?;
+        redis::Script::new(UNLOCK_SCRIPT)
+            .key(format!("scheduler:lock:{}", job.name()))
+            .arg(instance_id)
+            .invoke_async::<_, i64>(&mut con)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Redis error: {}", e))
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// SCHEDULER
+// ═══════════════════════════════════════════════════════════════════════════════
+
+pub struct Scheduler {
+    pub config: SchedulerConfig,
+    pub jobs: JobStore,
+    pub state: Arc<StripeWebhookState>,
+}
+
+impl Scheduler {
+    pub fn new(state: Arc<StripeWebhookState>) -> Self {
+        Self {
+            config: SchedulerConfig::from_env(),
+            jobs: JobStore::new(state.config.redis_url.clone()),
+            state,
+        }
+    }
+
+    /// Run every job that is due and whose lock this replica wins; returns the jobs run.
+    /// Due times persist across restarts, so work missed while down runs on the first tick.
+    pub async fn run_due(&self) -> Vec<Job> {
+        let mut ran = Vec::new();
+
+        for job in Job::ALL {
+            let now = self.state.clock.now();
+            match self.jobs.next_run(job).await {
+                Ok(Some(at)) if at > now => continue,
+                Ok(_) => {}
+                Err(e) => {
+                    // Without the shared store we can't tell who should run; skip rather than double-run
+                    println!("[SCHEDULER] ❌ {} skipped: {}", job.name(), e);
+                    continue;
+                }
+            }
+
+            match self.jobs.try_lock(job, &self.config.instance_id, self.config.lease).await {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    println!("[SCHEDULER] ❌ {} skipped: {}", job.name(), e);
+                    continue;
+                }
+            }
+
+            // Another replica may have run it between our check and our lock
+            if matches!(self.jobs.next_run(job).await, Ok(Some(at)) if at > now) {
+                let _ = self.jobs.unlock(job, &self.config.instance_id).await;
+                continue;
+            }
+
+            let started = std::time::Instant::now();
+            job.run(&self.state, &self.config).await;
+            metrics().observe(
+                "scheduler_job_duration_seconds",
+                &[("job", job.name())],
+                started.elapsed().as_secs_f64(),
+            );
+            metrics().inc_counter("scheduler_job_runs_total", &[("job", job.name())]);
+
+            let interval = chrono::Duration::from_std(job.interval(&self.state, &self.config))
+                .unwrap_or(chrono::Duration::hours(1));
+            if let Err(e) = self.jobs.set_next_run(job, now + interval).await {
+                println!("[SCHEDULER] ❌ Could not persist next run of {}: {}", job.name(), e);
+            }
+            if let Err(e) = self.jobs.unlock(job, &self.config.instance_id).await {
+                println!("[SCHEDULER] ⚠️ Could not release {} (lease expires): {}", job.name(), e);
+            }
+            ran.push(job);
+        }
+
+        ran
+    }
+
+    pub async fn run(self) {
+        println!(
+            "[SCHEDULER] ⏱️ Started as {} (tick {:?})",
+            self.config.instance_id, self.config.tick
+        );
+        let mut interval = tokio::time::interval(self.config.tick);
+        loop {
+            interval.tick().await;
+            self.run_due().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use crate::stripe_handler::{EventStamp, SubscriptionStatus};
+    use crate::stripe_models::Subscription;
+
+    fn at(ts: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(ts, 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn runs_due_jobs_once_per_interval_and_expires_lapsed() {
+        let clock = FixedClock::at(at(1_000_000));
+        let mut state = StripeWebhookState::new(crate::test_support::authenticator());
+        state.config.redis_url = None;
+        state.clock = clock.clone();
+        state
+            .subscriptions
+            .activate_subscription(
+                None,
+                "ada@example.com",
+                Some("cus_1".into()),
+                Some("sub_1".into()),
+                "pro_monthly",
+                EventStamp { id: "evt_checkout".into(), created: 1 },
+            )
+            .await;
+        let snapshot: Subscription = serde_json::from_value(serde_json::json!({
+            "id": "sub_1", "customer": "cus_1", "status": "active", "current_period_end": 900_000
+        }))
+        .unwrap();
+        state
+            .subscriptions
+            .apply_subscription_event(&snapshot, EventStamp { id: "evt_1".into(), created: 2 })
+            .await;
+
+        let mut scheduler = Scheduler::new(Arc::new(state));
+        scheduler.config.lapse_grace = Duration::from_secs(48 * 3600);
+        scheduler.config.expiry_interval = Duration::from_secs(600);
+
+        // First tick: everything is due. Period ended 100_000s ago (< 48h grace): not lapsed yet
+        assert_eq!(scheduler.run_due().await.len(), Job::ALL.len());
+        let status = |s: &Scheduler| {
+            let subs = s.state.subscriptions.clone();
+            async move { subs.get_by_email("ada@example.com").await.unwrap().status }
+        };
+        assert_eq!(status(&scheduler).await, SubscriptionStatus::Active);
+        assert!(scheduler.run_due().await.is_empty());
+
+        clock.set(at(900_000 + 48 * 3600 + 1));
+        let ran = scheduler.run_due().await;
+        assert!(ran.contains(&Job::ExpireLapsed));
+        assert_eq!(status(&scheduler).await, SubscriptionStatus::Canceled);
+    }
+}
@@ -0,0 +1,148 @@
+// lwas_economy/src/payments/stripe_api.rs
+// ARCHITECT: QANTUM AETERNA | STATUS: BETA
+// Minimal Stripe REST client (form-encoded requests over the resilient outbound layer)
+
+use crate::http_client::{OutboundClient, OutboundConfig};
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// STRIPE API CLIENT
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone)]
+pub struct StripeApiClient {
+    http: OutboundClient,
+    secret_key: String,
+    api_base: String,
+    files_base: String,
+}
+
+impl StripeApiClient {
+    pub fn new(secret_key: String, api_base: String, files_base: String) -> Self {
+        Self {
+            http: OutboundClient::new("stripe", OutboundConfig::from_env()),
+            secret_key,
+            api_base: api_base.trim_end_matches('/').to_string(),
+            files_base: files_base.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// POST form-encoded params. Every call carries an `Idempotency-Key`, kept
+    /// stable across retries; pass one in to make the call idempotent end-to-end.
+    pub async fn post_form(
+        &self,
+        endpoint: &str,
+        path: &str,
+        params: &[(String, String)],
+        idempotency_key: Option<&str>,
+    ) -> Result<serde_json::Value, String> {
+        let url = format!("{}{}", self.api_base, path);
+        let key = idempotency_key
+            .map(|k| k.to_string())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+        let resp = self
+            .http
+            .send(endpoint, |c| {
+                c.post(&url)
+                    .bearer_auth(&self.secret_key)
+                    .header("Idempotency-Key", &key)
+                    .form(params)
+            })
+            .await?;
+
+        Self::parse_response(resp).await
+    }
+
+    /// Upload a file to `files.stripe.com` (multipart), e.g. as dispute evidence
+    pub async fn upload_file(&self, purpose: &str, file_name: &str, bytes: &[u8]) -> Result<serde_json::Value, String> {
+        let url = format!("{}/v1/files", self.files_base);
+        let key = uuid::Uuid::new_v4().to_string();
+
+        // Multipart bodies are consumed on send, so each retry rebuilds the form
+        let resp = self
+            .http
+            .send("POST /v1/files", |c| {
+                let form = reqwest::multipart::Form::new()
+                    .text("purpose", purpose.to_string())
+                    .part(
+                        "file",
+                        reqwest::multipart::Part::bytes(bytes.to_vec()).file_name(file_name.to_string()),
+                    );
+                c.post(&url)
+                    .bearer_auth(&self.secret_key)
+                    .header("Idempotency-Key", &key)
+                    .multipart(form)
+            })
+            .await?;
+
+        Self::parse_response(resp).await
+    }
+
+    /// GET a resource; `Ok(None)` when Stripe answers 404
+    pub async fn get(&self, endpoint: &str, path: &str) -> Result<Option<serde_json::Value>, String> {
+        let url = format!("{}{}", self.api_base, path);
+
+        let resp = self
+            .http
+            .send(endpoint, |c| c.get(&url).bearer_auth(&self.secret_key))
+            .await?;
+
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Self::parse_response(resp).await.map(Some)
+    }
+
+    /// Every object of a list endpoint (e.g. `/v1/subscriptions`), following `has_more`
+    /// with `starting_after` until the last page
+    pub async fn list_all(
+        &self,
+        endpoint: &str,
+        path: &str,
+        query: &[(&str, &str)],
+        page_size: u32,
+    ) -> Result<Vec<serde_json::Value>, String> {
+        let url = format!("{}{}", self.api_base, path);
+        let limit = page_size.clamp(1, 100).to_string();
+        let mut objects = Vec::new();
+        let mut starting_after: Option<String> = None;
+
+        loop {
+            let mut params: Vec<(&str, &str)> = query.to_vec();
+            params.push(("limit", &limit));
+            if let Some(cursor) = &starting_after {
+                params.push(("starting_after", cursor));
+            }
+
+            let resp = self
+                .http
+                .send(endpoint, |c| c.get(&url).query(&params).bearer_auth(&self.secret_key))
+                .await?;
+            let page = Self::parse_response(resp).await?;
+
+            let data = page["data"].as_array().cloned().unwrap_or_default();
+            let cursor = data.last().and_then(|o| o["id"].as_str()).map(|id| id.to_string());
+            objects.extend(data);
+
+            match (page["has_more"].as_bool().unwrap_or(false), cursor) {
+                (true, Some(cursor)) => starting_after = Some(cursor),
+                _ => return Ok(objects),
+            }
+        }
+    }
+
+    async fn parse_response(resp: reqwest::Response) -> Result<serde_json::Value, String> {
+        let status = resp.status();
+        let body: serde_json::Value = resp
+            .json()
+            .await
+            .map_err(|e| format!("Stripe JSON error: {}", e))?;
+
+        if !status.is_success() {
+            let message = body["error"]["message"].as_str().unwrap_or("unknown error");
+            return Err(format!("Stripe API error {}: {}", status, message));
+        }
+
+        Ok(body)
+    }
+}
@@ -0,0 +1,683 @@
+// lwas_economy/src/payments/stripe_disputes.rs
+// ARCHITECT: QANTUM AETERNA | STATUS: BETA
+// Stripe chargebacks: dispute tracking, entitlement policy, evidence submission & deadline alerts
+
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::event_archive::Provider;
+use crate::events::{self, DomainEvent, DomainEventType};
+use crate::metrics::metrics;
+use crate::currency::format_amount;
+use crate::notifications::Notification;
+use crate::stripe_handler::{charge_subscription, log_payment_event, EventStamp, StripeWebhookState};
+use crate::stripe_models::{Dispute, EarlyFraudWarning};
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DISPUTE STORE
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StripeDisputeRecord {
+    pub dispute_id: String,
+    pub charge_id: String,
+    pub payment_intent_id: Option<String>,
+    pub amount: i64,
+    pub currency: String,
+    pub reason: String,
+    pub status: String,
+    pub customer_email: Option<String>,
+    pub evidence_due_by: Option<DateTime<Utc>>,
+    pub evidence_submitted_at: Option<DateTime<Utc>>,
+    pub entitlement_suspended: bool,
+    /// Smallest `dispute_alert_hours` threshold already alerted for
+    pub alerted_within_hours: Option<u64>,
+    pub last_event: Option<EventStamp>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl StripeDisputeRecord {
+    /// Inquiries (`warning_*`) precede a formal chargeback
+    fn stage(&self) -> &'static str {
+        if self.status.starts_with("warning_") {
+            "INQUIRY"
+        } else {
+            "CHARGEBACK"
+        }
+    }
+
+    fn awaiting_evidence(&self) -> bool {
+        matches!(self.status.as_str(), "needs_response" | "warning_needs_response")
+    }
+}
+
+#[derive(Clone)]
+pub struct StripeDisputeStore {
+    // Same in-memory model as SubscriptionManager; swap for DB alongside it.
+    disputes: Arc<RwLock<HashMap<String, StripeDisputeRecord>>>,
+}
+
+impl StripeDisputeStore {
+    pub fn new() -> Self {
+        Self {
+            disputes: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub async fn get(&self, dispute_id: &str) -> Option<StripeDisputeRecord> {
+        let store = self.disputes.read().await;
+        store.get(dispute_id).cloned()
+    }
+
+    pub async fn upsert(&self, dispute: StripeDisputeRecord) {
+        let mut store = self.disputes.write().await;
+        store.insert(dispute.dispute_id.clone(), dispute);
+    }
+
+    /// All disputes, most urgent evidence deadline first
+    pub async fn list(&self, status: Option<&str>) -> Vec<StripeDisputeRecord> {
+        let store = self.disputes.read().await;
+        let mut disputes: Vec<StripeDisputeRecord> = store
+            .values()
+            .filter(|d| status.is_none_or(|s| d.status == s))
+            .cloned()
+            .collect();
+        disputes.sort_by_key(|d| d.evidence_due_by.unwrap_or(DateTime::<Utc>::MAX_UTC));
+        disputes
+    }
+
+    /// Disputes awaiting evidence whose deadline has come within a threshold not yet alerted
+    /// for. Each is returned once per threshold, with the threshold crossed.
+    pub async fn take_deadline_alerts(
+        &self,
+        now: DateTime<Utc>,
+        thresholds_hours: &[u64],
+    ) -> Vec<(StripeDisputeRecord, u64)> {
+        let mut store = self.disputes.write().await;
+        let mut due = Vec::new();
+
+        for dispute in store.values_mut() {
+            let Some(due_by) = dispute.evidence_due_by.filter(|_| dispute.awaiting_evidence()) else {
+                continue;
+            };
+            let remaining = due_by - now;
+            let crossed = thresholds_hours
+                .iter()
+                .copied()
+                .filter(|h| remaining <= chrono::Duration::hours(*h as i64))
+                .filter(|h| dispute.alerted_within_hours.is_none_or(|alerted| *h < alerted))
+                .min();
+
+            if let Some(hours) = crossed {
+                dispute.alerted_within_hours = Some(hours);
+                due.push((dispute.clone(), hours));
+            }
+        }
+
+        due
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DISPUTE EVENTS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// `charge.dispute.created` / `.updated` / `.closed`
+pub async fn handle_dispute_event(
+    state: &StripeWebhookState,
+    dispute: Dispute,
+    stamp: EventStamp,
+) -> Result<(), String> {
+    let existing = state.disputes.get(&dispute.id).await;
+
+    if let Some(last) = existing.as_ref().and_then(|d| d.last_event.as_ref()) {
+        if stamp.created < last.created {
+            println!("[DISPUTE] ⚡ Skipping stale event {} for {}", stamp.id, dispute.id);
+            return Ok(());
+        }
+    }
+
+    let customer_email = match existing.as_ref().and_then(|d| d.customer_email.clone()) {
+        Some(email) => Some(email),
+        None => charge_subscription(state, &dispute.charge).await?.map(|s| s.email),
+    };
+
+    let now = Utc::now();
+    let mut record = StripeDisputeRecord {
+        dispute_id: dispute.id.clone(),
+        charge_id: dispute.charge.clone(),
+        payment_intent_id: dispute.payment_intent.clone(),
+        amount: dispute.amount,
+        currency: dispute.currency.clone(),
+        reason: dispute.reason.clone(),
+        status: dispute.status.clone(),
+        customer_email: customer_email.clone(),
+        evidence_due_by: dispute
+            .evidence_details
+            .as_ref()
+            .and_then(|e| e.due_by)
+            .and_then(|ts| DateTime::from_timestamp(ts, 0)),
+        evidence_submitted_at: existing.as_ref().and_then(|d| d.evidence_submitted_at),
+        entitlement_suspended: existing.as_ref().is_some_and(|d| d.entitlement_suspended),
+        alerted_within_hours: existing.as_ref().and_then(|d| d.alerted_within_hours),
+        last_event: Some(stamp.clone()),
+        created_at: existing.as_ref().map_or(now, |d| d.created_at),
+        updated_at: now,
+    };
+
+    if existing.is_none() {
+        state.alerter.dispute_opened(
+            Provider::Stripe,
+            &record.dispute_id,
+            &format_amount(record.amount, &record.currency),
+            &record.reason,
+            customer_email.as_deref(),
+        );
+    }
+
+    println!(
+        "[DISPUTE] ⚖️ {} {} on {} ({}, reason: {}, due: {:?})",
+        record.status,
+        record.dispute_id,
+        record.charge_id,
+        format_amount(record.amount, &record.currency),
+        record.reason,
+        record.evidence_due_by
+    );
+
+    let Some(email) = customer_email else {
+        println!(
+            "[DISPUTE] ⚠️ No customer for {}, entitlements untouched",
+            record.dispute_id
+        );
+        state.disputes.upsert(record).await;
+        return Ok(());
+    };
+
+    log_payment_event(
+        &email,
+        &format!("dispute.{}", record.status),
+        Some(record.amount),
+        Some(&record.currency),
+    );
+
+    let source = stamp.id.clone();
+    let changed = match record.status.as_str() {
+        // Funds stay with the cardholder: the entitlement goes with them
+        "lost" => {
+            record.entitlement_suspended = false;
+            state
+                .subscriptions
+                .revoke_subscription(&email, stamp)
+                .await
+                .then_some(DomainEventType::SubscriptionCanceled)
+        }
+        "won" | "warning_closed" => {
+            let reinstated =
+                record.entitlement_suspended && state.subscriptions.reinstate_subscription(&email).await;
+            record.entitlement_suspended = false;
+            reinstated.then_some(DomainEventType::SubscriptionUpdated)
+        }
+        _ => {
+            let suspended = !record.entitlement_suspended
+                && state.config.dispute_policy.should_suspend(Some(record.stage()))
+                && state.subscriptions.suspend_subscription(&email).await;
+            record.entitlement_suspended |= suspended;
+            suspended.then_some(DomainEventType::SubscriptionUpdated)
+        }
+    };
+
+    state.events.publish(DomainEvent::new(
+        DomainEventType::DisputeUpdated,
+        Some(&source),
+        serde_json::json!({
+            "email": email,
+            "dispute_id": record.dispute_id,
+            "charge_id": record.charge_id,
+            "status": record.status,
+            "stage": record.stage(),
+            "reason": record.reason,
+            "amount": record.amount,
+            "currency": record.currency,
+            "evidence_due_by": record.evidence_due_by,
+        }),
+        state.clock.now(),
+    )).await;
+    if let Some(event_type) = changed {
+        events::publish_subscription(state, event_type, &email, Some(&source)).await;
+        if event_type == DomainEventType::SubscriptionCanceled {
+            if let Some(sub) = state.subscriptions.get_by_email(&email).await {
+                state.notifier.send(Notification::subscription_canceled(&sub)).await;
+            }
+        }
+    }
+
+    state.disputes.upsert(record).await;
+    Ok(())
+}
+
+/// `radar.early_fraud_warning.created`: no money has moved yet, so entitlements are left alone
+/// and ops are alerted to refund actionable charges before they become disputes.
+pub async fn handle_early_fraud_warning(
+    state: &StripeWebhookState,
+    warning: EarlyFraudWarning,
+) -> Result<(), String> {
+    let email = charge_subscription(state, &warning.charge).await?.map(|s| s.email);
+
+    metrics().inc_counter(
+        "stripe_early_fraud_warnings_total",
+        &[("fraud_type", warning.fraud_type.as_str())],
+    );
+    println!(
+        "[DISPUTE] 🚨 Early fraud warning {} on {} ({}, customer: {}){}",
+        warning.id,
+        warning.charge,
+        warning.fraud_type,
+        email.as_deref().unwrap_or("-"),
+        if warning.actionable {
+            " - refund now to avoid a dispute"
+        } else {
+            ""
+        }
+    );
+
+    log_payment_event(
+        email.as_deref().unwrap_or("-"),
+        "radar.early_fraud_warning",
+        None,
+        None,
+    );
+
+    Ok(())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DEADLINE ALERTS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Alert on every dispute whose evidence deadline crossed a threshold; returns how many
+pub async fn alert_due_deadlines(state: &StripeWebhookState) -> usize {
+    let due = state
+        .disputes
+        .take_deadline_alerts(state.clock.now(), &state.config.dispute_alert_hours)
+        .await;
+
+    for (dispute, hours) in &due {
+        metrics().inc_counter(
+            "stripe_dispute_deadline_alerts_total",
+            &[("within_hours", hours.to_string().as_str())],
+        );
+        if let Some(due_by) = dispute.evidence_due_by {
+            state.alerter.dispute_deadline(
+                Provider::Stripe,
+                &dispute.dispute_id,
+                &format_amount(dispute.amount, &dispute.currency),
+                dispute.customer_email.as_deref(),
+                due_by,
+                *hours,
+            );
+        }
+    }
+
+    due.len()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DISPUTE ADMIN API
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Evidence fields Stripe accepts as file uploads
+const EVIDENCE_FILE_FIELDS: &[&str] = &[
+    "cancellation_policy",
+    "customer_communication",
+    "customer_signature",
+    "duplicate_charge_documentation",
+    "receipt",
+    "refund_policy",
+    "service_documentation",
+    "shipping_documentation",
+    "uncategorized_file",
+];
+
+#[derive(Deserialize)]
+pub struct ListStripeDisputesQuery {
+    pub status: Option<String>,
+}
+
+/// List tracked disputes (admin)
+pub async fn list_stripe_disputes(
+    State(state): State<Arc<StripeWebhookState>>,
+    headers: HeaderMap,
+    Query(query): Query<ListStripeDisputesQuery>,
+) -> impl IntoResponse {
+    if let Err(e) = state.auth.require_admin(&headers) {
+        return e.into_response();
+    }
+
+    Json(state.disputes.list(query.status.as_deref()).await).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct EvidenceFile {
+    /// One of the Stripe evidence file fields, e.g. `receipt`
+    pub field: String,
+    pub file_name: String,
+    pub content_base64: String,
+}
+
+#[derive(Deserialize)]
+pub struct SubmitStripeEvidenceRequest {
+    /// Stripe evidence text fields, e.g. `product_description`, `uncategorized_text`
+    #[serde(default)]
+    pub text: HashMap<String, String>,
+    #[serde(default)]
+    pub files: Vec<EvidenceFile>,
+    /// Submit to the card network now; otherwise evidence is staged and can still be amended
+    #[serde(default)]
+    pub submit: bool,
+}
+
+#[derive(Serialize)]
+pub struct SubmitStripeEvidenceResponse {
+    pub dispute_id: String,
+    pub status: String,
+    pub submitted: bool,
+    pub file_ids: HashMap<String, String>,
+}
+
+fn is_field_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c == '_')
+}
+
+/// Attach evidence to a dispute (admin). Files go to `files.stripe.com` first.
+pub async fn submit_stripe_dispute_evidence(
+    State(state): State<Arc<StripeWebhookState>>,
+    headers: HeaderMap,
+    Path(dispute_id): Path<String>,
+    Json(payload): Json<SubmitStripeEvidenceRequest>,
+) -> impl IntoResponse {
+    if let Err(e) = state.auth.require_admin(&headers) {
+        return e.into_response();
+    }
+
+    if payload.text.keys().any(|k| !is_field_name(k) || EVIDENCE_FILE_FIELDS.contains(&k.as_str())) {
+        return (StatusCode::BAD_REQUEST, "Invalid evidence text field").into_response();
+    }
+
+    let mut files = Vec::with_capacity(payload.files.len());
+    for file in payload.files {
+        if !EVIDENCE_FILE_FIELDS.contains(&file.field.as_str()) {
+            return (StatusCode::BAD_REQUEST, "Invalid evidence file field").into_response();
+        }
+        match base64::engine::general_purpose::STANDARD.decode(&file.content_base64) {
+            Ok(bytes) => files.push((file.field, file.file_name, bytes)),
+            Err(_) => return (StatusCode::BAD_REQUEST, "Invalid file encoding").into_response(),
+        }
+    }
+
+    let mut file_ids = HashMap::new();
+    for (field, file_name, bytes) in &files {
+        let uploaded = state.api.upload_file("dispute_evidence", file_name, bytes).await;
+        match uploaded.as_ref().ok().and_then(|f| f["id"].as_str()) {
+            Some(id) => {
+                file_ids.insert(field.clone(), id.to_string());
+            }
+            None => {
+                println!("[DISPUTE] ❌ Evidence upload failed for {}: {:?}", dispute_id, uploaded.err());
+                return (StatusCode::BAD_GATEWAY, "Evidence file upload failed").into_response();
+            }
+        }
+    }
+
+    let mut params: Vec<(String, String)> = payload
+        .text
+        .iter()
+        .chain(file_ids.iter())
+        .map(|(field, value)| (format!("evidence[{}]", field), value.clone()))
+        .collect();
+    params.push(("submit".into(), payload.submit.to_string()));
+
+    let path = format!("/v1/disputes/{}", dispute_id);
+    let updated: Dispute = match state
+        .api
+        .post_form("POST /v1/disputes/:id", &path, &params, None)
+        .await
+        .and_then(|v| serde_json::from_value(v).map_err(|e| format!("Failed to parse dispute: {}", e)))
+    {
+        Ok(dispute) => dispute,
+        Err(e) => {
+            println!("[DISPUTE] ❌ Evidence submission failed for {}: {}", dispute_id, e);
+            return (StatusCode::BAD_GATEWAY, e).into_response();
+        }
+    };
+
+    if let Some(mut record) = state.disputes.get(&dispute_id).await {
+        record.status = updated.status.clone();
+        if payload.submit {
+            record.evidence_submitted_at = Some(Utc::now());
+        }
+        record.updated_at = Utc::now();
+        state.disputes.upsert(record).await;
+    }
+
+    println!(
+        "[DISPUTE] 📎 Evidence {} for {} ({} files, status: {})",
+        if payload.submit { "submitted" } else { "staged" },
+        dispute_id,
+        file_ids.len(),
+        updated.status
+    );
+
+    Json(SubmitStripeEvidenceResponse {
+        dispute_id,
+        status: updated.status,
+        submitted: payload.submit,
+        file_ids,
+    })
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerting::{AlertConfig, Alerter};
+    use crate::clock::FixedClock;
+    use crate::stripe_api::StripeApiClient;
+    use crate::stripe_handler::{SubscriptionManager, SubscriptionStatus};
+    use axum::{routing::get, routing::post, Router};
+
+    const DUE_BY: i64 = 1_700_000_000;
+
+    fn dispute(status: &str) -> Dispute {
+        serde_json::from_value(serde_json::json!({
+            "id": "dp_1", "charge": "ch_1", "amount": 900, "currency": "eur",
+            "reason": "fraudulent", "status": status,
+            "evidence_details": { "due_by": DUE_BY }
+        }))
+        .unwrap()
+    }
+
+    fn stamp(id: &str, created: i64) -> EventStamp {
+        EventStamp {
+            id: id.to_string(),
+            created,
+        }
+    }
+
+    async fn state_with_mock(mock: Router) -> StripeWebhookState {
+        let base = crate::test_support::spawn_mock(mock).await;
+        let mut state = StripeWebhookState::new(crate::test_support::authenticator());
+        state.api = StripeApiClient::new("sk_test_mock".into(), base.clone(), base);
+        state.config.dispute_policy = crate::paypal_handler::DisputePolicy::SuspendOnClaim;
+        state
+    }
+
+    fn charges_mock() -> Router {
+        Router::new().route(
+            "/v1/charges/ch_1",
+            get(|| async {
+                Json(serde_json::json!({
+                    "id": "ch_1", "customer": "cus_1", "receipt_email": "ada@example.com",
+                    "amount": 900, "amount_refunded": 0, "currency": "eur",
+                    "refunded": false, "status": "succeeded"
+                }))
+            }),
+        )
+    }
+
+    async fn subscribed(state: &mut StripeWebhookState) {
+        state.subscriptions = SubscriptionManager::new();
+        state
+            .subscriptions
+            .activate_subscription(
+                None,
+                "ada@example.com",
+                Some("cus_1".into()),
+                Some("sub_1".into()),
+                "pro_monthly",
+                stamp("evt_checkout", 100),
+            )
+            .await;
+    }
+
+    async fn status(state: &StripeWebhookState) -> SubscriptionStatus {
+        state.subscriptions.get_by_email("ada@example.com").await.unwrap().status
+    }
+
+    #[tokio::test]
+    async fn dispute_lifecycle_follows_policy() {
+        let mut state = state_with_mock(charges_mock()).await;
+
+        // An inquiry alone does not suspend under suspend_on_claim
+        subscribed(&mut state).await;
+        handle_dispute_event(&state, dispute("warning_needs_response"), stamp("evt_1", 200)).await.unwrap();
+        assert_eq!(status(&state).await, SubscriptionStatus::Active);
+
+        handle_dispute_event(&state, dispute("needs_response"), stamp("evt_2", 300)).await.unwrap();
+        assert_eq!(status(&state).await, SubscriptionStatus::Suspended);
+        let record = state.disputes.get("dp_1").await.unwrap();
+        assert!(record.entitlement_suspended);
+        assert_eq!(record.evidence_due_by.unwrap().timestamp(), DUE_BY);
+
+        // Redelivered inquiry is older than what we hold
+        handle_dispute_event(&state, dispute("warning_closed"), stamp("evt_1", 200)).await.unwrap();
+        assert_eq!(status(&state).await, SubscriptionStatus::Suspended);
+
+        handle_dispute_event(&state, dispute("won"), stamp("evt_3", 400)).await.unwrap();
+        assert_eq!(status(&state).await, SubscriptionStatus::Active);
+
+        state.disputes = StripeDisputeStore::new();
+        subscribed(&mut state).await;
+        handle_dispute_event(&state, dispute("needs_response"), stamp("evt_2", 300)).await.unwrap();
+        handle_dispute_event(&state, dispute("lost"), stamp("evt_4", 500)).await.unwrap();
+        assert_eq!(status(&state).await, SubscriptionStatus::Canceled);
+    }
+
+    #[tokio::test]
+    async fn dispute_ignores_receipt_email_of_unknown_customer() {
+        for customer in [serde_json::json!("cus_stranger"), serde_json::Value::Null] {
+            let mock = Router::new().route(
+                "/v1/charges/ch_1",
+                get(move || async move {
+                    Json(serde_json::json!({
+                        "id": "ch_1", "customer": customer, "receipt_email": "ada@example.com",
+                        "amount": 900, "amount_refunded": 0, "currency": "eur",
+                        "refunded": false, "status": "succeeded"
+                    }))
+                }),
+            );
+            let mut state = state_with_mock(mock).await;
+            subscribed(&mut state).await;
+
+            handle_dispute_event(&state, dispute("needs_response"), stamp("evt_1", 200)).await.unwrap();
+            assert_eq!(status(&state).await, SubscriptionStatus::Active);
+            assert_eq!(state.disputes.get("dp_1").await.unwrap().customer_email, None);
+        }
+    }
+
+    #[tokio::test]
+    async fn deadline_alerts_fire_once_per_threshold() {
+        let mut state = state_with_mock(charges_mock()).await;
+        let clock = FixedClock::at(DateTime::from_timestamp(DUE_BY - 100 * 3600, 0).unwrap());
+        state.clock = clock.clone();
+        state.config.dispute_alert_hours = vec![72, 24];
+        let (hook, mut posted) = crate::test_support::spawn_chat_hook().await;
+        state.alerter = Alerter::new(AlertConfig {
+            webhook_url: Some(hook),
+            ..AlertConfig::from_env()
+        }, None);
+        handle_dispute_event(&state, dispute("needs_response"), stamp("evt_1", 200)).await.unwrap();
+        // Drain the new-dispute alert
+        posted.recv().await.unwrap();
+
+        assert_eq!(alert_due_deadlines(&state).await, 0);
+
+        clock.set(DateTime::from_timestamp(DUE_BY - 70 * 3600, 0).unwrap());
+        assert_eq!(alert_due_deadlines(&state).await, 1);
+        assert_eq!(alert_due_deadlines(&state).await, 0);
+        let message = posted.recv().await.unwrap()["text"].as_str().unwrap().to_string();
+        assert!(message.contains("Stripe dispute dp_1") && message.contains("less than 72h"));
+
+        // Skipping straight past both thresholds alerts once, for the tighter one
+        clock.set(DateTime::from_timestamp(DUE_BY - 3600, 0).unwrap());
+        assert_eq!(alert_due_deadlines(&state).await, 1);
+        assert_eq!(state.disputes.get("dp_1").await.unwrap().alerted_within_hours, Some(24));
+        assert_eq!(alert_due_deadlines(&state).await, 0);
+    }
+
+    #[tokio::test]
+    async fn evidence_uploads_files_then_updates_dispute() {
+        let mock = charges_mock()
+            .route(
+                "/v1/files",
+                post(|body: axum::body::Bytes| async move {
+                    let body = String::from_utf8_lossy(&body);
+                    assert!(body.contains("dispute_evidence") && body.contains("receipt.pdf"));
+                    Json(serde_json::json!({ "id": "file_1", "purpose": "dispute_evidence" }))
+                }),
+            )
+            .route(
+                "/v1/disputes/dp_1",
+                post(|body: String| async move {
+                    assert!(body.contains("evidence%5Breceipt%5D=file_1"));
+                    assert!(body.contains("evidence%5Bproduct_description%5D=Pro+plan"));
+                    assert!(body.contains("submit=true"));
+                    Json(serde_json::json!({
+                        "id": "dp_1", "charge": "ch_1", "amount": 900, "currency": "eur",
+                        "reason": "fraudulent", "status": "under_review"
+                    }))
+                }),
+            );
+        let state = state_with_mock(mock).await;
+        handle_dispute_event(&state, dispute("needs_response"), stamp("evt_1", 200)).await.unwrap();
+        let state = Arc::new(state);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer admin_test_token".parse().unwrap());
+        let payload: SubmitStripeEvidenceRequest = serde_json::from_value(serde_json::json!({
+            "text": { "product_description": "Pro plan" },
+            "files": [{ "field": "receipt", "file_name": "receipt.pdf", "content_base64": "JVBERg==" }],
+            "submit": true
+        }))
+        .unwrap();
+
+        let resp = submit_stripe_dispute_evidence(State(state.clone()), headers, Path("dp_1".into()), Json(payload))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let record = state.disputes.get("dp_1").await.unwrap();
+        assert_eq!(record.status, "under_review");
+        assert!(record.evidence_submitted_at.is_some());
+    }
+}
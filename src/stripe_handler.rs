@@ -4,7 +4,8 @@
 // Stripe Webhook Handler with Idempotency (Redis) & 0x4121 Verification
 
 use axum::{
-    extract::{Json, State},
+    body::Bytes,
+    extract::{FromRef, Json, State},
     http::{HeaderMap, StatusCode},
     response::IntoResponse,
 };
@@ -15,9 +16,28 @@ use serde::{Deserialize, Serialize};
 use sha2::Sha256;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
+use subtle::ConstantTimeEq;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
+use crate::alerting::{AlertConfig, Alerter};
+use crate::auth::{AuthUser, Authenticator};
+use crate::clock::{SharedClock, SystemClock};
+use crate::currency::format_amount;
+use crate::dunning::{self, DunningConfig, DunningStore};
+use crate::event_archive::{ArchiveOutcome, ArchivedEvent, EventArchive, Provider};
+use crate::events::{self, DomainEvent, DomainEventType, EventBus};
+use crate::inbox::{InboxConfig, InboxEntry, WebhookInbox};
+use crate::metrics::{metrics, redis_fallback, webhook_rejected};
+use crate::notifications::{Notification, NotificationConfig, Notifier};
+use crate::outbound_webhooks::{OutboundDispatcher, OutboundWebhookConfig};
+use crate::paypal_handler::{DisputePolicy, PayPalDisputeStore};
+use crate::reconciliation::ReconcileConfig;
+use crate::stripe_api::StripeApiClient;
+use crate::stripe_disputes::{self, StripeDisputeStore};
+use crate::stripe_models::{Card, Charge, CheckoutSession, Invoice, Refund, StripeEventKind, Subscription};
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // STRIPE CONFIGURATION
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -25,9 +45,34 @@ use uuid::Uuid;
 #[derive(Clone)]
 pub struct StripeConfig {
     pub secret_key: String,
-    pub webhook_secret: String,
+    pub webhook_secrets: Vec<WebhookSecret>,
+    pub webhook_tolerance: Duration,
     pub publishable_key: String,
     pub redis_url: Option<String>,
+    pub api_base: String,
+    pub files_base: String,
+    pub portal_return_url: Option<String>,
+    pub portal_configuration_id: Option<String>,
+    pub checkout_success_url: Option<String>,
+    pub checkout_cancel_url: Option<String>,
+    pub catalog: PlanCatalog,
+    /// Process the API's copy of each event (and its object) instead of the delivered payload
+    pub verify_by_refetch: bool,
+    pub refund_policy: RefundPolicy,
+    /// Same policies as PayPal; Stripe `warning_*` disputes (inquiries) are the INQUIRY stage
+    pub dispute_policy: DisputePolicy,
+    /// Alert once evidence (or a PayPal seller response) is due within each of these many
+    /// hours (e.g. 72, then 24)
+    pub dispute_alert_hours: Vec<u64>,
+    pub dispute_alert_interval: Duration,
+    pub dunning: DunningConfig,
+    pub inbox: InboxConfig,
+    /// How long raw webhook deliveries (Stripe and PayPal) stay in the event archive
+    pub archive_retention: Duration,
+    pub reconcile: ReconcileConfig,
+    pub outbound_webhooks: OutboundWebhookConfig,
+    pub notifications: NotificationConfig,
+    pub alerts: AlertConfig,
 }
 
 impl StripeConfig {
@@ -35,11 +80,213 @@ impl StripeConfig {
         Self {
             secret_key: std::env::var("STRIPE_SECRET_KEY")
                 .unwrap_or_else(|_| "sk_test_placeholder".to_string()),
-            webhook_secret: std::env::var("STRIPE_WEBHOOK_SECRET")
-                .unwrap_or_else(|_| "whsec_placeholder".to_string()),
+            webhook_secrets: WebhookSecret::from_env(),
+            webhook_tolerance: Duration::from_secs(
+                std::env::var("STRIPE_WEBHOOK_TOLERANCE_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(300),
+            ),
             publishable_key: std::env::var("STRIPE_PUBLISHABLE_KEY")
                 .unwrap_or_else(|_| "pk_test_placeholder".to_string()),
             redis_url: std::env::var("REDIS_URL").ok(),
+            api_base: std::env::var("STRIPE_API_BASE")
+                .unwrap_or_else(|_| "https://api.stripe.com".to_string()),
+            files_base: std::env::var("STRIPE_FILES_BASE")
+                .unwrap_or_else(|_| "https://files.stripe.com".to_string()),
+            portal_return_url: std::env::var("STRIPE_PORTAL_RETURN_URL").ok(),
+            portal_configuration_id: std::env::var("STRIPE_PORTAL_CONFIGURATION_ID").ok(),
+            checkout_success_url: std::env::var("STRIPE_CHECKOUT_SUCCESS_URL").ok(),
+            checkout_cancel_url: std::env::var("STRIPE_CHECKOUT_CANCEL_URL").ok(),
+            catalog: PlanCatalog::from_env(),
+            verify_by_refetch: std::env::var("STRIPE_VERIFY_BY_REFETCH")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            refund_policy: std::env::var("STRIPE_REFUND_POLICY")
+                .map(|v| RefundPolicy::parse(&v))
+                .unwrap_or(RefundPolicy::RevokeOnFull),
+            dispute_policy: std::env::var("STRIPE_DISPUTE_POLICY")
+                .map(|v| DisputePolicy::parse(&v))
+                .unwrap_or(DisputePolicy::SuspendOnClaim),
+            dispute_alert_hours: std::env::var("STRIPE_DISPUTE_ALERT_HOURS")
+                .unwrap_or_else(|_| "72,24".to_string())
+                .split(',')
+                .filter_map(|h| h.trim().parse().ok())
+                .collect(),
+            dispute_alert_interval: Duration::from_secs(
+                std::env::var("STRIPE_DISPUTE_ALERT_INTERVAL_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(900),
+            ),
+            dunning: DunningConfig::from_env(),
+            inbox: InboxConfig::from_env(),
+            archive_retention: Duration::from_secs(
+                std::env::var("EVENT_ARCHIVE_RETENTION_DAYS")
+                    .ok()
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .unwrap_or(30)
+                    * 86400,
+            ),
+            reconcile: ReconcileConfig::from_env(),
+            outbound_webhooks: OutboundWebhookConfig::from_env(),
+            notifications: NotificationConfig::from_env(),
+            alerts: AlertConfig::from_env(),
+        }
+    }
+
+    /// Mode implied by the secret key: `sk_live_`/`rk_live_` keys only ever see live events
+    pub fn expected_livemode(&self) -> bool {
+        self.secret_key.starts_with("sk_live_") || self.secret_key.starts_with("rk_live_")
+    }
+
+    /// Refuse live mode while any value is still a placeholder default
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.expected_livemode() {
+            return Ok(());
+        }
+
+        let mut placeholders = Vec::new();
+        if self.publishable_key.contains("placeholder") {
+            placeholders.push("STRIPE_PUBLISHABLE_KEY".to_string());
+        }
+        for secret in &self.webhook_secrets {
+            if secret.secret.contains("placeholder") {
+                placeholders.push(format!("webhook secret {}", secret.label));
+            }
+        }
+
+        if placeholders.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Stripe live key configured but placeholders remain: {}",
+                placeholders.join(", ")
+            ))
+        }
+    }
+}
+
+/// A webhook signing secret; several are active while an endpoint secret is rolled
+#[derive(Clone, Debug)]
+pub struct WebhookSecret {
+    pub label: String,
+    pub secret: String,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl WebhookSecret {
+    /// `STRIPE_WEBHOOK_SECRETS=whsec_new,whsec_old@2026-11-01T00:00:00Z` (expiry optional),
+    /// falling back to the single `STRIPE_WEBHOOK_SECRET`.
+    pub fn from_env() -> Vec<Self> {
+        let raw = std::env::var("STRIPE_WEBHOOK_SECRETS")
+            .or_else(|_| std::env::var("STRIPE_WEBHOOK_SECRET"))
+            .unwrap_or_else(|_| "whsec_placeholder".to_string());
+
+        raw.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .enumerate()
+            .map(|(i, entry)| {
+                let (secret, expires_at) = match entry.split_once('@') {
+                    Some((secret, expiry)) => {
+                        let expires_at = DateTime::parse_from_rfc3339(expiry)
+                            .map(|dt| dt.with_timezone(&Utc))
+                            .map_err(|_| println!("❌ Invalid expiry for webhook secret #{}: {}", i, expiry))
+                            .ok();
+                        (secret, expires_at)
+                    }
+                    None => (entry, None),
+                };
+                WebhookSecret {
+                    label: format!("#{}", i),
+                    secret: secret.to_string(),
+                    expires_at,
+                }
+            })
+            .collect()
+    }
+
+    fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_none_or(|expiry| now < expiry)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// PLAN CATALOG
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckoutMode {
+    Subscription,
+    Payment,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CatalogEntry {
+    pub price_id: String,
+    pub mode: CheckoutMode,
+}
+
+/// Plans we sell, keyed by our plan name (e.g. `pro_monthly`).
+/// Loaded from `STRIPE_PLAN_CATALOG`:
+/// `{"pro_monthly": {"price_id": "price_...", "mode": "subscription"}}`
+#[derive(Clone, Debug, Default)]
+pub struct PlanCatalog {
+    entries: HashMap<String, CatalogEntry>,
+}
+
+impl PlanCatalog {
+    pub fn from_env() -> Self {
+        let entries = match std::env::var("STRIPE_PLAN_CATALOG") {
+            Ok(raw) => serde_json::from_str(&raw).unwrap_or_else(|e| {
+                println!("❌ Invalid STRIPE_PLAN_CATALOG: {}", e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        Self { entries }
+    }
+
+    pub fn get(&self, plan: &str) -> Option<&CatalogEntry> {
+        self.entries.get(plan)
+    }
+
+    /// Our plan name for a Stripe price
+    pub fn plan_for_price(&self, price_id: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(_, entry)| entry.price_id == price_id)
+            .map(|(plan, _)| plan.as_str())
+    }
+}
+
+/// What a refund does to the customer's entitlement
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RefundPolicy {
+    /// Revoke access once the charge is fully refunded; partial refunds keep it
+    RevokeOnFull,
+    /// Revoke access on any refund, partial or full
+    RevokeOnAny,
+    /// Record refunds but never touch entitlements
+    Keep,
+}
+
+impl RefundPolicy {
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "revoke_on_any" => RefundPolicy::RevokeOnAny,
+            "keep" => RefundPolicy::Keep,
+            _ => RefundPolicy::RevokeOnFull,
+        }
+    }
+
+    fn should_revoke(&self, fully_refunded: bool) -> bool {
+        match self {
+            RefundPolicy::RevokeOnFull => fully_refunded,
+            RefundPolicy::RevokeOnAny => true,
+            RefundPolicy::Keep => false,
         }
     }
 }
@@ -63,18 +310,6 @@ pub struct StripeEventData {
     pub object: serde_json::Value,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CheckoutSession {
-    pub id: String,
-    pub customer: Option<String>,
-    pub customer_email: Option<String>,
-    pub subscription: Option<String>,
-    pub amount_total: Option<i64>,
-    pub currency: Option<String>,
-    pub status: String,
-    pub metadata: Option<HashMap<String, String>>,
-}
-
 // ═══════════════════════════════════════════════════════════════════════════════
 // IDEMPOTENCY STORE (Redis or In-Memory)
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -113,13 +348,21 @@ impl IdempotencyStore {
 
     /// O(1) - Check if event already processed
     pub async fn is_processed(&self, event_id: &str) -> bool {
+        let processed = self.lookup(event_id).await;
+        let result = if processed { "hit" } else { "miss" };
+        metrics().inc_counter("idempotency_checks_total", &[("result", result)]);
+        processed
+    }
+
+    async fn lookup(&self, event_id: &str) -> bool {
         if let Some(client) = &self.redis_client {
              if let Ok(mut con) = client.get_multiplexed_async_connection().await {
                  let exists: bool = con.exists(format!("event:{}", event_id)).await.unwrap_or(false);
                  return exists;
              }
         }
-        
+
+        redis_fallback("idempotency", self.redis_client.is_some());
         let store = self.processed_events_fallback.read().await;
         store.contains_key(event_id)
     }
@@ -134,6 +377,7 @@ impl IdempotencyStore {
              }
         }
 
+        redis_fallback("idempotency", self.redis_client.is_some());
         let mut store = self.processed_events_fallback.write().await;
         store.insert(
             event_id.clone(),
@@ -144,6 +388,14 @@ impl IdempotencyStore {
             },
         );
     }
+
+    /// Drop fallback entries processed before `cutoff` (Redis entries expire on their own)
+    pub async fn prune_fallback(&self, cutoff: DateTime<Utc>) -> usize {
+        let mut store = self.processed_events_fallback.write().await;
+        let before = store.len();
+        store.retain(|_, e| e.processed_at >= cutoff);
+        before - store.len()
+    }
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -155,6 +407,34 @@ pub struct SubscriptionManager {
     // In production, use DB. For now, in-memory is fine for demo, 
     // or Redis could also be used here. Keeping in-memory/Redis simplicity.
     subscriptions: Arc<RwLock<HashMap<String, UserSubscription>>>,
+    /// Newest subscription snapshot seen before its checkout completed, by Stripe subscription id
+    pending_updates: Arc<RwLock<HashMap<String, (Subscription, EventStamp)>>>,
+}
+
+/// Identity and `created` time of the Stripe event that last changed a subscription.
+/// Stripe does not guarantee delivery order, so older events must not overwrite newer state.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EventStamp {
+    pub id: String,
+    pub created: i64,
+}
+
+impl EventStamp {
+    pub fn of(event: &StripeEvent) -> Self {
+        Self {
+            id: event.id.clone(),
+            created: event.created,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ApplyOutcome {
+    Applied,
+    /// A newer event was already applied; state left untouched
+    Stale,
+    /// No local subscription yet; kept until checkout completes
+    Deferred,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -167,6 +447,14 @@ pub struct UserSubscription {
     pub status: SubscriptionStatus,
     pub activated_at: DateTime<Utc>,
     pub current_period_end: Option<DateTime<Utc>>,
+    pub trial_end: Option<DateTime<Utc>>,
+    /// Whether the Stripe subscription has a default payment method; card-less trials don't
+    pub payment_method_on_file: bool,
+    /// While Suspended: the status a reinstatement returns to. Set at suspension and
+    /// replaced by whatever Stripe reports in the meantime.
+    #[serde(default)]
+    pub suspended_from: Option<SubscriptionStatus>,
+    pub last_event: Option<EventStamp>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -176,6 +464,19 @@ pub enum SubscriptionPlan {
     Enterprise { monthly: bool },
 }
 
+impl SubscriptionPlan {
+    /// The checkout plan name, e.g. `pro_monthly`
+    pub fn name(&self) -> &'static str {
+        match self {
+            SubscriptionPlan::Free => "free",
+            SubscriptionPlan::Pro { monthly: true } => "pro_monthly",
+            SubscriptionPlan::Pro { monthly: false } => "pro_annual",
+            SubscriptionPlan::Enterprise { monthly: true } => "enterprise_monthly",
+            SubscriptionPlan::Enterprise { monthly: false } => "enterprise_annual",
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub enum SubscriptionStatus {
     Active,
@@ -183,24 +484,131 @@ pub enum SubscriptionStatus {
     PastDue,
     Canceled,
     Unpaid,
+    Suspended,
+}
+
+impl SubscriptionStatus {
+    pub fn from_stripe(status: &str) -> Self {
+        match status {
+            "active" => SubscriptionStatus::Active,
+            "trialing" => SubscriptionStatus::Trialing,
+            "past_due" => SubscriptionStatus::PastDue,
+            "canceled" | "incomplete_expired" => SubscriptionStatus::Canceled,
+            "paused" => SubscriptionStatus::Suspended,
+            _ => SubscriptionStatus::Unpaid, // unpaid, incomplete
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubscriptionStatus::Active => "active",
+            SubscriptionStatus::Trialing => "trialing",
+            SubscriptionStatus::PastDue => "past_due",
+            SubscriptionStatus::Canceled => "canceled",
+            SubscriptionStatus::Unpaid => "unpaid",
+            SubscriptionStatus::Suspended => "suspended",
+        }
+    }
+}
+
+impl UserSubscription {
+    /// Whether the plan's features are available at `now`. A trial grants access only until
+    /// `trial_end`, even if the event converting or ending it has not arrived yet.
+    pub fn is_entitled(&self, now: DateTime<Utc>) -> bool {
+        match self.status {
+            SubscriptionStatus::Active | SubscriptionStatus::PastDue => true,
+            SubscriptionStatus::Trialing => self.trial_end.is_none_or(|end| now < end),
+            SubscriptionStatus::Canceled | SubscriptionStatus::Unpaid | SubscriptionStatus::Suspended => false,
+        }
+    }
+
+    /// Whole days left in an ongoing trial
+    pub fn trial_days_left(&self, now: DateTime<Utc>) -> Option<i64> {
+        match (&self.status, self.trial_end) {
+            (SubscriptionStatus::Trialing, Some(end)) if end > now => Some((end - now).num_days()),
+            _ => None,
+        }
+    }
+
+    /// Whether an event stamped `stamp` carrying `incoming` status is older than what we hold.
+    /// Stripe timestamps are in seconds; on a tie a cancellation is never undone.
+    fn is_stale(&self, stamp: &EventStamp, incoming: &SubscriptionStatus) -> bool {
+        match &self.last_event {
+            Some(last) if stamp.created < last.created => true,
+            Some(last) if stamp.created == last.created => {
+                self.status == SubscriptionStatus::Canceled && *incoming != SubscriptionStatus::Canceled
+            }
+            _ => false,
+        }
+    }
+
+    /// The status we hold once Stripe reports `incoming`
+    pub fn resolve_status(&self, incoming: SubscriptionStatus) -> SubscriptionStatus {
+        let keep_local = match self.status {
+            // A local suspension (dispute) outlives Stripe-side updates, but not a cancellation
+            SubscriptionStatus::Suspended => incoming != SubscriptionStatus::Canceled,
+            // Our dunning grace period is over even while Stripe keeps retrying
+            SubscriptionStatus::Unpaid => incoming == SubscriptionStatus::PastDue,
+            _ => false,
+        };
+        if keep_local {
+            self.status.clone()
+        } else {
+            incoming
+        }
+    }
+
+    fn apply_snapshot(&mut self, snapshot: &Subscription, stamp: &EventStamp) -> ApplyOutcome {
+        let incoming = SubscriptionStatus::from_stripe(&snapshot.status);
+        if self.is_stale(stamp, &incoming) {
+            return ApplyOutcome::Stale;
+        }
+
+        self.status = self.resolve_status(incoming.clone());
+        if self.status == SubscriptionStatus::Suspended {
+            self.suspended_from = Some(incoming);
+        }
+        self.stripe_subscription_id = Some(snapshot.id.clone());
+        self.stripe_customer_id = Some(snapshot.customer.clone());
+        if let Some(end) = snapshot.current_period_end {
+            self.current_period_end = DateTime::from_timestamp(end, 0);
+        }
+        self.trial_end = snapshot.trial_end.and_then(|end| DateTime::from_timestamp(end, 0));
+        self.payment_method_on_file = snapshot.default_payment_method.is_some();
+        self.last_event = Some(stamp.clone());
+        ApplyOutcome::Applied
+    }
 }
 
 impl SubscriptionManager {
     pub fn new() -> Self {
         Self {
             subscriptions: Arc::new(RwLock::new(HashMap::new())),
+            pending_updates: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
     /// Activate subscription after successful payment
     pub async fn activate_subscription(
         &self,
+        user_id: Option<Uuid>,
         email: &str,
         stripe_customer_id: Option<String>,
         stripe_subscription_id: Option<String>,
         plan_name: &str,
+        stamp: EventStamp,
     ) -> UserSubscription {
-        let user_id = Uuid::new_v4();
+        let mut store = self.subscriptions.write().await;
+
+        // A redelivered or late checkout must not reset a subscription that already moved on
+        if let Some(existing) = store.get(email) {
+            if stripe_subscription_id.is_some() && existing.stripe_subscription_id == stripe_subscription_id {
+                println!("[SUBSCRIPTION] ⚡ Ignoring repeated activation {} for {}", stamp.id, email);
+                return existing.clone();
+            }
+        }
+
+        let user_id = user_id.unwrap_or_else(Uuid::new_v4);
         let plan = match plan_name {
             "pro_monthly" => SubscriptionPlan::Pro { monthly: true },
             "pro_annual" => SubscriptionPlan::Pro { monthly: false },
@@ -209,7 +617,7 @@ impl SubscriptionManager {
             _ => SubscriptionPlan::Free,
         };
 
-        let subscription = UserSubscription {
+        let mut subscription = UserSubscription {
             user_id,
             email: email.to_string(),
             stripe_customer_id,
@@ -218,22 +626,104 @@ impl SubscriptionManager {
             status: SubscriptionStatus::Active,
             activated_at: Utc::now(),
             current_period_end: None,
+            trial_end: None,
+            payment_method_on_file: false,
+            suspended_from: None,
+            // Only subscription events order subscription state; the checkout itself doesn't
+            last_event: None,
         };
 
-        let mut store = self.subscriptions.write().await;
+        // Subscription events that beat the checkout here are applied now (e.g. `trialing`)
+        if let Some(sub_id) = subscription.stripe_subscription_id.clone() {
+            if let Some((snapshot, pending_stamp)) = self.pending_updates.write().await.remove(&sub_id) {
+                if subscription.apply_snapshot(&snapshot, &pending_stamp) == ApplyOutcome::Applied {
+                    println!("[SUBSCRIPTION] 🔁 Applied early event {} to {}", pending_stamp.id, sub_id);
+                }
+            }
+        }
+
         store.insert(email.to_string(), subscription.clone());
 
-        println!("[SUBSCRIPTION] ✅ Activated {} for {}", plan_name, email);
+        println!(
+            "[SUBSCRIPTION] ✅ Activated {} for {} ({:?}, {})",
+            plan_name, email, subscription.status, stamp.id
+        );
 
         subscription
     }
 
+    /// Apply a Stripe subscription snapshot from a `customer.subscription.*` event,
+    /// skipping it when a newer event was already applied
+    pub async fn apply_subscription_event(&self, snapshot: &Subscription, stamp: EventStamp) -> ApplyOutcome {
+        let mut store = self.subscriptions.write().await;
+        let key = store
+            .iter()
+            .find(|(_, s)| s.stripe_subscription_id.as_deref() == Some(snapshot.id.as_str()))
+            .map(|(k, _)| k.clone());
+
+        let Some(key) = key else {
+            let mut pending = self.pending_updates.write().await;
+            let newer = pending
+                .get(&snapshot.id)
+                .is_none_or(|(_, held)| stamp.created >= held.created);
+            if newer {
+                pending.insert(snapshot.id.clone(), (snapshot.clone(), stamp));
+            }
+            return ApplyOutcome::Deferred;
+        };
+
+        let local = store.get_mut(&key).expect("key found under the same lock");
+        let outcome = local.apply_snapshot(snapshot, &stamp);
+        match outcome {
+            ApplyOutcome::Applied => println!(
+                "[SUBSCRIPTION] 🔄 {} is now {:?} ({})",
+                snapshot.id, local.status, stamp.id
+            ),
+            _ => println!(
+                "[SUBSCRIPTION] ⚡ Skipped stale event {} for {} (last: {:?})",
+                stamp.id,
+                snapshot.id,
+                local.last_event.as_ref().map(|l| &l.id)
+            ),
+        }
+        outcome
+    }
+
     /// Get subscription by email
     pub async fn get_by_email(&self, email: &str) -> Option<UserSubscription> {
         let store = self.subscriptions.read().await;
         store.get(email).cloned()
     }
 
+    /// Get subscription by Stripe customer id
+    pub async fn get_by_customer_id(&self, customer_id: &str) -> Option<UserSubscription> {
+        let store = self.subscriptions.read().await;
+        store
+            .values()
+            .find(|s| s.stripe_customer_id.as_deref() == Some(customer_id))
+            .cloned()
+    }
+
+    /// Get subscription by Stripe subscription id
+    pub async fn get_by_stripe_subscription_id(&self, subscription_id: &str) -> Option<UserSubscription> {
+        let store = self.subscriptions.read().await;
+        store
+            .values()
+            .find(|s| s.stripe_subscription_id.as_deref() == Some(subscription_id))
+            .cloned()
+    }
+
+    /// Snapshot of every local subscription
+    pub async fn all(&self) -> Vec<UserSubscription> {
+        self.subscriptions.read().await.values().cloned().collect()
+    }
+
+    /// Get subscription by our user id
+    pub async fn get_by_user_id(&self, user_id: Uuid) -> Option<UserSubscription> {
+        let store = self.subscriptions.read().await;
+        store.values().find(|s| s.user_id == user_id).cloned()
+    }
+
     /// Cancel subscription
     pub async fn cancel_subscription(&self, email: &str) -> bool {
         let mut store = self.subscriptions.write().await;
@@ -245,6 +735,131 @@ impl SubscriptionManager {
             false
         }
     }
+
+    /// Revoke access because of `stamp` (e.g. a refund). Recorded as the latest event so
+    /// older subscription events cannot restore access; a newer renewal still can.
+    pub async fn revoke_subscription(&self, email: &str, stamp: EventStamp) -> bool {
+        let mut store = self.subscriptions.write().await;
+        match store.get_mut(email) {
+            Some(sub) if sub.status != SubscriptionStatus::Canceled => {
+                sub.status = SubscriptionStatus::Canceled;
+                if sub.last_event.as_ref().is_none_or(|last| stamp.created >= last.created) {
+                    sub.last_event = Some(stamp);
+                }
+                println!("[SUBSCRIPTION] ⛔ Revoked subscription for {}", email);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Payment failed: keep access during the dunning grace period, flagged PastDue
+    pub async fn mark_past_due(&self, email: &str) -> bool {
+        self.transition(
+            email,
+            &[SubscriptionStatus::Active, SubscriptionStatus::Trialing],
+            SubscriptionStatus::PastDue,
+        )
+        .await
+    }
+
+    /// Dunning grace period over without payment
+    pub async fn mark_unpaid(&self, email: &str) -> bool {
+        self.transition(email, &[SubscriptionStatus::PastDue], SubscriptionStatus::Unpaid)
+            .await
+    }
+
+    /// Overdue invoice paid
+    pub async fn restore_active(&self, email: &str) -> bool {
+        self.transition(
+            email,
+            &[SubscriptionStatus::PastDue, SubscriptionStatus::Unpaid],
+            SubscriptionStatus::Active,
+        )
+        .await
+    }
+
+    async fn transition(&self, email: &str, from: &[SubscriptionStatus], to: SubscriptionStatus) -> bool {
+        let mut store = self.subscriptions.write().await;
+        match store.get_mut(email) {
+            Some(sub) if from.contains(&sub.status) => {
+                println!("[SUBSCRIPTION] 🔄 {} {:?} → {:?}", email, sub.status, to);
+                sub.status = to;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Cancel paid subscriptions whose period ended before `cutoff` with no renewal event.
+    /// A later Stripe event restores them. Returns the affected emails.
+    pub async fn expire_lapsed(&self, cutoff: DateTime<Utc>) -> Vec<String> {
+        self.expire_where(|s| {
+            matches!(s.status, SubscriptionStatus::Active | SubscriptionStatus::PastDue)
+                && s.current_period_end.is_some_and(|end| end < cutoff)
+        })
+        .await
+    }
+
+    /// Cancel trials whose `trial_end` is before `cutoff` with no conversion event
+    pub async fn expire_trials(&self, cutoff: DateTime<Utc>) -> Vec<String> {
+        self.expire_where(|s| {
+            s.status == SubscriptionStatus::Trialing && s.trial_end.is_some_and(|end| end < cutoff)
+        })
+        .await
+    }
+
+    async fn expire_where(&self, lapsed: impl Fn(&UserSubscription) -> bool) -> Vec<String> {
+        let mut store = self.subscriptions.write().await;
+        store
+            .values_mut()
+            .filter(|s| lapsed(s))
+            .map(|s| {
+                s.status = SubscriptionStatus::Canceled;
+                s.email.clone()
+            })
+            .collect()
+    }
+
+    /// Drop early snapshots for subscriptions whose checkout never completed
+    pub async fn prune_pending(&self, cutoff: DateTime<Utc>) -> usize {
+        let mut pending = self.pending_updates.write().await;
+        let before = pending.len();
+        pending.retain(|_, (_, stamp)| stamp.created >= cutoff.timestamp());
+        before - pending.len()
+    }
+
+    /// Suspend an entitled subscription (e.g. while a dispute is open)
+    pub async fn suspend_subscription(&self, email: &str) -> bool {
+        let mut store = self.subscriptions.write().await;
+        match store.get_mut(email) {
+            Some(sub)
+                if matches!(
+                    sub.status,
+                    SubscriptionStatus::Active | SubscriptionStatus::Trialing | SubscriptionStatus::PastDue
+                ) =>
+            {
+                sub.suspended_from = Some(std::mem::replace(&mut sub.status, SubscriptionStatus::Suspended));
+                println!("[SUBSCRIPTION] ⏸️ Suspended subscription for {}", email);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Lift a suspension once the reason for it is gone, back to the status held before it
+    /// (or the latest one Stripe reported since): a trial stays a trial, PastDue stays in dunning
+    pub async fn reinstate_subscription(&self, email: &str) -> bool {
+        let mut store = self.subscriptions.write().await;
+        match store.get_mut(email) {
+            Some(sub) if sub.status == SubscriptionStatus::Suspended => {
+                sub.status = sub.suspended_from.take().unwrap_or(SubscriptionStatus::Active);
+                println!("[SUBSCRIPTION] ▶️ Reinstated subscription for {} as {:?}", email, sub.status);
+                true
+            }
+            _ => false,
+        }
+    }
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -253,45 +868,80 @@ impl SubscriptionManager {
 
 type HmacSha256 = Hmac<Sha256>;
 
-/// Verify Stripe webhook signature
-/// Big O: O(n) where n is payload size
+/// Parsed `Stripe-Signature` header: `t=timestamp,v1=sig[,v1=sig...]`
+#[derive(Debug, PartialEq)]
+pub struct SignatureHeader<'a> {
+    pub timestamp: &'a str,
+    pub v1_signatures: Vec<&'a str>,
+}
+
+/// Keeps every `v1` entry: during secret rotation Stripe signs with each active secret
+pub fn parse_signature_header(signature_header: &str) -> Result<SignatureHeader<'_>, String> {
+    let mut timestamp = None;
+    let mut v1_signatures = Vec::new();
+
+    for part in signature_header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = Some(value),
+            Some(("v1", value)) => v1_signatures.push(value),
+            _ => {} // v0 and unknown schemes are ignored
+        }
+    }
+
+    let timestamp = timestamp.ok_or("Missing timestamp")?;
+    if v1_signatures.is_empty() {
+        return Err("Missing signature".to_string());
+    }
+
+    Ok(SignatureHeader {
+        timestamp,
+        v1_signatures,
+    })
+}
+
+/// Verify Stripe webhook signature over the exact delivered bytes against every
+/// secret active at `now`. Returns the label of the secret that matched.
+/// Big O: O(n * s + s * v) where n is payload size, s secrets, v signatures
 pub fn verify_webhook_signature(
     payload: &[u8],
     signature_header: &str,
-    webhook_secret: &str,
-) -> Result<(), String> {
-    // Parse signature header: t=timestamp,v1=signature
-    let parts: HashMap<&str, &str> = signature_header
-        .split(',')
-        .filter_map(|part| {
-            let mut split = part.splitn(2, '=');
-            Some((split.next()?, split.next()?))
-        })
-        .collect();
-
-    let timestamp = parts.get("t").ok_or("Missing timestamp")?;
-    let expected_sig = parts.get("v1").ok_or("Missing signature")?;
-
-    // Check timestamp (5 minute tolerance)
-    let ts: i64 = timestamp.parse().map_err(|_| "Invalid timestamp")?;
-    let now = Utc::now().timestamp();
-    if (now - ts).abs() > 300 {
-        return Err("Webhook timestamp too old".to_string());
+    webhook_secrets: &[WebhookSecret],
+    tolerance: Duration,
+    now: DateTime<Utc>,
+) -> Result<String, String> {
+    let header = parse_signature_header(signature_header)?;
+
+    let ts: i64 = header.timestamp.parse().map_err(|_| "Invalid timestamp")?;
+    if now.timestamp().abs_diff(ts) > tolerance.as_secs() {
+        return Err("Webhook timestamp outside tolerance".to_string());
     }
 
-    // Compute expected signature
-    let signed_payload = format!("{}.{}", timestamp, String::from_utf8_lossy(payload));
-    let mut mac = HmacSha256::new_from_slice(webhook_secret.as_bytes())
-        .map_err(|_| "Invalid webhook secret")?;
-    mac.update(signed_payload.as_bytes());
-    let computed_sig = hex::encode(mac.finalize().into_bytes());
+    // Malformed entries can never match; skip rather than fail the whole header
+    let candidates: Vec<Vec<u8>> = header
+        .v1_signatures
+        .iter()
+        .filter_map(|sig| hex::decode(sig).ok())
+        .collect();
 
-    // Constant-time comparison
-    if computed_sig != *expected_sig {
-        return Err("Invalid webhook signature".to_string());
+    for secret in webhook_secrets.iter().filter(|s| s.is_active(now)) {
+        // Signed payload is `{t}.{raw body}`
+        let mut mac = HmacSha256::new_from_slice(secret.secret.as_bytes())
+            .map_err(|_| "Invalid webhook secret")?;
+        mac.update(header.timestamp.as_bytes());
+        mac.update(b".");
+        mac.update(payload);
+        let computed = mac.finalize().into_bytes();
+
+        // Constant-time comparison (length mismatch returns false early, which leaks nothing secret)
+        if candidates
+            .iter()
+            .any(|sig| bool::from(computed.as_slice().ct_eq(sig.as_slice())))
+        {
+            return Ok(secret.label.clone());
+        }
     }
 
-    Ok(())
+    Err("Invalid webhook signature".to_string())
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -303,52 +953,144 @@ pub struct StripeWebhookState {
     pub config: StripeConfig,
     pub idempotency: IdempotencyStore,
     pub subscriptions: SubscriptionManager,
+    pub disputes: StripeDisputeStore,
+    /// Owned here so the scheduler can watch PayPal's response deadlines too
+    pub paypal_disputes: PayPalDisputeStore,
+    pub dunning: DunningStore,
+    pub inbox: WebhookInbox,
+    pub archive: EventArchive,
+    pub events: EventBus,
+    pub outbound: OutboundDispatcher,
+    pub notifier: Notifier,
+    pub alerter: Alerter,
+    pub api: StripeApiClient,
+    pub auth: Authenticator,
+    pub clock: SharedClock,
 }
 
 impl StripeWebhookState {
-    pub fn new() -> Self {
+    pub fn new(auth: Authenticator) -> Self {
         let config = StripeConfig::from_env();
+        let outbound = OutboundDispatcher::new(config.outbound_webhooks.clone(), config.redis_url.clone());
         Self {
+            events: EventBus::new(1024, outbound.clone()),
+            outbound,
+            auth,
             idempotency: IdempotencyStore::new(config.redis_url.clone()),
+            inbox: WebhookInbox::new(config.redis_url.clone()),
+            archive: EventArchive::new(config.redis_url.clone(), config.archive_retention),
+            notifier: Notifier::new(config.notifications.clone(), config.redis_url.clone()),
+            alerter: Alerter::new(config.alerts.clone(), config.redis_url.clone()),
+            api: StripeApiClient::new(
+                config.secret_key.clone(),
+                config.api_base.clone(),
+                config.files_base.clone(),
+            ),
             config,
             subscriptions: SubscriptionManager::new(),
+            disputes: StripeDisputeStore::new(),
+            paypal_disputes: PayPalDisputeStore::new(),
+            dunning: DunningStore::new(),
+            clock: Arc::new(SystemClock),
         }
     }
 }
 
+impl FromRef<Arc<StripeWebhookState>> for Authenticator {
+    fn from_ref(state: &Arc<StripeWebhookState>) -> Self {
+        state.auth.clone()
+    }
+}
+
+impl FromRef<Arc<StripeWebhookState>> for EventArchive {
+    fn from_ref(state: &Arc<StripeWebhookState>) -> Self {
+        state.archive.clone()
+    }
+}
+
 /// Main webhook handler
 pub async fn stripe_webhook_handler(
     State(state): State<Arc<StripeWebhookState>>,
     headers: HeaderMap,
-    body: String,
+    body: Bytes,
 ) -> impl IntoResponse {
+    metrics().inc_counter("webhooks_received_total", &[("provider", "stripe")]);
+
     // Get signature header
     let signature = match headers.get("stripe-signature") {
         Some(sig) => sig.to_str().unwrap_or(""),
         None => {
             println!("[WEBHOOK] ❌ Missing Stripe-Signature header");
+            webhook_rejected("stripe", "unknown", "missing_signature");
+            state.alerter.signature_failed(Provider::Stripe, state.clock.now());
             return (StatusCode::BAD_REQUEST, "Missing signature").into_response();
         }
     };
 
     // Verify signature (0x4121 Security Gate)
-    if let Err(e) =
-        verify_webhook_signature(body.as_bytes(), signature, &state.config.webhook_secret)
-    {
-        println!("[WEBHOOK] ❌ Signature verification failed: {}", e);
-        return (StatusCode::UNAUTHORIZED, "Invalid signature").into_response();
+    match verify_webhook_signature(
+        &body,
+        signature,
+        &state.config.webhook_secrets,
+        state.config.webhook_tolerance,
+        state.clock.now(),
+    ) {
+        Ok(secret) => {
+            println!("[WEBHOOK] 🔐 Signature verified with secret {}", secret);
+            state.alerter.webhook_received(state.clock.now());
+        }
+        Err(e) => {
+            println!("[WEBHOOK] ❌ Signature verification failed: {}", e);
+            webhook_rejected("stripe", "unknown", "invalid_signature");
+            state.alerter.signature_failed(Provider::Stripe, state.clock.now());
+            return (StatusCode::UNAUTHORIZED, "Invalid signature").into_response();
+        }
     }
 
-    // Parse event
-    let event: StripeEvent = match serde_json::from_str(&body) {
-        Ok(e) => e,
+    // Parse event. JSON is UTF-8 (RFC 8259), so a body that is not cannot be an event, and
+    // one that is gets stored as this exact string
+    let parsed = std::str::from_utf8(&body)
+        .map_err(|e| e.to_string())
+        .and_then(|raw| serde_json::from_str::<StripeEvent>(raw).map(|e| (raw, e)).map_err(|e| e.to_string()));
+    let (raw_body, event) = match parsed {
+        Ok(parsed) => parsed,
         Err(e) => {
             println!("[WEBHOOK] ❌ Failed to parse event: {}", e);
+            webhook_rejected("stripe", "unknown", "invalid_payload");
             return (StatusCode::BAD_REQUEST, "Invalid event").into_response();
         }
     };
 
     println!("[WEBHOOK] 📬 Received: {} ({})", event.event_type, event.id);
+    metrics().inc_counter(
+        "webhooks_verified_total",
+        &[("provider", "stripe"), ("event_type", &event.event_type)],
+    );
+    let archived = ArchivedEvent::new(
+        Provider::Stripe,
+        &event.id,
+        &event.event_type,
+        &headers,
+        raw_body.to_string(),
+        state.clock.now(),
+    );
+
+    // A test-mode event must never grant live entitlements (and vice versa)
+    let expected_livemode = state.config.expected_livemode();
+    if event.livemode != expected_livemode {
+        let expected = if expected_livemode { "live" } else { "test" };
+        metrics().inc_counter("stripe_webhook_livemode_mismatch_total", &[("expected", expected)]);
+        webhook_rejected("stripe", &event.event_type, "livemode_mismatch");
+        println!(
+            "[WEBHOOK] ❌ Event {} livemode={} but configured key is {} mode",
+            event.id, event.livemode, expected
+        );
+        state
+            .archive
+            .record(archived.with_outcome(ArchiveOutcome::Rejected, Some("livemode mismatch".into())))
+            .await;
+        return (StatusCode::BAD_REQUEST, "Event livemode does not match configured key").into_response();
+    }
 
     // Idempotency check - prevent double processing
     if state.idempotency.is_processed(&event.id).await {
@@ -359,137 +1101,558 @@ pub async fn stripe_webhook_handler(
         return (StatusCode::OK, "Already processed").into_response();
     }
 
-    // Process based on event type
-    let result = match event.event_type.as_str() {
-        "checkout.session.completed" => handle_checkout_completed(&state, &event).await,
-        "invoice.paid" => handle_invoice_paid(&state, &event).await,
-        "invoice.payment_failed" => handle_payment_failed(&state, &event).await,
-        "customer.subscription.deleted" => handle_subscription_deleted(&state, &event).await,
-        _ => {
-            println!("[WEBHOOK] ℹ️ Unhandled event type: {}", event.event_type);
-            Ok(())
+    // Archived before enqueueing: a worker may finish the event before this handler resumes,
+    // and its outcome must land on an existing record (redeliveries keep the first one)
+    let first_delivery = state.archive.record(archived).await;
+
+    // Persist before acknowledging; the worker pool does the actual processing
+    let entry = InboxEntry::new(&event, raw_body.to_string(), state.clock.now());
+    match state.inbox.enqueue(entry).await {
+        Ok(true) => {
+            // Never over a final outcome, e.g. a worker that already processed it
+            state.archive.set_outcome(&event.id, ArchiveOutcome::Queued, None, state.clock.now()).await;
+            (StatusCode::OK, "Queued").into_response()
+        }
+        Ok(false) => {
+            println!("[WEBHOOK] ⚡ Event {} already queued", event.id);
+            (StatusCode::OK, "Already queued").into_response()
         }
-    };
-
-    // Mark as processed with result
-    let event_result = match &result {
-        Ok(_) => EventResult::Success {
-            user_id: Uuid::new_v4(),
-            plan: "processed".to_string(),
-        },
-        Err(e) => EventResult::Failed { error: e.clone() },
-    };
-    state
-        .idempotency
-        .mark_processed(event.id, event_result)
-        .await;
-
-    match result {
-        Ok(_) => (StatusCode::OK, "Success").into_response(),
         Err(e) => {
-            println!("[WEBHOOK] ❌ Processing error: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, e).into_response()
+            // Stripe redelivers on non-2xx, so nothing is lost
+            println!("[WEBHOOK] ❌ Failed to persist {}: {}", event.id, e);
+            if first_delivery {
+                state.archive.set_outcome(&event.id, ArchiveOutcome::Failed, Some(e), state.clock.now()).await;
+            }
+            (StatusCode::SERVICE_UNAVAILABLE, "Inbox unavailable").into_response()
         }
     }
 }
 
-// ═══════════════════════════════════════════════════════════════════════════════
-// EVENT HANDLERS
-// ═══════════════════════════════════════════════════════════════════════════════
-
-async fn handle_checkout_completed(
-    state: &StripeWebhookState,
-    event: &StripeEvent,
-) -> Result<(), String> {
-    let session: CheckoutSession = serde_json::from_value(event.data.object.clone())
-        .map_err(|e| format!("Failed to parse session: {}", e))?;
+/// Process a persisted event (called by the inbox workers). `Err` means retry later.
+pub async fn process_event(state: &StripeWebhookState, event: StripeEvent) -> Result<(), String> {
+    if state.idempotency.is_processed(&event.id).await {
+        println!("[WEBHOOK] ⚡ Event {} already processed (idempotent)", event.id);
+        return Ok(());
+    }
 
-    let email = session.customer_email.unwrap_or_default();
-    let plan = session
-        .metadata
-        .as_ref()
-        .and_then(|m| m.get("plan"))
-        .map(|s| s.as_str())
-        .unwrap_or("pro_monthly");
+    // Defense in depth: act only on what the Stripe API itself reports
+    let event = if state.config.verify_by_refetch {
+        refetch_event(state, &event)
+            .await?
+            .ok_or_else(|| format!("Event {} not found via Stripe API", event.id))?
+    } else {
+        event
+    };
 
-    println!(
-        "[CHECKOUT] ✅ Session completed for: {} (Plan: {})",
-        email, plan
-    );
+    dispatch_event(state, &event).await?;
 
-    // Activate subscription
     state
-        .subscriptions
-        .activate_subscription(&email, session.customer, session.subscription, plan)
+        .idempotency
+        .mark_processed(
+            event.id,
+            EventResult::Success {
+                user_id: Uuid::new_v4(),
+                plan: "processed".to_string(),
+            },
+        )
         .await;
-
-    // Log to immutable audit trail
-    log_payment_event(&email, "checkout.completed", session.amount_total);
-
     Ok(())
 }
 
-async fn handle_invoice_paid(
-    state: &StripeWebhookState,
-    event: &StripeEvent,
-) -> Result<(), String> {
-    let customer_email = event
-        .data
-        .object
-        .get("customer_email")
-        .and_then(|v| v.as_str())
-        .unwrap_or("unknown");
-
-    let amount = event
-        .data
-        .object
-        .get("amount_paid")
-        .and_then(|v| v.as_i64())
-        .unwrap_or(0);
+// ═══════════════════════════════════════════════════════════════════════════════
+// EVENT HANDLERS
+// ═══════════════════════════════════════════════════════════════════════════════
 
-    println!(
-        "[INVOICE] 💰 Paid: {} (€{})",
-        customer_email,
-        amount as f64 / 100.0
-    );
+/// API collection and id of an event's object, for object types we act on
+fn object_location(object: &serde_json::Value) -> Option<(&'static str, &str)> {
+    let id = object["id"].as_str()?;
+    let collection = match object["object"].as_str()? {
+        "checkout.session" => "checkout/sessions",
+        "invoice" => "invoices",
+        "subscription" => "subscriptions",
+        "charge" => "charges",
+        "refund" => "refunds",
+        "dispute" => "disputes",
+        "radar.early_fraud_warning" => "radar/early_fraud_warnings",
+        "payment_intent" => "payment_intents",
+        "customer" => "customers",
+        _ => return None,
+    };
+    Some((collection, id))
+}
+
+/// Retrieve the event via `GET /v1/events/{id}` and swap in the latest version of its object.
+/// `Ok(None)` means Stripe does not know the event.
+async fn refetch_event(state: &StripeWebhookState, delivered: &StripeEvent) -> Result<Option<StripeEvent>, String> {
+    let raw = match state
+        .api
+        .get("GET /v1/events/:id", &format!("/v1/events/{}", delivered.id))
+        .await?
+    {
+        Some(raw) => raw,
+        None => return Ok(None),
+    };
+    let mut event: StripeEvent =
+        serde_json::from_value(raw).map_err(|e| format!("Failed to parse refetched event: {}", e))?;
+    if event.id != delivered.id {
+        return Err(format!("Refetched event id {} != {}", event.id, delivered.id));
+    }
 
-    log_payment_event(customer_email, "invoice.paid", Some(amount));
+    if let Some((collection, id)) = object_location(&event.data.object) {
+        let path = format!("/v1/{}/{}", collection, id);
+        let endpoint = format!("GET /v1/{}/:id", collection);
+        match state.api.get(&endpoint, &path).await? {
+            Some(latest) => event.data.object = latest,
+            // Gone upstream (e.g. deleted): the event's own snapshot is all there is
+            None => println!("[WEBHOOK] ℹ️ {} no longer retrievable, using event snapshot", path),
+        }
+    }
 
-    Ok(())
+    println!("[WEBHOOK] 🔁 Processing API copy of {}", event.id);
+    Ok(Some(event))
 }
 
-async fn handle_payment_failed(
-    state: &StripeWebhookState,
-    event: &StripeEvent,
+async fn dispatch_event(state: &StripeWebhookState, event: &StripeEvent) -> Result<(), String> {
+    let kind = StripeEventKind::from_event(event)?;
+
+    match kind {
+        StripeEventKind::CheckoutSessionCompleted(session) => {
+            handle_checkout_completed(state, session, EventStamp::of(event)).await
+        }
+        StripeEventKind::InvoicePaid(invoice) => handle_invoice_paid(state, invoice, &event.id).await,
+        StripeEventKind::InvoicePaymentFailed(invoice) => handle_payment_failed(state, invoice, &event.id).await,
+        StripeEventKind::SubscriptionCreated(subscription)
+        | StripeEventKind::SubscriptionUpdated(subscription)
+        | StripeEventKind::SubscriptionDeleted(subscription) => {
+            handle_subscription_changed(state, subscription, EventStamp::of(event)).await
+        }
+        StripeEventKind::SubscriptionTrialWillEnd(subscription) => {
+            handle_trial_will_end(state, subscription, EventStamp::of(event)).await
+        }
+        StripeEventKind::ChargeRefunded(charge) => handle_charge_refunded(state, charge, EventStamp::of(event)).await,
+        StripeEventKind::RefundUpdated(refund) => handle_refund_updated(state, refund).await,
+        StripeEventKind::DisputeCreated(dispute)
+        | StripeEventKind::DisputeUpdated(dispute)
+        | StripeEventKind::DisputeClosed(dispute) => {
+            stripe_disputes::handle_dispute_event(state, dispute, EventStamp::of(event)).await
+        }
+        StripeEventKind::EarlyFraudWarningCreated(warning) => {
+            stripe_disputes::handle_early_fraud_warning(state, warning).await
+        }
+        StripeEventKind::CustomerCreated(customer) | StripeEventKind::CustomerUpdated(customer) => {
+            // Emails go out in the customer's language
+            if let Some(email) = &customer.email {
+                state.notifier.set_locale(email, &customer.preferred_locales).await;
+            }
+            Ok(())
+        }
+        StripeEventKind::SourceExpiring(card) => handle_card_expiring(state, card).await,
+        StripeEventKind::Unknown(event_type) => {
+            println!("[WEBHOOK] ℹ️ Unhandled event type: {}", event_type);
+            Ok(())
+        }
+        other => {
+            println!(
+                "[WEBHOOK] ℹ️ No action for {} on {} (customer: {})",
+                event.event_type,
+                other.object_id().unwrap_or("-"),
+                other.customer_id().unwrap_or("-")
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Our subscription for the customer behind a charge (refunds and disputes only name the
+/// charge). `receipt_email` is whatever the payer typed, so it never identifies an account.
+pub async fn charge_subscription(
+    state: &StripeWebhookState,
+    charge_id: &str,
+) -> Result<Option<UserSubscription>, String> {
+    let charge: Option<Charge> = state
+        .api
+        .get("GET /v1/charges/:id", &format!("/v1/charges/{}", charge_id))
+        .await?
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(|e| format!("Failed to parse charge {}: {}", charge_id, e))?;
+
+    Ok(match charge.and_then(|c| c.customer) {
+        Some(customer) => state.subscriptions.get_by_customer_id(&customer).await,
+        None => None,
+    })
+}
+
+/// Whether `charge` paid an invoice of the subscription we track (Stripe lists the invoice's
+/// subscription, not the charge's)
+async fn pays_for_subscription(
+    state: &StripeWebhookState,
+    charge: &Charge,
+    subscription: &UserSubscription,
+) -> Result<bool, String> {
+    let (Some(invoice_id), Some(subscription_id)) = (&charge.invoice, &subscription.stripe_subscription_id) else {
+        return Ok(false);
+    };
+    let invoice: Option<Invoice> = state
+        .api
+        .get("GET /v1/invoices/:id", &format!("/v1/invoices/{}", invoice_id))
+        .await?
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(|e| format!("Failed to parse invoice {}: {}", invoice_id, e))?;
+
+    Ok(invoice.and_then(|i| i.subscription).as_deref() == Some(subscription_id.as_str()))
+}
+
+/// Email for a Stripe customer: the key we stored at checkout, else (for customers we don't
+/// track) the one on the object. The object's email changes when the customer edits it in
+/// the Billing Portal, and would no longer find their subscription.
+async fn resolve_email(
+    state: &StripeWebhookState,
+    email: Option<&str>,
+    customer_id: &str,
+) -> Result<String, String> {
+    if let Some(sub) = state.subscriptions.get_by_customer_id(customer_id).await {
+        return Ok(sub.email);
+    }
+
+    email
+        .filter(|e| !e.is_empty())
+        .map(|e| e.to_string())
+        .ok_or_else(|| format!("No email for customer {}", customer_id))
+}
+
+async fn handle_checkout_completed(
+    state: &StripeWebhookState,
+    session: CheckoutSession,
+    stamp: EventStamp,
+) -> Result<(), String> {
+    let email = session
+        .customer_email
+        .or_else(|| session.customer_details.and_then(|d| d.email))
+        .ok_or_else(|| format!("Checkout session {} has no customer email", session.id))?;
+    // Sessions created by /stripe/checkout carry our user id
+    let user_id = session
+        .client_reference_id
+        .as_deref()
+        .and_then(|id| Uuid::parse_str(id).ok());
+    let plan = session
+        .metadata
+        .as_ref()
+        .and_then(|m| m.get("plan"))
+        .map(|s| s.as_str())
+        .unwrap_or("pro_monthly");
+
+    println!(
+        "[CHECKOUT] ✅ Session completed for: {} (Plan: {})",
+        email, plan
+    );
+
+    // Activate subscription
+    let source = stamp.id.clone();
+    let subscription = state
+        .subscriptions
+        .activate_subscription(user_id, &email, session.customer, session.subscription, plan, stamp)
+        .await;
+
+    // Log to immutable audit trail
+    log_payment_event(&email, "checkout.completed", session.amount_total, session.currency.as_deref());
+
+    let now = state.clock.now();
+    state.events.publish(DomainEvent::new(
+        DomainEventType::SubscriptionActivated,
+        Some(&source),
+        events::subscription_data(&subscription),
+        now,
+    )).await;
+    // Subscriptions are paid through invoices (`invoice.paid`); one-off purchases right here
+    if session.mode == "payment" {
+        state.events.publish(DomainEvent::new(
+            DomainEventType::PaymentSucceeded,
+            Some(&source),
+            serde_json::json!({
+                "email": email,
+                "user_id": subscription.user_id,
+                "amount": session.amount_total,
+                "currency": session.currency,
+                "checkout_session_id": session.id,
+            }),
+            now,
+        )).await;
+    }
+
+    Ok(())
+}
+
+/// Invoice fields our services care about
+fn invoice_data(email: &str, invoice: &Invoice, amount: i64) -> serde_json::Value {
+    serde_json::json!({
+        "email": email,
+        "invoice_id": invoice.id,
+        "subscription_id": invoice.subscription,
+        "customer_id": invoice.customer,
+        "amount": amount,
+        "currency": invoice.currency,
+        "attempt_count": invoice.attempt_count,
+        "hosted_invoice_url": invoice.hosted_invoice_url,
+    })
+}
+
+async fn handle_invoice_paid(
+    state: &StripeWebhookState,
+    invoice: Invoice,
+    source: &str,
+) -> Result<(), String> {
+    let customer_email = resolve_email(state, invoice.customer_email.as_deref(), &invoice.customer).await?;
+
+    println!(
+        "[INVOICE] 💰 Paid: {} ({})",
+        customer_email,
+        format_amount(invoice.amount_paid, &invoice.currency)
+    );
+
+    log_payment_event(&customer_email, "invoice.paid", Some(invoice.amount_paid), Some(&invoice.currency));
+    state.events.publish(DomainEvent::new(
+        DomainEventType::PaymentSucceeded,
+        Some(source),
+        invoice_data(&customer_email, &invoice, invoice.amount_paid),
+        state.clock.now(),
+    )).await;
+    // Nothing to confirm for $0 invoices (trial starts, fully discounted periods)
+    if invoice.amount_paid > 0 {
+        state.notifier.send(Notification::receipt(&customer_email, &invoice)).await;
+    }
+    dunning::handle_invoice_paid(state, &customer_email, &invoice, source).await;
+
+    Ok(())
+}
+
+async fn handle_payment_failed(
+    state: &StripeWebhookState,
+    invoice: Invoice,
+    source: &str,
 ) -> Result<(), String> {
-    let customer_email = event
-        .data
-        .object
-        .get("customer_email")
-        .and_then(|v| v.as_str())
-        .unwrap_or("unknown");
+    let customer_email = resolve_email(state, invoice.customer_email.as_deref(), &invoice.customer).await?;
 
     println!("[PAYMENT] ❌ Failed for: {}", customer_email);
 
-    // TODO: Send notification email, retry logic, etc.
-    log_payment_event(customer_email, "payment.failed", None);
+    log_payment_event(&customer_email, "payment.failed", Some(invoice.amount_due), Some(&invoice.currency));
+    state.events.publish(DomainEvent::new(
+        DomainEventType::PaymentFailed,
+        Some(source),
+        invoice_data(&customer_email, &invoice, invoice.amount_due),
+        state.clock.now(),
+    )).await;
+    dunning::handle_payment_failed(state, &customer_email, &invoice, source).await;
+
+    Ok(())
+}
+
+async fn handle_subscription_changed(
+    state: &StripeWebhookState,
+    subscription: Subscription,
+    stamp: EventStamp,
+) -> Result<(), String> {
+    let source = stamp.id.clone();
+    let was_canceled = state
+        .subscriptions
+        .get_by_stripe_subscription_id(&subscription.id)
+        .await
+        .is_some_and(|s| s.status == SubscriptionStatus::Canceled);
+    let outcome = state
+        .subscriptions
+        .apply_subscription_event(&subscription, stamp)
+        .await;
+    if outcome != ApplyOutcome::Applied {
+        return Ok(());
+    }
+
+    let Some(local) = state.subscriptions.get_by_stripe_subscription_id(&subscription.id).await else {
+        return Ok(());
+    };
+    let event_type = if local.status == SubscriptionStatus::Canceled {
+        if subscription.status == "canceled" {
+            log_payment_event(&local.email, "subscription.deleted", None, None);
+        }
+        if !was_canceled {
+            state.notifier.send(Notification::subscription_canceled(&local)).await;
+        }
+        DomainEventType::SubscriptionCanceled
+    } else {
+        DomainEventType::SubscriptionUpdated
+    };
+    state.events.publish(DomainEvent::new(
+        event_type,
+        Some(&source),
+        events::subscription_data(&local),
+        state.clock.now(),
+    )).await;
 
     Ok(())
 }
 
-async fn handle_subscription_deleted(
+/// Sent by Stripe three days before a trial ends (or immediately for shorter trials)
+async fn handle_trial_will_end(
     state: &StripeWebhookState,
-    event: &StripeEvent,
+    subscription: Subscription,
+    stamp: EventStamp,
 ) -> Result<(), String> {
-    let customer_email = event
-        .data
-        .object
-        .get("customer_email")
-        .and_then(|v| v.as_str());
+    let stamp_id = stamp.id.clone();
+    state
+        .subscriptions
+        .apply_subscription_event(&subscription, stamp)
+        .await;
+
+    let Some(local) = state.subscriptions.get_by_stripe_subscription_id(&subscription.id).await else {
+        println!("[TRIAL] ℹ️ Trial ending for untracked subscription {}", subscription.id);
+        return Ok(());
+    };
 
-    if let Some(email) = customer_email {
-        state.subscriptions.cancel_subscription(email).await;
-        log_payment_event(email, "subscription.deleted", None);
+    let ends = local.trial_end.map(|end| end.to_rfc3339()).unwrap_or_else(|| "-".into());
+    let missing_behavior = subscription
+        .trial_settings
+        .as_ref()
+        .map(|t| t.end_behavior.missing_payment_method.as_str())
+        .unwrap_or("create_invoice");
+
+    let on_file = if local.payment_method_on_file { "yes" } else { "no" };
+    metrics().inc_counter("trial_reminders_total", &[("payment_method", on_file)]);
+    if local.payment_method_on_file {
+        println!("[TRIAL] 📧 Reminder to {}: trial ends {}, then billing starts", local.email, ends);
+    } else {
+        println!(
+            "[TRIAL] 📧 Reminder to {}: trial ends {}, no payment method yet (otherwise: {})",
+            local.email, ends, missing_behavior
+        );
+    }
+    state.notifier.send(Notification::trial_ending(&local)).await;
+    log_payment_event(&local.email, "trial.will_end", None, None);
+    state.events.publish(DomainEvent::new(
+        DomainEventType::SubscriptionTrialWillEnd,
+        Some(&stamp_id),
+        events::subscription_data(&local),
+        state.clock.now(),
+    )).await;
+
+    Ok(())
+}
+
+/// Sent by Stripe at the start of the month a saved card expires
+async fn handle_card_expiring(state: &StripeWebhookState, card: Card) -> Result<(), String> {
+    let Some(customer) = card.customer.as_deref() else {
+        return Ok(());
+    };
+    let Some(email) = state.subscriptions.get_by_customer_id(customer).await.map(|s| s.email) else {
+        println!("[CARD] ℹ️ Card {} expiring for untracked customer {}", card.id, customer);
+        return Ok(());
+    };
+
+    println!(
+        "[CARD] 📧 {} card ending in {} of {} expires {:02}/{}",
+        card.brand.as_deref().unwrap_or("Unknown"),
+        card.last4,
+        email,
+        card.exp_month,
+        card.exp_year
+    );
+    state.notifier.send(Notification::card_expiring(&email, &card)).await;
+
+    Ok(())
+}
+
+async fn handle_charge_refunded(
+    state: &StripeWebhookState,
+    charge: Charge,
+    stamp: EventStamp,
+) -> Result<(), String> {
+    let fully_refunded = charge.refunded || charge.amount_refunded >= charge.amount;
+    let refunded = format_amount(charge.amount_refunded, &charge.currency);
+
+    // Only the charge's customer says whose entitlement it paid for; `receipt_email` is
+    // whatever the payer typed. One-off charges without a customer have nothing to revoke.
+    let subscription = match &charge.customer {
+        Some(customer) => state.subscriptions.get_by_customer_id(customer).await,
+        None => None,
+    };
+    let Some(subscription) = subscription else {
+        println!("[REFUND] 💸 {} refunded on {} (no known customer)", refunded, charge.id);
+        return Ok(());
+    };
+    let email = subscription.email.clone();
+
+    println!(
+        "[REFUND] 💸 {} refunded to {} on {} ({})",
+        refunded,
+        email,
+        charge.id,
+        if fully_refunded { "full" } else { "partial" }
+    );
+
+    let event_type = if fully_refunded { "charge.refunded" } else { "charge.refunded.partial" };
+    log_payment_event(&email, event_type, Some(charge.amount_refunded), Some(&charge.currency));
+    let source = stamp.id.clone();
+    state.events.publish(DomainEvent::new(
+        DomainEventType::PaymentRefunded,
+        Some(&source),
+        serde_json::json!({
+            "email": email,
+            "charge_id": charge.id,
+            "amount_refunded": charge.amount_refunded,
+            "currency": charge.currency,
+            "full": fully_refunded,
+        }),
+        state.clock.now(),
+    )).await;
+
+    if !state.config.refund_policy.should_revoke(fully_refunded) {
+        return Ok(());
+    }
+    // A refunded one-off purchase, or another subscription's invoice, paid for something else
+    if !pays_for_subscription(state, &charge, &subscription).await? {
+        println!("[REFUND] ℹ️ {} did not pay for {}'s subscription, access kept", charge.id, email);
+        return Ok(());
+    }
+    if state.subscriptions.revoke_subscription(&email, stamp).await {
+        events::publish_subscription(state, DomainEventType::SubscriptionCanceled, &email, Some(&source)).await;
+        if let Some(sub) = state.subscriptions.get_by_email(&email).await {
+            state.notifier.send(Notification::subscription_canceled(&sub)).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Refund status changes after creation, most importantly `failed` / `canceled`
+async fn handle_refund_updated(state: &StripeWebhookState, refund: Refund) -> Result<(), String> {
+    let status = refund.status.as_deref().unwrap_or("unknown");
+
+    let email = match &refund.charge {
+        Some(charge_id) => charge_subscription(state, charge_id).await?.map(|s| s.email),
+        None => None,
+    }
+    .unwrap_or_else(|| "-".to_string());
+
+    println!(
+        "[REFUND] 🔄 Refund {} for {} is {} ({})",
+        refund.id,
+        email,
+        status,
+        format_amount(refund.amount, &refund.currency)
+    );
+
+    log_payment_event(
+        &email,
+        &format!("refund.{}", status),
+        Some(refund.amount),
+        Some(&refund.currency),
+    );
+
+    if matches!(status, "failed" | "canceled") {
+        // The money never reached the customer; restoring access is a judgement call
+        println!(
+            "[REFUND] ⚠️ Refund {} {}: access revoked by it is NOT restored automatically",
+            refund.id, status
+        );
     }
 
     Ok(())
@@ -499,12 +1662,13 @@ async fn handle_subscription_deleted(
 // IMMUTABLE AUDIT LOG
 // ═══════════════════════════════════════════════════════════════════════════════
 
-fn log_payment_event(email: &str, event_type: &str, amount: Option<i64>) {
+pub fn log_payment_event(email: &str, event_type: &str, amount: Option<i64>, currency: Option<&str>) {
     let log_entry = serde_json::json!({
         "timestamp": Utc::now().to_rfc3339(),
         "event": event_type,
         "email": email,
         "amount_cents": amount,
+        "currency": currency,
         "veritas_hash": format!("0x4121:{:x}", rand::random::<u64>()),
     });
 
@@ -516,25 +1680,1105 @@ fn log_payment_event(email: &str, event_type: &str, amount: Option<i64>) {
 // CUSTOMER PORTAL
 // ═══════════════════════════════════════════════════════════════════════════════
 
+#[derive(Deserialize, Default)]
+pub struct PortalSessionRequest {
+    /// Optional; must match the caller's linked customer when given
+    pub customer_id: Option<String>,
+}
+
 #[derive(Serialize)]
 pub struct PortalSessionResponse {
     pub url: String,
 }
 
-/// Create Stripe Customer Portal session
+/// Create Stripe Customer Portal session for the caller's own customer
 pub async fn create_portal_session(
     State(state): State<Arc<StripeWebhookState>>,
-    Json(payload): Json<serde_json::Value>,
+    user: AuthUser,
+    payload: Option<Json<PortalSessionRequest>>,
 ) -> impl IntoResponse {
-    let customer_id = payload["customer_id"].as_str().unwrap_or("");
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
 
-    // In production: Call Stripe API to create portal session
-    let portal_url = format!(
-        "https://billing.stripe.com/p/session/test_portal_{}",
-        customer_id
-    );
+    let customer_id = match state
+        .subscriptions
+        .get_by_user_id(user.user_id)
+        .await
+        .and_then(|s| s.stripe_customer_id)
+    {
+        Some(id) => id,
+        None => return (StatusCode::NOT_FOUND, "No billing account for user").into_response(),
+    };
+
+    if let Some(requested) = payload.customer_id.as_deref().map(str::trim) {
+        if !requested.is_empty() && requested != customer_id {
+            println!(
+                "[PORTAL] ⛔ User {} requested portal for foreign customer {}",
+                user.user_id, requested
+            );
+            return (StatusCode::FORBIDDEN, "Customer does not belong to caller").into_response();
+        }
+    }
+
+    let mut params = vec![("customer".to_string(), customer_id.clone())];
+    if let Some(return_url) = &state.config.portal_return_url {
+        params.push(("return_url".to_string(), return_url.clone()));
+    }
+    if let Some(configuration) = &state.config.portal_configuration_id {
+        params.push(("configuration".to_string(), configuration.clone()));
+    }
+
+    let session = match state
+        .api
+        .post_form("POST /v1/billing_portal/sessions", "/v1/billing_portal/sessions", &params, None)
+        .await
+    {
+        Ok(session) => session,
+        Err(e) => {
+            println!("[PORTAL] ❌ Failed to create portal session for {}: {}", customer_id, e);
+            return (StatusCode::BAD_GATEWAY, "Failed to create portal session").into_response();
+        }
+    };
+
+    let portal_url = match session["url"].as_str() {
+        Some(url) => url.to_string(),
+        None => return (StatusCode::BAD_GATEWAY, "Portal session has no url").into_response(),
+    };
 
     println!("[PORTAL] 🔗 Created portal session for: {}", customer_id);
 
-    Json(PortalSessionResponse { url: portal_url })
+    Json(PortalSessionResponse { url: portal_url }).into_response()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// CHECKOUT
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Deserialize)]
+pub struct CheckoutRequest {
+    /// Optional; must match the authenticated user when given
+    pub user_id: Option<Uuid>,
+    pub plan: String,
+    pub customer_email: Option<String>,
+    pub trial_days: Option<u32>,
+    /// `false` starts a card-less trial that converts once a payment method is added
+    /// and is canceled by Stripe at `trial_end` otherwise
+    #[serde(default = "default_true")]
+    pub require_payment_method: bool,
+    #[serde(default)]
+    pub allow_promotion_codes: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Serialize)]
+pub struct CheckoutResponse {
+    pub session_id: String,
+    pub url: String,
+}
+
+/// Create a Stripe Checkout session for a catalog plan
+pub async fn create_checkout_session(
+    State(state): State<Arc<StripeWebhookState>>,
+    user: AuthUser,
+    Json(payload): Json<CheckoutRequest>,
+) -> impl IntoResponse {
+    if payload.user_id.is_some_and(|id| id != user.user_id) {
+        return (StatusCode::FORBIDDEN, "user_id does not match caller").into_response();
+    }
+
+    let entry = match state.config.catalog.get(&payload.plan) {
+        Some(entry) => entry.clone(),
+        None => return (StatusCode::BAD_REQUEST, "Unknown plan").into_response(),
+    };
+
+    let (success_url, cancel_url) = match (
+        &state.config.checkout_success_url,
+        &state.config.checkout_cancel_url,
+    ) {
+        (Some(success), Some(cancel)) => (success.clone(), cancel.clone()),
+        _ => {
+            println!("[CHECKOUT] ❌ STRIPE_CHECKOUT_SUCCESS_URL / STRIPE_CHECKOUT_CANCEL_URL not set");
+            return (StatusCode::SERVICE_UNAVAILABLE, "Checkout not configured").into_response();
+        }
+    };
+
+    if let Some(days) = payload.trial_days {
+        if entry.mode != CheckoutMode::Subscription {
+            return (StatusCode::BAD_REQUEST, "Trials are only available for subscriptions").into_response();
+        }
+        if !(1..=730).contains(&days) {
+            return (StatusCode::BAD_REQUEST, "trial_days must be between 1 and 730").into_response();
+        }
+    } else if !payload.require_payment_method {
+        return (StatusCode::BAD_REQUEST, "Card-less checkout requires trial_days").into_response();
+    }
+
+    let user_id = user.user_id.to_string();
+    let mode = match entry.mode {
+        CheckoutMode::Subscription => "subscription",
+        CheckoutMode::Payment => "payment",
+    };
+
+    let mut params: Vec<(String, String)> = vec![
+        ("mode".into(), mode.into()),
+        ("line_items[0][price]".into(), entry.price_id.clone()),
+        ("line_items[0][quantity]".into(), "1".into()),
+        ("client_reference_id".into(), user_id.clone()),
+        ("success_url".into(), success_url),
+        ("cancel_url".into(), cancel_url),
+        ("metadata[plan]".into(), payload.plan.clone()),
+        ("metadata[user_id]".into(), user_id.clone()),
+    ];
+
+    // Reuse the Stripe customer we already know for this user
+    let existing_customer = state
+        .subscriptions
+        .get_by_user_id(user.user_id)
+        .await
+        .and_then(|s| s.stripe_customer_id);
+    match (existing_customer, &payload.customer_email) {
+        (Some(customer), _) => params.push(("customer".into(), customer)),
+        (None, Some(email)) => params.push(("customer_email".into(), email.clone())),
+        (None, None) => {}
+    }
+
+    match entry.mode {
+        CheckoutMode::Subscription => {
+            params.push(("subscription_data[metadata][plan]".into(), payload.plan.clone()));
+            params.push(("subscription_data[metadata][user_id]".into(), user_id.clone()));
+            if let Some(days) = payload.trial_days {
+                params.push(("subscription_data[trial_period_days]".into(), days.to_string()));
+            }
+            if !payload.require_payment_method {
+                params.push(("payment_method_collection".into(), "if_required".into()));
+                params.push((
+                    "subscription_data[trial_settings][end_behavior][missing_payment_method]".into(),
+                    "cancel".into(),
+                ));
+            }
+        }
+        CheckoutMode::Payment => {
+            params.push(("payment_intent_data[metadata][plan]".into(), payload.plan.clone()));
+            params.push(("payment_intent_data[metadata][user_id]".into(), user_id.clone()));
+        }
+    }
+
+    if payload.allow_promotion_codes {
+        params.push(("allow_promotion_codes".into(), "true".into()));
+    }
+
+    let session = match state
+        .api
+        .post_form("POST /v1/checkout/sessions", "/v1/checkout/sessions", &params, None)
+        .await
+    {
+        Ok(session) => session,
+        Err(e) => {
+            println!("[CHECKOUT] ❌ Failed to create session for {}: {}", user_id, e);
+            return (StatusCode::BAD_GATEWAY, "Failed to create checkout session").into_response();
+        }
+    };
+
+    let (Some(session_id), Some(url)) = (session["id"].as_str(), session["url"].as_str()) else {
+        return (StatusCode::BAD_GATEWAY, "Checkout session has no url").into_response();
+    };
+
+    println!(
+        "[CHECKOUT] 🛒 Created session {} for {} (Plan: {})",
+        session_id, user_id, payload.plan
+    );
+
+    Json(CheckoutResponse {
+        session_id: session_id.to_string(),
+        url: url.to_string(),
+    })
+    .into_response()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// ENTITLEMENTS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Serialize)]
+pub struct SubscriptionSummary {
+    pub plan: SubscriptionPlan,
+    pub status: SubscriptionStatus,
+    pub entitled: bool,
+    pub trial_end: Option<DateTime<Utc>>,
+    pub trial_days_left: Option<i64>,
+    pub payment_method_on_file: bool,
+    pub current_period_end: Option<DateTime<Utc>>,
+}
+
+/// The caller's plan and whether it currently grants access
+pub async fn get_subscription(State(state): State<Arc<StripeWebhookState>>, user: AuthUser) -> impl IntoResponse {
+    let Some(sub) = state.subscriptions.get_by_user_id(user.user_id).await else {
+        return (StatusCode::NOT_FOUND, "No subscription for user").into_response();
+    };
+    let now = state.clock.now();
+
+    Json(SubscriptionSummary {
+        entitled: sub.is_entitled(now),
+        trial_days_left: sub.trial_days_left(now),
+        plan: sub.plan,
+        status: sub.status,
+        trial_end: sub.trial_end,
+        payment_method_on_file: sub.payment_method_on_file,
+        current_period_end: sub.current_period_end,
+    })
+    .into_response()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// REFUNDS (ADMIN)
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Deserialize)]
+pub struct RefundRequest {
+    /// Exactly one of `charge_id` / `payment_intent_id`
+    pub charge_id: Option<String>,
+    pub payment_intent_id: Option<String>,
+    /// Amount in the smallest currency unit; omitted refunds the remaining balance
+    pub amount: Option<i64>,
+    /// `duplicate`, `fraudulent` or `requested_by_customer`
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct RefundResponse {
+    pub refund_id: String,
+    pub status: Option<String>,
+    pub amount: i64,
+    pub currency: String,
+}
+
+/// Issue a refund through the Stripe API. The caller's `Idempotency-Key` is passed on
+/// to Stripe so a retried request never refunds twice.
+pub async fn create_refund(
+    State(state): State<Arc<StripeWebhookState>>,
+    headers: HeaderMap,
+    Json(payload): Json<RefundRequest>,
+) -> impl IntoResponse {
+    if let Err(e) = state.auth.require_admin(&headers) {
+        return e.into_response();
+    }
+
+    let idempotency_key = match headers.get("idempotency-key").and_then(|v| v.to_str().ok()) {
+        Some(key) if !key.trim().is_empty() => key.trim().to_string(),
+        _ => return (StatusCode::BAD_REQUEST, "Idempotency-Key header required").into_response(),
+    };
+
+    let mut params: Vec<(String, String)> = match (&payload.charge_id, &payload.payment_intent_id) {
+        (Some(charge), None) => vec![("charge".into(), charge.clone())],
+        (None, Some(intent)) => vec![("payment_intent".into(), intent.clone())],
+        _ => {
+            return (StatusCode::BAD_REQUEST, "Give exactly one of charge_id or payment_intent_id")
+                .into_response()
+        }
+    };
+
+    if let Some(amount) = payload.amount {
+        if amount <= 0 {
+            return (StatusCode::BAD_REQUEST, "amount must be positive").into_response();
+        }
+        params.push(("amount".into(), amount.to_string()));
+    }
+    if let Some(reason) = &payload.reason {
+        if !matches!(reason.as_str(), "duplicate" | "fraudulent" | "requested_by_customer") {
+            return (StatusCode::BAD_REQUEST, "Unknown refund reason").into_response();
+        }
+        params.push(("reason".into(), reason.clone()));
+    }
+    params.push(("metadata[source]".into(), "admin_api".into()));
+
+    let refund: Refund = match state
+        .api
+        .post_form("POST /v1/refunds", "/v1/refunds", &params, Some(&idempotency_key))
+        .await
+        .and_then(|v| serde_json::from_value(v).map_err(|e| format!("Failed to parse refund: {}", e)))
+    {
+        Ok(refund) => refund,
+        Err(e) => {
+            println!("[REFUND] ❌ Refund failed ({}): {}", idempotency_key, e);
+            return (StatusCode::BAD_GATEWAY, e).into_response();
+        }
+    };
+
+    println!(
+        "[REFUND] 🧾 Issued {} for {} on {}",
+        refund.id,
+        format_amount(refund.amount, &refund.currency),
+        refund.charge.as_deref().or(refund.payment_intent.as_deref()).unwrap_or("-")
+    );
+
+    Json(RefundResponse {
+        refund_id: refund.id,
+        status: refund.status,
+        amount: refund.amount,
+        currency: refund.currency,
+    })
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use axum::Router;
+    use chrono::TimeZone;
+    use proptest::prelude::*;
+
+    const TOLERANCE: Duration = Duration::from_secs(300);
+
+    fn secret(label: &str, value: &str) -> WebhookSecret {
+        WebhookSecret {
+            label: label.to_string(),
+            secret: value.to_string(),
+            expires_at: None,
+        }
+    }
+
+    fn sign(payload: &[u8], secret: &str, timestamp: i64) -> String {
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(format!("{}.", timestamp).as_bytes());
+        mac.update(payload);
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    fn at(timestamp: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(timestamp, 0).unwrap()
+    }
+
+    #[test]
+    fn verifies_non_utf8_payload_byte_exact() {
+        let payload = b"{\"name\":\"\xff\xfe\"}";
+        let header = format!("t=1700000000,v1={}", sign(payload, "whsec_a", 1_700_000_000));
+        let secrets = [secret("#0", "whsec_a")];
+
+        let result = verify_webhook_signature(payload, &header, &secrets, TOLERANCE, at(1_700_000_000));
+        assert_eq!(result, Ok("#0".to_string()));
+    }
+
+    #[test]
+    fn accepts_any_v1_and_reports_matching_secret() {
+        let payload = b"{}";
+        let ts = 1_700_000_000;
+        let header = format!(
+            "t={},v1={},v1={},v0=deadbeef",
+            ts,
+            sign(payload, "whsec_unknown", ts),
+            sign(payload, "whsec_old", ts)
+        );
+        let secrets = [secret("#0", "whsec_new"), secret("#1", "whsec_old")];
+
+        let result = verify_webhook_signature(payload, &header, &secrets, TOLERANCE, at(ts));
+        assert_eq!(result, Ok("#1".to_string()));
+    }
+
+    #[test]
+    fn rejects_expired_secret() {
+        let payload = b"{}";
+        let ts = 1_700_000_000;
+        let header = format!("t={},v1={}", ts, sign(payload, "whsec_old", ts));
+        let mut old = secret("#0", "whsec_old");
+        old.expires_at = Some(at(ts - 1));
+
+        assert!(verify_webhook_signature(payload, &header, &[old], TOLERANCE, at(ts)).is_err());
+    }
+
+    #[test]
+    fn enforces_configured_tolerance() {
+        let payload = b"{}";
+        let ts = 1_700_000_000;
+        let header = format!("t={},v1={}", ts, sign(payload, "whsec_a", ts));
+        let secrets = [secret("#0", "whsec_a")];
+        let tolerance = Duration::from_secs(60);
+
+        assert!(verify_webhook_signature(payload, &header, &secrets, tolerance, at(ts + 60)).is_ok());
+        assert!(verify_webhook_signature(payload, &header, &secrets, tolerance, at(ts + 61)).is_err());
+        assert!(verify_webhook_signature(payload, &header, &secrets, tolerance, at(ts - 61)).is_err());
+    }
+
+    #[tokio::test]
+    async fn handler_uses_injected_clock() {
+        let ts = 1_600_000_000;
+        let clock = FixedClock::at(at(ts));
+        let mut state = StripeWebhookState::new(crate::test_support::authenticator());
+        state.config.webhook_secrets = vec![secret("#0", "whsec_test")];
+        state.config.webhook_tolerance = TOLERANCE;
+        state.clock = clock.clone();
+        let state = Arc::new(state);
+
+        let body = br#"{"id":"evt_1","type":"ping","created":1600000000,"livemode":false,"data":{"object":{}}}"#;
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "stripe-signature",
+            format!("t={},v1={}", ts, sign(body, "whsec_test", ts)).parse().unwrap(),
+        );
+
+        let resp = stripe_webhook_handler(State(state.clone()), headers.clone(), Bytes::from_static(body))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        clock.set(at(ts + 3600));
+        let resp = stripe_webhook_handler(State(state), headers, Bytes::from_static(body))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    fn test_state(secret_key: &str) -> StripeWebhookState {
+        let mut state = StripeWebhookState::new(crate::test_support::authenticator());
+        state.config.secret_key = secret_key.to_string();
+        state.config.webhook_secrets = vec![secret("#0", "whsec_test")];
+        state
+    }
+
+    #[tokio::test]
+    async fn rejects_event_with_mismatched_livemode() {
+        let state = Arc::new(test_state("sk_live_abc"));
+        let ts = Utc::now().timestamp();
+        let body = br#"{"id":"evt_2","type":"ping","created":1600000000,"livemode":false,"data":{"object":{}}}"#;
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "stripe-signature",
+            format!("t={},v1={}", ts, sign(body, "whsec_test", ts)).parse().unwrap(),
+        );
+
+        let resp = stripe_webhook_handler(State(state), headers, Bytes::from_static(body))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    fn refetch_state(api_base: String) -> StripeWebhookState {
+        let mut state = test_state("sk_test_mock");
+        state.config.verify_by_refetch = true;
+        state.api = StripeApiClient::new("sk_test_mock".to_string(), api_base.clone(), api_base);
+        state
+    }
+
+    fn subscription_event(id: &str, status: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "type": "customer.subscription.updated",
+            "created": 1_700_000_000,
+            "livemode": false,
+            "data": { "object": {
+                "id": "sub_1", "object": "subscription", "customer": "cus_1", "status": status
+            }}
+        })
+    }
+
+    #[tokio::test]
+    async fn refetch_uses_api_copy_and_latest_object() {
+        use axum::routing::get;
+
+        let mock = Router::new()
+            .route("/v1/events/evt_sub", get(|| async { Json(subscription_event("evt_sub", "active")) }))
+            .route(
+                "/v1/subscriptions/sub_1",
+                get(|| async {
+                    Json(serde_json::json!({
+                        "id": "sub_1", "object": "subscription", "customer": "cus_1", "status": "canceled"
+                    }))
+                }),
+            );
+        let state = refetch_state(crate::test_support::spawn_mock(mock).await);
+
+        // Delivered payload claims something else entirely
+        let delivered: StripeEvent = serde_json::from_value(subscription_event("evt_sub", "trialing")).unwrap();
+        let fresh = refetch_event(&state, &delivered).await.unwrap().unwrap();
+
+        assert_eq!(fresh.data.object["status"], "canceled");
+    }
+
+    #[tokio::test]
+    async fn refetch_rejects_event_unknown_to_stripe() {
+        let state = refetch_state(crate::test_support::spawn_mock(Router::new()).await);
+        let forged: StripeEvent = serde_json::from_value(subscription_event("evt_forged", "active")).unwrap();
+
+        let err = process_event(&state, forged).await.unwrap_err();
+        assert!(err.contains("not found via Stripe API"));
+        assert!(!state.idempotency.is_processed("evt_forged").await);
+    }
+
+    #[tokio::test]
+    async fn webhook_is_queued_and_acknowledged_before_processing() {
+        let state = Arc::new(test_state("sk_test_mock"));
+        let body = serde_json::to_vec(&subscription_event("evt_queued", "active")).unwrap();
+        let ts = Utc::now().timestamp();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "stripe-signature",
+            format!("t={},v1={}", ts, sign(&body, "whsec_test", ts)).parse().unwrap(),
+        );
+
+        let delivered = body.clone();
+        let resp = stripe_webhook_handler(State(state.clone()), headers.clone(), Bytes::from(body.clone()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let redelivery = stripe_webhook_handler(State(state.clone()), headers, Bytes::from(body))
+            .await
+            .into_response();
+        assert_eq!(redelivery.status(), StatusCode::OK);
+
+        let entry = state.inbox.claim_next(Utc::now(), "w", Duration::from_secs(60)).await.unwrap().unwrap();
+        assert_eq!(entry.event_id, "evt_queued");
+        assert_eq!(entry.ordering_key, "customer:cus_1");
+        assert!(!state.idempotency.is_processed("evt_queued").await);
+
+        // The raw delivery is archived once, headers included
+        let archived = state.archive.get("evt_queued").await.unwrap();
+        assert_eq!(archived.outcome, ArchiveOutcome::Queued);
+        assert_eq!(archived.body, entry.payload);
+        assert_eq!(entry.payload.as_bytes(), delivered.as_slice());
+        assert!(archived.headers.contains_key("stripe-signature"));
+    }
+
+    #[tokio::test]
+    async fn signed_body_that_is_not_utf8_is_rejected_not_mangled() {
+        let state = Arc::new(test_state("sk_test_mock"));
+        let mut body = serde_json::to_vec(&subscription_event("evt_latin1", "active")).unwrap();
+        let at = body.windows(6).position(|w| w == b"active").unwrap();
+        body[at] = 0xE1;
+        let ts = Utc::now().timestamp();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "stripe-signature",
+            format!("t={},v1={}", ts, sign(&body, "whsec_test", ts)).parse().unwrap(),
+        );
+
+        let resp = stripe_webhook_handler(State(state.clone()), headers, Bytes::from(body)).await.into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert!(state.inbox.claim_next(Utc::now(), "w", Duration::from_secs(60)).await.unwrap().is_none());
+        assert!(state.archive.get("evt_latin1").await.is_none());
+    }
+
+    fn event(id: &str, event_type: &str, created: i64, object: serde_json::Value) -> StripeEvent {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "type": event_type, "created": created, "livemode": false,
+            "data": { "object": object }
+        }))
+        .unwrap()
+    }
+
+    fn sub_event(id: &str, event_type: &str, created: i64, status: &str) -> StripeEvent {
+        event(
+            id,
+            event_type,
+            created,
+            serde_json::json!({
+                "id": "sub_1", "object": "subscription", "customer": "cus_1",
+                "status": status, "current_period_end": created + 2_592_000
+            }),
+        )
+    }
+
+    /// Checkout → subscription lifecycle as Stripe emits it (by `created`)
+    fn lifecycle() -> Vec<StripeEvent> {
+        vec![
+            sub_event("evt_created", "customer.subscription.created", 100, "active"),
+            event(
+                "evt_checkout",
+                "checkout.session.completed",
+                101,
+                serde_json::json!({
+                    "id": "cs_1", "object": "checkout.session", "mode": "subscription",
+                    "status": "complete", "customer": "cus_1", "subscription": "sub_1",
+                    "customer_email": "ada@example.com", "metadata": { "plan": "pro_monthly" }
+                }),
+            ),
+            event(
+                "evt_invoice",
+                "invoice.paid",
+                101,
+                serde_json::json!({
+                    "id": "in_1", "object": "invoice", "customer": "cus_1",
+                    "customer_email": "ada@example.com", "subscription": "sub_1",
+                    "amount_due": 900, "amount_paid": 900, "currency": "eur", "attempt_count": 1
+                }),
+            ),
+            sub_event("evt_past_due", "customer.subscription.updated", 200, "past_due"),
+            sub_event("evt_recovered", "customer.subscription.updated", 300, "active"),
+        ]
+    }
+
+    fn permutations(items: &[usize]) -> Vec<Vec<usize>> {
+        if items.len() <= 1 {
+            return vec![items.to_vec()];
+        }
+        let mut result = Vec::new();
+        for i in 0..items.len() {
+            let mut rest = items.to_vec();
+            let first = rest.remove(i);
+            for mut tail in permutations(&rest) {
+                tail.insert(0, first);
+                result.push(tail);
+            }
+        }
+        result
+    }
+
+    async fn deliver(state: &mut StripeWebhookState, events: &[StripeEvent], order: &[usize]) -> UserSubscription {
+        state.subscriptions = SubscriptionManager::new();
+        for &i in order {
+            dispatch_event(state, &events[i]).await.unwrap();
+        }
+        state.subscriptions.get_by_email("ada@example.com").await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn any_delivery_order_converges_on_deletion() {
+        let mut state = test_state("sk_test_mock");
+        let mut events = lifecycle();
+        events.push(sub_event("evt_deleted", "customer.subscription.deleted", 400, "canceled"));
+
+        for order in permutations(&(0..events.len()).collect::<Vec<_>>()) {
+            let sub = deliver(&mut state, &events, &order).await;
+            assert_eq!(sub.status, SubscriptionStatus::Canceled, "order {:?}", order);
+            assert_eq!(sub.last_event.unwrap().id, "evt_deleted", "order {:?}", order);
+        }
+    }
+
+    #[tokio::test]
+    async fn shuffled_redeliveries_converge_on_latest_update() {
+        use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+        let mut state = test_state("sk_test_mock");
+        let events = lifecycle();
+        // Every event delivered twice, as happens when Stripe retries
+        let mut order: Vec<usize> = (0..events.len()).chain(0..events.len()).collect();
+        let mut rng = StdRng::seed_from_u64(0x4121);
+
+        for _ in 0..200 {
+            order.shuffle(&mut rng);
+            let sub = deliver(&mut state, &events, &order).await;
+            assert_eq!(sub.status, SubscriptionStatus::Active, "order {:?}", order);
+            assert_eq!(sub.last_event.unwrap().id, "evt_recovered", "order {:?}", order);
+            assert_eq!(sub.current_period_end.unwrap().timestamp(), 300 + 2_592_000);
+        }
+    }
+
+    #[tokio::test]
+    async fn cancellation_wins_timestamp_tie() {
+        let mut state = test_state("sk_test_mock");
+        let mut events = lifecycle();
+        events.push(sub_event("evt_deleted", "customer.subscription.deleted", 300, "canceled"));
+
+        let sub = deliver(&mut state, &events, &[1, 5, 4]).await;
+        assert_eq!(sub.status, SubscriptionStatus::Canceled);
+    }
+
+    fn charge_refunded(id: &str, created: i64, amount_refunded: i64) -> StripeEvent {
+        refund_of(id, created, amount_refunded, Some("in_1"))
+    }
+
+    fn refund_of(id: &str, created: i64, amount_refunded: i64, invoice: Option<&str>) -> StripeEvent {
+        event(
+            id,
+            "charge.refunded",
+            created,
+            serde_json::json!({
+                "id": "ch_1", "object": "charge", "customer": "cus_1", "invoice": invoice, "amount": 900,
+                "amount_refunded": amount_refunded, "currency": "eur",
+                "refunded": amount_refunded == 900, "status": "succeeded"
+            }),
+        )
+    }
+
+    /// Stripe API serving invoices: `in_1` for sub_1, `in_other` for another subscription
+    async fn invoice_api(state: &mut StripeWebhookState) {
+        use axum::extract::Path;
+        use axum::routing::get;
+
+        let mock = Router::new().route(
+            "/v1/invoices/:id",
+            get(|Path(id): Path<String>| async move {
+                let subscription = if id == "in_1" { "sub_1" } else { "sub_other" };
+                Json(serde_json::json!({
+                    "id": id, "object": "invoice", "customer": "cus_1", "subscription": subscription,
+                    "amount_due": 900, "amount_paid": 900, "currency": "eur", "attempt_count": 1
+                }))
+            }),
+        );
+        let base = crate::test_support::spawn_mock(mock).await;
+        state.api = StripeApiClient::new("sk_test_mock".into(), base.clone(), base);
+    }
+
+    #[tokio::test]
+    async fn refund_policy_decides_revocation() {
+        let mut state = test_state("sk_test_mock");
+        invoice_api(&mut state).await;
+        let events = lifecycle();
+        let order: Vec<usize> = (0..events.len()).collect();
+
+        deliver(&mut state, &events, &order).await;
+        dispatch_event(&state, &charge_refunded("evt_partial", 400, 300)).await.unwrap();
+        let sub = state.subscriptions.get_by_email("ada@example.com").await.unwrap();
+        assert_eq!(sub.status, SubscriptionStatus::Active);
+
+        dispatch_event(&state, &charge_refunded("evt_full", 500, 900)).await.unwrap();
+        let sub = state.subscriptions.get_by_email("ada@example.com").await.unwrap();
+        assert_eq!(sub.status, SubscriptionStatus::Canceled);
+
+        // A late subscription update from before the refund does not restore access
+        dispatch_event(&state, &events[4]).await.unwrap();
+        let sub = state.subscriptions.get_by_email("ada@example.com").await.unwrap();
+        assert_eq!(sub.status, SubscriptionStatus::Canceled);
+
+        state.config.refund_policy = RefundPolicy::Keep;
+        deliver(&mut state, &events, &order).await;
+        dispatch_event(&state, &charge_refunded("evt_full", 500, 900)).await.unwrap();
+        let sub = state.subscriptions.get_by_email("ada@example.com").await.unwrap();
+        assert_eq!(sub.status, SubscriptionStatus::Active);
+    }
+
+    #[tokio::test]
+    async fn refunding_something_else_keeps_the_subscription() {
+        let mut state = test_state("sk_test_mock");
+        invoice_api(&mut state).await;
+        let events = lifecycle();
+        deliver(&mut state, &events, &(0..events.len()).collect::<Vec<_>>()).await;
+
+        // A one-off purchase (no invoice), then an invoice of another subscription
+        dispatch_event(&state, &refund_of("evt_oneoff", 500, 900, None)).await.unwrap();
+        dispatch_event(&state, &refund_of("evt_other", 501, 900, Some("in_other"))).await.unwrap();
+        let sub = state.subscriptions.get_by_email("ada@example.com").await.unwrap();
+        assert_eq!(sub.status, SubscriptionStatus::Active);
+    }
+
+    #[tokio::test]
+    async fn refund_ignores_receipt_email_of_unknown_customer() {
+        let mut state = test_state("sk_test_mock");
+        let events = lifecycle();
+        let order: Vec<usize> = (0..events.len()).collect();
+        deliver(&mut state, &events, &order).await;
+
+        for customer in [serde_json::json!("cus_stranger"), serde_json::Value::Null] {
+            let refund = event(
+                "evt_stranger",
+                "charge.refunded",
+                500,
+                serde_json::json!({
+                    "id": "ch_9", "object": "charge", "customer": customer, "amount": 900,
+                    "amount_refunded": 900, "currency": "eur", "refunded": true,
+                    "status": "succeeded", "receipt_email": "ada@example.com"
+                }),
+            );
+            dispatch_event(&state, &refund).await.unwrap();
+        }
+
+        let sub = state.subscriptions.get_by_email("ada@example.com").await.unwrap();
+        assert_eq!(sub.status, SubscriptionStatus::Active);
+    }
+
+    #[tokio::test]
+    async fn invoices_resolve_the_customer_not_their_current_email() {
+        let mut state = test_state("sk_test_mock");
+        deliver(&mut state, &lifecycle()[..2], &[0, 1]).await;
+
+        // The customer changed their email in the Billing Portal; invoices now carry the new one
+        let invoice = |id: &str, event_type: &str, paid: i64| {
+            event(
+                id,
+                event_type,
+                500,
+                serde_json::json!({
+                    "id": "in_2", "object": "invoice", "customer": "cus_1",
+                    "customer_email": "ada@new.example.com", "subscription": "sub_1",
+                    "amount_due": 900, "amount_paid": paid, "currency": "eur", "attempt_count": 1
+                }),
+            )
+        };
+        dispatch_event(&state, &invoice("evt_failed", "invoice.payment_failed", 0)).await.unwrap();
+        let sub = state.subscriptions.get_by_email("ada@example.com").await.unwrap();
+        assert_eq!(sub.status, SubscriptionStatus::PastDue);
+        assert!(state.dunning.get("ada@example.com").await.unwrap().is_open());
+        assert!(state.dunning.get("ada@new.example.com").await.is_none());
+
+        dispatch_event(&state, &invoice("evt_paid", "invoice.paid", 900)).await.unwrap();
+        let sub = state.subscriptions.get_by_email("ada@example.com").await.unwrap();
+        assert_eq!(sub.status, SubscriptionStatus::Active);
+    }
+
+    #[tokio::test]
+    async fn portal_only_opens_for_callers_own_customer() {
+        use axum::routing::post;
+
+        let mock = Router::new().route(
+            "/v1/billing_portal/sessions",
+            post(|body: String| async move {
+                assert!(body.contains("customer=cus_1"));
+                Json(serde_json::json!({ "id": "bps_1", "url": "https://billing.stripe.com/p/session_1" }))
+            }),
+        );
+        let mut state = test_state("sk_test_mock");
+        let base = crate::test_support::spawn_mock(mock).await;
+        state.api = StripeApiClient::new("sk_test_mock".into(), base.clone(), base);
+        let owner = state
+            .subscriptions
+            .activate_subscription(
+                None,
+                "ada@example.com",
+                Some("cus_1".into()),
+                Some("sub_1".into()),
+                "pro_monthly",
+                EventStamp { id: "evt_checkout".into(), created: 1 },
+            )
+            .await
+            .user_id;
+        let state = Arc::new(state);
+        let portal = |user_id, customer_id: Option<&str>| {
+            let request = PortalSessionRequest { customer_id: customer_id.map(String::from) };
+            create_portal_session(State(state.clone()), AuthUser { user_id }, Some(Json(request)))
+        };
+
+        let stranger = portal(Uuid::new_v4(), None).await.into_response();
+        assert_eq!(stranger.status(), StatusCode::NOT_FOUND);
+
+        let foreign = portal(owner, Some("cus_2")).await.into_response();
+        assert_eq!(foreign.status(), StatusCode::FORBIDDEN);
+
+        for requested in [None, Some("cus_1")] {
+            let own = portal(owner, requested).await.into_response();
+            assert_eq!(own.status(), StatusCode::OK);
+        }
+    }
+
+    #[tokio::test]
+    async fn admin_refund_forwards_idempotency_key() {
+        use axum::routing::post;
+
+        let mock = Router::new().route(
+            "/v1/refunds",
+            post(|headers: HeaderMap, body: String| async move {
+                assert_eq!(headers["idempotency-key"], "refund-42");
+                assert!(body.contains("charge=ch_1") && body.contains("amount=300"));
+                Json(serde_json::json!({
+                    "id": "re_1", "object": "refund", "charge": "ch_1",
+                    "amount": 300, "currency": "eur", "status": "succeeded"
+                }))
+            }),
+        );
+        let mut state = test_state("sk_test_mock");
+        let base = crate::test_support::spawn_mock(mock).await;
+        state.api = StripeApiClient::new("sk_test_mock".into(), base.clone(), base);
+        let state = Arc::new(state);
+        let request = || RefundRequest {
+            charge_id: Some("ch_1".into()),
+            payment_intent_id: None,
+            amount: Some(300),
+            reason: None,
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer admin_test_token".parse().unwrap());
+
+        let resp = create_refund(State(state.clone()), headers.clone(), Json(request()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        headers.insert("idempotency-key", "refund-42".parse().unwrap());
+        let resp = create_refund(State(state), headers, Json(request())).await.into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    fn trial_event(id: &str, event_type: &str, created: i64, status: &str, card: bool) -> StripeEvent {
+        event(
+            id,
+            event_type,
+            created,
+            serde_json::json!({
+                "id": "sub_1", "object": "subscription", "customer": "cus_1", "status": status,
+                "trial_end": 1_000, "default_payment_method": if card { Some("pm_1") } else { None },
+                "trial_settings": { "end_behavior": { "missing_payment_method": "cancel" } }
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn card_less_trial_expires_at_deadline_unless_converted() {
+        let mut state = test_state("sk_test_mock");
+        let mut events = lifecycle();
+        events[0] = trial_event("evt_created", "customer.subscription.created", 100, "trialing", false);
+        events.truncate(2);
+        events.push(trial_event("evt_will_end", "customer.subscription.trial_will_end", 700, "trialing", false));
+
+        // Checkout before or after the subscription snapshot: both end up trialing
+        for order in [[0, 1, 2], [1, 0, 2]] {
+            let sub = deliver(&mut state, &events, &order).await;
+            assert_eq!(sub.status, SubscriptionStatus::Trialing, "order {:?}", order);
+            assert_eq!(sub.trial_end.unwrap().timestamp(), 1_000);
+            assert!(!sub.payment_method_on_file);
+            assert!(sub.is_entitled(at(999)));
+            assert_eq!(sub.trial_days_left(at(999)), Some(0));
+            // Stripe's cancellation may lag; access ends at trial_end regardless
+            assert!(!sub.is_entitled(at(1_000)));
+        }
+
+        let converted = trial_event("evt_converted", "customer.subscription.updated", 1_000, "active", true);
+        dispatch_event(&state, &converted).await.unwrap();
+        let sub = state.subscriptions.get_by_email("ada@example.com").await.unwrap();
+        assert!(sub.is_entitled(at(5_000)));
+        assert!(sub.payment_method_on_file);
+    }
+
+    #[tokio::test]
+    async fn reinstatement_restores_the_status_behind_the_suspension() {
+        let mut state = test_state("sk_test_mock");
+        let mut events = lifecycle();
+        events[0] = trial_event("evt_created", "customer.subscription.created", 100, "trialing", false);
+        events.truncate(2);
+        deliver(&mut state, &events, &[0, 1]).await;
+
+        // A suspended trial comes back as a trial, still bounded by trial_end
+        assert!(state.subscriptions.suspend_subscription("ada@example.com").await);
+        assert!(state.subscriptions.reinstate_subscription("ada@example.com").await);
+        let sub = state.subscriptions.get_by_email("ada@example.com").await.unwrap();
+        assert_eq!(sub.status, SubscriptionStatus::Trialing);
+        assert!(!sub.is_entitled(at(1_000)));
+        assert_eq!(state.subscriptions.expire_trials(at(1_001)).await, vec!["ada@example.com".to_string()]);
+
+        // What Stripe reports during the suspension is what it comes back as
+        let mut state = test_state("sk_test_mock");
+        deliver(&mut state, &lifecycle()[..2], &[0, 1]).await;
+        assert!(state.subscriptions.suspend_subscription("ada@example.com").await);
+        dispatch_event(&state, &sub_event("evt_past_due", "customer.subscription.updated", 200, "past_due"))
+            .await
+            .unwrap();
+        let status = |sub: Option<UserSubscription>| sub.unwrap().status;
+        assert_eq!(status(state.subscriptions.get_by_email("ada@example.com").await), SubscriptionStatus::Suspended);
+        assert!(state.subscriptions.reinstate_subscription("ada@example.com").await);
+        assert_eq!(status(state.subscriptions.get_by_email("ada@example.com").await), SubscriptionStatus::PastDue);
+    }
+
+    #[tokio::test]
+    async fn card_less_checkout_requires_trial_and_skips_card_collection() {
+        use axum::routing::post;
+
+        let mock = Router::new().route(
+            "/v1/checkout/sessions",
+            post(|body: String| async move {
+                assert!(body.contains("payment_method_collection=if_required"));
+                assert!(body.contains("subscription_data%5Btrial_period_days%5D=14"));
+                assert!(body.contains("%5Bmissing_payment_method%5D=cancel"));
+                Json(serde_json::json!({ "id": "cs_1", "url": "https://checkout.stripe.com/c/cs_1" }))
+            }),
+        );
+        let mut state = test_state("sk_test_mock");
+        let base = crate::test_support::spawn_mock(mock).await;
+        state.api = StripeApiClient::new("sk_test_mock".into(), base.clone(), base);
+        state.config.checkout_success_url = Some("https://app.example/ok".into());
+        state.config.checkout_cancel_url = Some("https://app.example/cancel".into());
+        state.config.catalog = PlanCatalog {
+            entries: HashMap::from([(
+                "pro_monthly".to_string(),
+                CatalogEntry {
+                    price_id: "price_1".into(),
+                    mode: CheckoutMode::Subscription,
+                },
+            )]),
+        };
+        let state = Arc::new(state);
+        let user = || AuthUser { user_id: Uuid::new_v4() };
+        let request = |trial_days| CheckoutRequest {
+            user_id: None,
+            plan: "pro_monthly".into(),
+            customer_email: None,
+            trial_days,
+            require_payment_method: false,
+            allow_promotion_codes: false,
+        };
+
+        let resp = create_checkout_session(State(state.clone()), user(), Json(request(None)))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = create_checkout_session(State(state), user(), Json(request(Some(14))))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn live_config_refuses_placeholders() {
+        let mut config = test_state("rk_live_abc").config;
+        config.publishable_key = "pk_live_abc".to_string();
+        assert!(config.validate().is_ok());
+
+        config.webhook_secrets.push(secret("#1", "whsec_placeholder"));
+        assert!(config.validate().is_err());
+
+        config.secret_key = "sk_test_placeholder".to_string();
+        assert!(config.validate().is_ok());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_signed_payload_verifies(
+            payload in proptest::collection::vec(any::<u8>(), 0..512),
+            key in "whsec_[a-zA-Z0-9]{1,40}",
+            ts in 0i64..4_000_000_000,
+            skew in -300i64..=300,
+        ) {
+            let header = format!("t={},v1={}", ts, sign(&payload, &key, ts));
+            let secrets = [secret("#0", &key)];
+            prop_assert!(verify_webhook_signature(&payload, &header, &secrets, TOLERANCE, at(ts + skew)).is_ok());
+        }
+
+        #[test]
+        fn prop_tampered_payload_rejected(
+            payload in proptest::collection::vec(any::<u8>(), 1..512),
+            index in any::<prop::sample::Index>(),
+            flip in 1u8..=255,
+        ) {
+            let ts = 1_700_000_000;
+            let header = format!("t={},v1={}", ts, sign(&payload, "whsec_a", ts));
+            let mut tampered = payload.clone();
+            tampered[index.index(payload.len())] ^= flip;
+
+            let secrets = [secret("#0", "whsec_a")];
+            prop_assert!(verify_webhook_signature(&tampered, &header, &secrets, TOLERANCE, at(ts)).is_err());
+        }
+
+        #[test]
+        fn prop_parser_keeps_every_v1_in_order(
+            ts in "[0-9]{1,12}",
+            sigs in proptest::collection::vec("[0-9a-f]{64}", 1..6),
+            noise in proptest::collection::vec("(v0|v2|x)=[a-z0-9]{0,8}", 0..4),
+        ) {
+            let mut parts: Vec<String> = vec![format!("t={}", ts)];
+            parts.extend(sigs.iter().map(|s| format!("v1={}", s)));
+            parts.extend(noise);
+            let header = parts.join(",");
+
+            let parsed = parse_signature_header(&header).unwrap();
+            prop_assert_eq!(parsed.timestamp, ts.as_str());
+            prop_assert_eq!(parsed.v1_signatures, sigs.iter().map(String::as_str).collect::<Vec<_>>());
+        }
+
+        // Fuzz-style: arbitrary headers and bodies must be rejected cleanly, never panic
+        #[test]
+        fn fuzz_header_parser_never_panics(header in any::<String>(), payload in any::<Vec<u8>>()) {
+            let _ = parse_signature_header(&header);
+            let secrets = [secret("#0", "whsec_a")];
+            prop_assert!(verify_webhook_signature(&payload, &header, &secrets, TOLERANCE, at(0)).is_err());
+        }
+
+        #[test]
+        fn fuzz_structured_headers_never_panic(
+            entries in proptest::collection::vec("[tv0-9=,a-f -]{0,80}", 0..8),
+        ) {
+            let header = entries.join(",");
+            let _ = parse_signature_header(&header);
+            let secrets = [secret("#0", "whsec_a")];
+            let _ = verify_webhook_signature(b"{}", &header, &secrets, TOLERANCE, at(0));
+        }
+    }
 }
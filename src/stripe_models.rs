@@ -0,0 +1,351 @@
+// lwas_economy/src/payments/stripe_models.rs
+// ARCHITECT: QANTUM AETERNA | STATUS: BETA
+// Typed Stripe API objects & event dispatch
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::stripe_handler::StripeEvent;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// STRIPE OBJECTS
+// ═══════════════════════════════════════════════════════════════════════════════
+// Only the fields we act on. Fields Stripe documents as nullable are `Option`;
+// everything else is required so a malformed payload fails to parse.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckoutSession {
+    pub id: String,
+    pub client_reference_id: Option<String>,
+    pub customer: Option<String>,
+    pub customer_email: Option<String>,
+    pub customer_details: Option<CustomerDetails>,
+    pub subscription: Option<String>,
+    pub payment_intent: Option<String>,
+    pub mode: String,
+    pub amount_total: Option<i64>,
+    pub currency: Option<String>,
+    pub status: String,
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomerDetails {
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Invoice {
+    pub id: String,
+    pub customer: String,
+    pub customer_email: Option<String>,
+    pub subscription: Option<String>,
+    pub amount_due: i64,
+    pub amount_paid: i64,
+    pub currency: String,
+    pub status: Option<String>,
+    pub attempt_count: u32,
+    pub next_payment_attempt: Option<i64>,
+    pub billing_reason: Option<String>,
+    pub hosted_invoice_url: Option<String>,
+    /// Customer-facing number (e.g. `A1B2C3-0001`), set once finalized
+    pub number: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subscription {
+    pub id: String,
+    pub customer: String,
+    pub status: String,
+    pub current_period_end: Option<i64>,
+    #[serde(default)]
+    pub cancel_at_period_end: bool,
+    pub trial_end: Option<i64>,
+    pub trial_settings: Option<TrialSettings>,
+    pub default_payment_method: Option<String>,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+    pub items: Option<SubscriptionItems>,
+}
+
+impl Subscription {
+    /// Price of the first item; our plans are single-item subscriptions
+    pub fn price_id(&self) -> Option<&str> {
+        self.items.as_ref()?.data.first().map(|i| i.price.id.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrialSettings {
+    pub end_behavior: TrialEndBehavior,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrialEndBehavior {
+    /// `create_invoice`, `pause` or `cancel`
+    pub missing_payment_method: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionItems {
+    pub data: Vec<SubscriptionItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionItem {
+    pub id: String,
+    pub price: Price,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Price {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Charge {
+    pub id: String,
+    pub customer: Option<String>,
+    pub invoice: Option<String>,
+    pub payment_intent: Option<String>,
+    pub receipt_email: Option<String>,
+    pub amount: i64,
+    pub amount_refunded: i64,
+    pub currency: String,
+    pub refunded: bool,
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Refund {
+    pub id: String,
+    pub charge: Option<String>,
+    pub payment_intent: Option<String>,
+    pub amount: i64,
+    pub currency: String,
+    pub status: Option<String>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dispute {
+    pub id: String,
+    pub charge: String,
+    pub payment_intent: Option<String>,
+    pub amount: i64,
+    pub currency: String,
+    pub reason: String,
+    pub status: String,
+    pub evidence_details: Option<EvidenceDetails>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvidenceDetails {
+    pub due_by: Option<i64>,
+}
+
+/// Radar early fraud warning: the issuer reported the charge as fraudulent, usually ahead of a dispute
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EarlyFraudWarning {
+    pub id: String,
+    pub charge: String,
+    pub payment_intent: Option<String>,
+    pub fraud_type: String,
+    /// Whether the charge can still be refunded to head off a dispute
+    pub actionable: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentIntent {
+    pub id: String,
+    pub customer: Option<String>,
+    pub amount: i64,
+    pub currency: String,
+    pub status: String,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Customer {
+    pub id: String,
+    pub email: Option<String>,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub preferred_locales: Vec<String>,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+/// Saved card, as carried by `customer.source.expiring`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Card {
+    pub id: String,
+    pub customer: Option<String>,
+    pub brand: Option<String>,
+    pub last4: String,
+    pub exp_month: u32,
+    pub exp_year: i32,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// TYPED EVENT DISPATCH
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug, Clone)]
+pub enum StripeEventKind {
+    CheckoutSessionCompleted(CheckoutSession),
+    InvoicePaid(Invoice),
+    InvoicePaymentFailed(Invoice),
+    SubscriptionCreated(Subscription),
+    SubscriptionUpdated(Subscription),
+    SubscriptionDeleted(Subscription),
+    SubscriptionTrialWillEnd(Subscription),
+    ChargeSucceeded(Charge),
+    ChargeRefunded(Charge),
+    RefundUpdated(Refund),
+    DisputeCreated(Dispute),
+    DisputeUpdated(Dispute),
+    DisputeClosed(Dispute),
+    EarlyFraudWarningCreated(EarlyFraudWarning),
+    PaymentIntentSucceeded(PaymentIntent),
+    PaymentIntentFailed(PaymentIntent),
+    CustomerCreated(Customer),
+    CustomerUpdated(Customer),
+    SourceExpiring(Card),
+    /// Event types we don't model; carries the type for logging
+    Unknown(String),
+}
+
+fn parse<T: serde::de::DeserializeOwned>(event: &StripeEvent, object: &str) -> Result<T, String> {
+    serde_json::from_value(event.data.object.clone())
+        .map_err(|e| format!("Failed to parse {} in {} ({}): {}", object, event.event_type, event.id, e))
+}
+
+impl StripeEventKind {
+    /// Parse `data.object` according to the event type
+    pub fn from_event(event: &StripeEvent) -> Result<Self, String> {
+        use StripeEventKind::*;
+
+        Ok(match event.event_type.as_str() {
+            "checkout.session.completed" => CheckoutSessionCompleted(parse(event, "checkout session")?),
+            "invoice.paid" => InvoicePaid(parse(event, "invoice")?),
+            "invoice.payment_failed" => InvoicePaymentFailed(parse(event, "invoice")?),
+            "customer.subscription.created" => SubscriptionCreated(parse(event, "subscription")?),
+            "customer.subscription.updated" => SubscriptionUpdated(parse(event, "subscription")?),
+            "customer.subscription.deleted" => SubscriptionDeleted(parse(event, "subscription")?),
+            "customer.subscription.trial_will_end" => SubscriptionTrialWillEnd(parse(event, "subscription")?),
+            "charge.succeeded" => ChargeSucceeded(parse(event, "charge")?),
+            "charge.refunded" => ChargeRefunded(parse(event, "charge")?),
+            "refund.updated" => RefundUpdated(parse(event, "refund")?),
+            "charge.dispute.created" => DisputeCreated(parse(event, "dispute")?),
+            "charge.dispute.updated" => DisputeUpdated(parse(event, "dispute")?),
+            "charge.dispute.closed" => DisputeClosed(parse(event, "dispute")?),
+            "radar.early_fraud_warning.created" => EarlyFraudWarningCreated(parse(event, "early fraud warning")?),
+            "payment_intent.succeeded" => PaymentIntentSucceeded(parse(event, "payment intent")?),
+            "payment_intent.payment_failed" => PaymentIntentFailed(parse(event, "payment intent")?),
+            "customer.created" => CustomerCreated(parse(event, "customer")?),
+            "customer.updated" => CustomerUpdated(parse(event, "customer")?),
+            "customer.source.expiring" => SourceExpiring(parse(event, "card")?),
+            other => Unknown(other.to_string()),
+        })
+    }
+
+    /// Id of the Stripe object the event carries
+    pub fn object_id(&self) -> Option<&str> {
+        use StripeEventKind::*;
+
+        match self {
+            CheckoutSessionCompleted(s) => Some(&s.id),
+            InvoicePaid(i) | InvoicePaymentFailed(i) => Some(&i.id),
+            SubscriptionCreated(s) | SubscriptionUpdated(s) | SubscriptionDeleted(s) | SubscriptionTrialWillEnd(s) => {
+                Some(&s.id)
+            }
+            ChargeSucceeded(c) | ChargeRefunded(c) => Some(&c.id),
+            RefundUpdated(r) => Some(&r.id),
+            DisputeCreated(d) | DisputeUpdated(d) | DisputeClosed(d) => Some(&d.id),
+            EarlyFraudWarningCreated(w) => Some(&w.id),
+            PaymentIntentSucceeded(p) | PaymentIntentFailed(p) => Some(&p.id),
+            CustomerCreated(c) | CustomerUpdated(c) => Some(&c.id),
+            SourceExpiring(c) => Some(&c.id),
+            Unknown(_) => None,
+        }
+    }
+
+    /// Stripe customer the event concerns, when the object names one
+    pub fn customer_id(&self) -> Option<&str> {
+        use StripeEventKind::*;
+
+        match self {
+            CheckoutSessionCompleted(s) => s.customer.as_deref(),
+            InvoicePaid(i) | InvoicePaymentFailed(i) => Some(&i.customer),
+            SubscriptionCreated(s) | SubscriptionUpdated(s) | SubscriptionDeleted(s) | SubscriptionTrialWillEnd(s) => {
+                Some(&s.customer)
+            }
+            ChargeSucceeded(c) | ChargeRefunded(c) => c.customer.as_deref(),
+            PaymentIntentSucceeded(p) | PaymentIntentFailed(p) => p.customer.as_deref(),
+            CustomerCreated(c) | CustomerUpdated(c) => Some(&c.id),
+            SourceExpiring(c) => c.customer.as_deref(),
+            RefundUpdated(_)
+            | DisputeCreated(_)
+            | DisputeUpdated(_)
+            | DisputeClosed(_)
+            | EarlyFraudWarningCreated(_)
+            | Unknown(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event_type: &str, object: serde_json::Value) -> StripeEvent {
+        serde_json::from_value(serde_json::json!({
+            "id": "evt_1", "type": event_type, "created": 1_700_000_000, "livemode": false,
+            "data": { "object": object }
+        }))
+        .unwrap()
+    }
+
+    fn invoice() -> serde_json::Value {
+        serde_json::json!({
+            "id": "in_1", "customer": "cus_1", "customer_email": "ada@example.com", "subscription": "sub_1",
+            "amount_due": 900, "amount_paid": 900, "currency": "usd", "status": "paid", "attempt_count": 1,
+            "next_payment_attempt": null, "billing_reason": "subscription_cycle", "hosted_invoice_url": null,
+            "number": "A1B2C3-0001"
+        })
+    }
+
+    #[test]
+    fn known_types_parse_into_their_object() {
+        let kind = StripeEventKind::from_event(&event("invoice.paid", invoice())).unwrap();
+        assert!(matches!(&kind, StripeEventKind::InvoicePaid(i) if i.amount_paid == 900));
+        assert_eq!(kind.object_id(), Some("in_1"));
+        assert_eq!(kind.customer_id(), Some("cus_1"));
+    }
+
+    #[test]
+    fn missing_required_field_is_a_parse_error() {
+        let mut object = invoice();
+        object.as_object_mut().unwrap().remove("customer");
+
+        let err = StripeEventKind::from_event(&event("invoice.paid", object)).unwrap_err();
+        assert!(err.contains("invoice in invoice.paid (evt_1)"), "{}", err);
+        assert!(err.contains("missing field `customer`"), "{}", err);
+
+        // A wrongly typed field too
+        let mut object = invoice();
+        object["amount_paid"] = serde_json::json!("900");
+        assert!(StripeEventKind::from_event(&event("invoice.payment_failed", object)).is_err());
+    }
+
+    #[test]
+    fn unmodelled_types_become_unknown() {
+        // Whatever the object looks like: it is not parsed
+        let kind = StripeEventKind::from_event(&event("payout.paid", serde_json::json!({ "id": "po_1" }))).unwrap();
+        assert!(matches!(&kind, StripeEventKind::Unknown(t) if t == "payout.paid"));
+        assert_eq!(kind.object_id(), None);
+        assert_eq!(kind.customer_id(), None);
+    }
+}
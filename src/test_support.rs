@@ -0,0 +1,46 @@
+// lwas_economy/src/payments/test_support.rs
+// ARCHITECT: QANTUM AETERNA | STATUS: BETA
+// Shared helpers for tests
+
+use axum::{routing::post, Json, Router};
+use tokio::sync::mpsc;
+
+use crate::auth::{AuthConfig, Authenticator};
+
+/// Serve `router` on an ephemeral local port (e.g. as a mock Stripe API) and return its base URL
+pub async fn spawn_mock(router: Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, router).await.unwrap();
+    });
+    format!("http://{}", addr)
+}
+
+/// Bearer token `authenticator()` accepts on admin endpoints
+pub const ADMIN_TOKEN: &str = "admin_test_token";
+
+/// Authenticator configured in code rather than from the environment, which tests share
+pub fn authenticator() -> Authenticator {
+    Authenticator::new(AuthConfig {
+        admin_token: Some(ADMIN_TOKEN.to_string()),
+        ..Default::default()
+    })
+}
+
+/// A mock chat webhook: its URL, and the JSON bodies posted to it
+pub async fn spawn_chat_hook() -> (String, mpsc::UnboundedReceiver<serde_json::Value>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let url = spawn_mock(Router::new().route(
+        "/hook",
+        post(move |Json(body): Json<serde_json::Value>| {
+            let tx = tx.clone();
+            async move {
+                let _ = tx.send(body);
+                "ok"
+            }
+        }),
+    ))
+    .await;
+    (format!("{}/hook", url), rx)
+}